@@ -8,6 +8,8 @@
 #include <stdint.h>
 #include <stdlib.h>
 
+#define WGPUCOPY_BUFFER_ALIGNMENT 4
+
 #define WGPUMAX_BIND_GROUPS 4
 
 #define WGPUMAX_COLOR_TARGETS 4
@@ -60,6 +62,7 @@ typedef enum {
   WGPUBufferMapAsyncStatus_Error,
   WGPUBufferMapAsyncStatus_Unknown,
   WGPUBufferMapAsyncStatus_ContextLost,
+  WGPUBufferMapAsyncStatus_Timeout,
 } WGPUBufferMapAsyncStatus;
 
 typedef enum {
@@ -113,6 +116,8 @@ typedef enum {
 typedef enum {
   WGPUPresentMode_NoVsync = 0,
   WGPUPresentMode_Vsync = 1,
+  WGPUPresentMode_Mailbox = 2,
+  WGPUPresentMode_Relaxed = 3,
 } WGPUPresentMode;
 
 typedef enum {
@@ -123,6 +128,12 @@ typedef enum {
   WGPUPrimitiveTopology_TriangleStrip = 4,
 } WGPUPrimitiveTopology;
 
+typedef enum {
+  WGPUQueueType_General = 0,
+  WGPUQueueType_Compute = 1,
+  WGPUQueueType_Transfer = 2,
+} WGPUQueueType;
+
 typedef enum {
   WGPUStencilOperation_Keep = 0,
   WGPUStencilOperation_Zero = 1,
@@ -134,11 +145,25 @@ typedef enum {
   WGPUStencilOperation_DecrementWrap = 7,
 } WGPUStencilOperation;
 
+typedef enum {
+  WGPUStorageTextureAccess_ReadOnly = 0,
+  WGPUStorageTextureAccess_WriteOnly = 1,
+  WGPUStorageTextureAccess_ReadWrite = 2,
+} WGPUStorageTextureAccess;
+
 typedef enum {
   WGPUStoreOp_Clear = 0,
   WGPUStoreOp_Store = 1,
 } WGPUStoreOp;
 
+typedef enum {
+  WGPUSwapChainStatus_Success = 0,
+  WGPUSwapChainStatus_Suboptimal = 1,
+  WGPUSwapChainStatus_Timeout = 2,
+  WGPUSwapChainStatus_Outdated = 3,
+  WGPUSwapChainStatus_Lost = 4,
+} WGPUSwapChainStatus;
+
 typedef enum {
   WGPUTextureAspect_All,
   WGPUTextureAspect_StencilOnly,
@@ -196,6 +221,58 @@ typedef enum {
   WGPUTextureFormat_Depth32Float = 41,
   WGPUTextureFormat_Depth24Plus = 42,
   WGPUTextureFormat_Depth24PlusStencil8 = 43,
+  WGPUTextureFormat_Bc1RgbaUnorm = 44,
+  WGPUTextureFormat_Bc1RgbaUnormSrgb = 45,
+  WGPUTextureFormat_Bc2RgbaUnorm = 46,
+  WGPUTextureFormat_Bc2RgbaUnormSrgb = 47,
+  WGPUTextureFormat_Bc3RgbaUnorm = 48,
+  WGPUTextureFormat_Bc3RgbaUnormSrgb = 49,
+  WGPUTextureFormat_Bc4RUnorm = 50,
+  WGPUTextureFormat_Bc4RSnorm = 51,
+  WGPUTextureFormat_Bc5RgUnorm = 52,
+  WGPUTextureFormat_Bc5RgSnorm = 53,
+  WGPUTextureFormat_Bc6hRgbUfloat = 54,
+  WGPUTextureFormat_Bc6hRgbSfloat = 55,
+  WGPUTextureFormat_Bc7RgbaUnorm = 56,
+  WGPUTextureFormat_Bc7RgbaUnormSrgb = 57,
+  WGPUTextureFormat_Etc2Rgb8Unorm = 58,
+  WGPUTextureFormat_Etc2Rgb8UnormSrgb = 59,
+  WGPUTextureFormat_Etc2Rgb8A1Unorm = 60,
+  WGPUTextureFormat_Etc2Rgb8A1UnormSrgb = 61,
+  WGPUTextureFormat_Etc2Rgba8Unorm = 62,
+  WGPUTextureFormat_Etc2Rgba8UnormSrgb = 63,
+  WGPUTextureFormat_EacR11Unorm = 64,
+  WGPUTextureFormat_EacR11Snorm = 65,
+  WGPUTextureFormat_EacRg11Unorm = 66,
+  WGPUTextureFormat_EacRg11Snorm = 67,
+  WGPUTextureFormat_Astc4x4RgbaUnorm = 68,
+  WGPUTextureFormat_Astc4x4RgbaUnormSrgb = 69,
+  WGPUTextureFormat_Astc5x4RgbaUnorm = 70,
+  WGPUTextureFormat_Astc5x4RgbaUnormSrgb = 71,
+  WGPUTextureFormat_Astc5x5RgbaUnorm = 72,
+  WGPUTextureFormat_Astc5x5RgbaUnormSrgb = 73,
+  WGPUTextureFormat_Astc6x5RgbaUnorm = 74,
+  WGPUTextureFormat_Astc6x5RgbaUnormSrgb = 75,
+  WGPUTextureFormat_Astc6x6RgbaUnorm = 76,
+  WGPUTextureFormat_Astc6x6RgbaUnormSrgb = 77,
+  WGPUTextureFormat_Astc8x5RgbaUnorm = 78,
+  WGPUTextureFormat_Astc8x5RgbaUnormSrgb = 79,
+  WGPUTextureFormat_Astc8x6RgbaUnorm = 80,
+  WGPUTextureFormat_Astc8x6RgbaUnormSrgb = 81,
+  WGPUTextureFormat_Astc8x8RgbaUnorm = 82,
+  WGPUTextureFormat_Astc8x8RgbaUnormSrgb = 83,
+  WGPUTextureFormat_Astc10x5RgbaUnorm = 84,
+  WGPUTextureFormat_Astc10x5RgbaUnormSrgb = 85,
+  WGPUTextureFormat_Astc10x6RgbaUnorm = 86,
+  WGPUTextureFormat_Astc10x6RgbaUnormSrgb = 87,
+  WGPUTextureFormat_Astc10x8RgbaUnorm = 88,
+  WGPUTextureFormat_Astc10x8RgbaUnormSrgb = 89,
+  WGPUTextureFormat_Astc10x10RgbaUnorm = 90,
+  WGPUTextureFormat_Astc10x10RgbaUnormSrgb = 91,
+  WGPUTextureFormat_Astc12x10RgbaUnorm = 92,
+  WGPUTextureFormat_Astc12x10RgbaUnormSrgb = 93,
+  WGPUTextureFormat_Astc12x12RgbaUnorm = 94,
+  WGPUTextureFormat_Astc12x12RgbaUnormSrgb = 95,
 } WGPUTextureFormat;
 
 typedef enum {
@@ -207,6 +284,16 @@ typedef enum {
   WGPUTextureViewDimension_D3,
 } WGPUTextureViewDimension;
 
+/**
+ * Severity of a message reported by the backend validation.
+ */
+typedef enum {
+  WGPUValidationMessageLevel_Error = 0,
+  WGPUValidationMessageLevel_Warning = 1,
+  WGPUValidationMessageLevel_Info = 2,
+  WGPUValidationMessageLevel_Verbose = 3,
+} WGPUValidationMessageLevel;
+
 typedef enum {
   WGPUVertexFormat_Uchar2 = 1,
   WGPUVertexFormat_Uchar4 = 3,
@@ -250,6 +337,10 @@ typedef WGPUId_Adapter_Dummy WGPUAdapterId;
 
 typedef struct {
   bool anisotropic_filtering;
+  bool descriptor_indexing;
+  bool texture_compression_bc;
+  bool texture_compression_etc2;
+  bool texture_compression_astc;
 } WGPUExtensions;
 
 typedef struct {
@@ -259,6 +350,9 @@ typedef struct {
 typedef struct {
   WGPUExtensions extensions;
   WGPULimits limits;
+  uint32_t compute_queue_count;
+  uint32_t transfer_queue_count;
+  bool background_maintenance;
 } WGPUDeviceDescriptor;
 
 typedef uint64_t WGPUId_BindGroup_Dummy;
@@ -359,6 +453,12 @@ typedef struct {
   WGPUOrigin3d origin;
 } WGPUTextureCopyView;
 
+typedef struct {
+  WGPUBufferAddress offset;
+  uint32_t row_pitch;
+  uint32_t image_height;
+} WGPUTextureDataLayout;
+
 typedef struct {
   uint32_t width;
   uint32_t height;
@@ -397,6 +497,9 @@ typedef enum {
   WGPUBindingResource_Buffer,
   WGPUBindingResource_Sampler,
   WGPUBindingResource_TextureView,
+  WGPUBindingResource_BufferArray,
+  WGPUBindingResource_SamplerArray,
+  WGPUBindingResource_TextureViewArray,
 } WGPUBindingResource_Tag;
 
 typedef struct {
@@ -411,12 +514,30 @@ typedef struct {
   WGPUTextureViewId _0;
 } WGPUBindingResource_WGPUTextureView_Body;
 
+typedef struct {
+  const WGPUBufferBinding *_0;
+  uintptr_t _1;
+} WGPUBindingResource_WGPUBufferArray_Body;
+
+typedef struct {
+  const WGPUSamplerId *_0;
+  uintptr_t _1;
+} WGPUBindingResource_WGPUSamplerArray_Body;
+
+typedef struct {
+  const WGPUTextureViewId *_0;
+  uintptr_t _1;
+} WGPUBindingResource_WGPUTextureViewArray_Body;
+
 typedef struct {
   WGPUBindingResource_Tag tag;
   union {
     WGPUBindingResource_WGPUBuffer_Body buffer;
     WGPUBindingResource_WGPUSampler_Body sampler;
     WGPUBindingResource_WGPUTextureView_Body texture_view;
+    WGPUBindingResource_WGPUBufferArray_Body buffer_array;
+    WGPUBindingResource_WGPUSamplerArray_Body sampler_array;
+    WGPUBindingResource_WGPUTextureViewArray_Body texture_view_array;
   };
 } WGPUBindingResource;
 
@@ -444,6 +565,11 @@ typedef struct {
   WGPUTextureViewDimension texture_dimension;
   bool multisampled;
   bool dynamic;
+  WGPUTextureFormat storage_texture_format;
+  WGPUStorageTextureAccess storage_texture_access;
+  uint32_t count;
+  const WGPUSamplerId *immutable_samplers;
+  uintptr_t immutable_samplers_length;
 } WGPUBindGroupLayoutBinding;
 
 typedef struct {
@@ -613,6 +739,7 @@ typedef uint32_t WGPUTextureUsage;
 #define WGPUTextureUsage_SAMPLED 4
 #define WGPUTextureUsage_STORAGE 8
 #define WGPUTextureUsage_OUTPUT_ATTACHMENT 16
+#define WGPUTextureUsage_STORAGE_READ 32
 #define WGPUTextureUsage_NONE 0
 #define WGPUTextureUsage_UNINITIALIZED 65535
 
@@ -622,8 +749,12 @@ typedef struct {
   uint32_t width;
   uint32_t height;
   WGPUPresentMode present_mode;
+  uint32_t image_count;
+  uint32_t max_frame_latency;
 } WGPUSwapChainDescriptor;
 
+typedef void (*WGPUSwapChainFrameCallback)(const uint8_t *data, uintptr_t size, uint32_t bytes_per_row, uint8_t *userdata);
+
 typedef struct {
   WGPUExtent3d size;
   uint32_t array_layer_count;
@@ -634,24 +765,94 @@ typedef struct {
   WGPUTextureUsage usage;
 } WGPUTextureDescriptor;
 
-typedef WGPUDeviceId WGPUQueueId;
+typedef uint32_t WGPUTextureFormatFeatureFlags;
+#define WGPUTextureFormatFeatureFlags_FILTERABLE 1
+#define WGPUTextureFormatFeatureFlags_BLENDABLE 2
+#define WGPUTextureFormatFeatureFlags_STORAGE_ATOMIC 4
+#define WGPUTextureFormatFeatureFlags_COLOR_ATTACHMENT 8
+#define WGPUTextureFormatFeatureFlags_DEPTH_STENCIL_ATTACHMENT 16
+
+typedef struct {
+  WGPUTextureUsage allowed_usages;
+  WGPUTextureFormatFeatureFlags flags;
+  uint32_t sample_counts;
+} WGPUTextureFormatFeatures;
+
+typedef uint64_t WGPUId_Queue;
+
+typedef WGPUId_Queue WGPUQueueId;
+
+typedef void (*WGPUSubmittedWorkDoneCallback)(uint8_t *userdata);
+
+typedef uintptr_t WGPUSubmissionIndex;
 
 typedef uint64_t WGPUId_RenderBundle_Dummy;
 
 typedef WGPUId_RenderBundle_Dummy WGPURenderBundleId;
 
 typedef uint32_t WGPUBackendBit;
+#define WGPUBackendBit_VULKAN 2
+#define WGPUBackendBit_GL 32
+#define WGPUBackendBit_METAL 4
+#define WGPUBackendBit_DX12 8
+#define WGPUBackendBit_DX11 16
+#define WGPUBackendBit_PRIMARY 14
+#define WGPUBackendBit_SECONDARY 48
+
+typedef void (*WGPUValidationMessageCallback)(WGPUValidationMessageLevel level, const char *message, uint8_t *userdata);
+
+typedef struct {
+  /**
+   * Application name, as a null-terminated string. Null means "wgpu".
+   */
+  const char *app_name;
+  uint32_t app_version;
+  /**
+   * Backends to initialize, adapters can only be requested on them.
+   */
+  WGPUBackendBit backends;
+  /**
+   * Called with the validation messages, in addition to logging them.
+   *
+   * The validation can't be toggled here: the Vulkan backend creates its
+   * instance with the validation layers in its debug builds only, so the
+   * callback is never called with a release build of the backend.
+   */
+  WGPUValidationMessageCallback validation_callback;
+  uint8_t *userdata;
+} WGPUInstanceDescriptor;
 
 typedef struct {
   WGPUPowerPreference power_preference;
   WGPUBackendBit backends;
 } WGPURequestAdapterOptions;
 
+typedef struct {
+  const WGPUTextureFormat *formats;
+  uintptr_t formats_length;
+  const WGPUPresentMode *present_modes;
+  uintptr_t present_modes_length;
+  WGPUTextureUsage usage;
+  uint32_t min_image_count;
+  uint32_t max_image_count;
+  WGPUExtent3d current_extent;
+  WGPUExtent3d min_extent;
+  WGPUExtent3d max_extent;
+} WGPUSurfaceCapabilities;
+
 typedef struct {
   WGPUTextureId texture_id;
   WGPUTextureViewId view_id;
 } WGPUSwapChainOutput;
 
+typedef struct {
+  WGPUTextureAspect aspect;
+  uint32_t base_mip_level;
+  uint32_t level_count;
+  uint32_t base_array_layer;
+  uint32_t array_layer_count;
+} WGPUTextureSubresourceRange;
+
 typedef struct {
   WGPUTextureFormat format;
   WGPUTextureViewDimension dimension;
@@ -662,38 +863,66 @@ typedef struct {
   uint32_t array_layer_count;
 } WGPUTextureViewDescriptor;
 
-#if !defined(WGPU_REMOTE)
+WGPUTextureFormatFeatures wgpu_adapter_get_texture_format_features(WGPUAdapterId adapter_id,
+                                                                    WGPUTextureFormat format);
+
 WGPUDeviceId wgpu_adapter_request_device(WGPUAdapterId adapter_id,
                                          const WGPUDeviceDescriptor *desc);
-#endif
 
 void wgpu_bind_group_destroy(WGPUBindGroupId bind_group_id);
 
 void wgpu_buffer_destroy(WGPUBufferId buffer_id);
 
+uint8_t *wgpu_buffer_get_mapped_range(WGPUBufferId buffer_id,
+                                      WGPUBufferAddress start,
+                                      WGPUBufferAddress size);
+
 void wgpu_buffer_map_read_async(WGPUBufferId buffer_id,
                                 WGPUBufferAddress start,
                                 WGPUBufferAddress size,
                                 WGPUBufferMapReadCallback callback,
                                 uint8_t *userdata);
 
+const uint8_t *wgpu_buffer_map_read_sync(WGPUBufferId buffer_id,
+                                         WGPUBufferAddress start,
+                                         WGPUBufferAddress size,
+                                         uint64_t timeout_ns);
+
 void wgpu_buffer_map_write_async(WGPUBufferId buffer_id,
                                  WGPUBufferAddress start,
                                  WGPUBufferAddress size,
                                  WGPUBufferMapWriteCallback callback,
                                  uint8_t *userdata);
 
+uint8_t *wgpu_buffer_map_write_sync(WGPUBufferId buffer_id,
+                                    WGPUBufferAddress start,
+                                    WGPUBufferAddress size,
+                                    uint64_t timeout_ns);
+
 void wgpu_buffer_unmap(WGPUBufferId buffer_id);
 
-#if !defined(WGPU_REMOTE)
+void wgpu_buffer_unmap_range(WGPUBufferId buffer_id,
+                             WGPUBufferAddress start,
+                             WGPUBufferAddress size);
+
 WGPUComputePassId wgpu_command_encoder_begin_compute_pass(WGPUCommandEncoderId encoder_id,
                                                           const WGPUComputePassDescriptor *desc);
-#endif
 
-#if !defined(WGPU_REMOTE)
 WGPURenderPassId wgpu_command_encoder_begin_render_pass(WGPUCommandEncoderId encoder_id,
                                                         const WGPURenderPassDescriptor *desc);
-#endif
+
+void wgpu_command_encoder_clear_buffer(WGPUCommandEncoderId command_encoder_id,
+                                       WGPUBufferId buffer_id,
+                                       WGPUBufferAddress offset,
+                                       WGPUBufferAddress size,
+                                       uint32_t value);
+
+void wgpu_command_encoder_clear_texture(WGPUCommandEncoderId command_encoder_id,
+                                        WGPUTextureId texture_id,
+                                        const WGPUTextureSubresourceRange *range,
+                                        WGPUColor clear_color,
+                                        float clear_depth,
+                                        uint32_t clear_stencil);
 
 void wgpu_command_encoder_copy_buffer_to_buffer(WGPUCommandEncoderId command_encoder_id,
                                                 WGPUBufferId source,
@@ -720,6 +949,11 @@ void wgpu_command_encoder_copy_texture_to_texture(WGPUCommandEncoderId command_e
 WGPUCommandBufferId wgpu_command_encoder_finish(WGPUCommandEncoderId encoder_id,
                                                 const WGPUCommandBufferDescriptor *desc);
 
+void wgpu_command_encoder_generate_mipmaps(WGPUCommandEncoderId command_encoder_id,
+                                           WGPUTextureId texture_id,
+                                           uint32_t base_level,
+                                           uint32_t level_count);
+
 void wgpu_compute_pass_dispatch(WGPUComputePassId pass_id, uint32_t x, uint32_t y, uint32_t z);
 
 void wgpu_compute_pass_dispatch_indirect(WGPUComputePassId pass_id,
@@ -742,90 +976,99 @@ void wgpu_compute_pass_set_bind_group(WGPUComputePassId pass_id,
 
 void wgpu_compute_pass_set_pipeline(WGPUComputePassId pass_id, WGPUComputePipelineId pipeline_id);
 
-#if !defined(WGPU_REMOTE)
+void wgpu_create_instance(const WGPUInstanceDescriptor *desc);
+
 WGPUSurfaceId wgpu_create_surface_from_metal_layer(void *layer);
-#endif
 
-#if !defined(WGPU_REMOTE)
+WGPUSurfaceId wgpu_create_surface_from_wayland(void *display, void *surface);
+
 WGPUSurfaceId wgpu_create_surface_from_windows_hwnd(void *_hinstance, void *hwnd);
-#endif
 
-#if !defined(WGPU_REMOTE)
 WGPUSurfaceId wgpu_create_surface_from_xlib(const void **display, uint64_t window);
+
+#if defined(WGPU_XCB)
+WGPUSurfaceId wgpu_create_surface_from_xcb(void *connection, uint32_t window);
 #endif
 
-#if !defined(WGPU_REMOTE)
 WGPUBindGroupId wgpu_device_create_bind_group(WGPUDeviceId device_id,
                                               const WGPUBindGroupDescriptor *desc);
-#endif
 
-#if !defined(WGPU_REMOTE)
 WGPUBindGroupLayoutId wgpu_device_create_bind_group_layout(WGPUDeviceId device_id,
                                                            const WGPUBindGroupLayoutDescriptor *desc);
-#endif
 
-#if !defined(WGPU_REMOTE)
 WGPUBufferId wgpu_device_create_buffer(WGPUDeviceId device_id, const WGPUBufferDescriptor *desc);
-#endif
 
-#if !defined(WGPU_REMOTE)
 WGPUBufferId wgpu_device_create_buffer_mapped(WGPUDeviceId device_id,
                                               const WGPUBufferDescriptor *desc,
                                               uint8_t **mapped_ptr_out);
-#endif
 
-#if !defined(WGPU_REMOTE)
 WGPUCommandEncoderId wgpu_device_create_command_encoder(WGPUDeviceId device_id,
                                                         const WGPUCommandEncoderDescriptor *desc);
-#endif
 
-#if !defined(WGPU_REMOTE)
 WGPUComputePipelineId wgpu_device_create_compute_pipeline(WGPUDeviceId device_id,
                                                           const WGPUComputePipelineDescriptor *desc);
-#endif
 
-#if !defined(WGPU_REMOTE)
+WGPUSwapChainId wgpu_device_create_headless_swap_chain(WGPUDeviceId device_id,
+                                                       const WGPUSwapChainDescriptor *desc,
+                                                       WGPUSwapChainFrameCallback callback,
+                                                       uint8_t *userdata);
+
 WGPUPipelineLayoutId wgpu_device_create_pipeline_layout(WGPUDeviceId device_id,
                                                         const WGPUPipelineLayoutDescriptor *desc);
-#endif
 
-#if !defined(WGPU_REMOTE)
 WGPURenderPipelineId wgpu_device_create_render_pipeline(WGPUDeviceId device_id,
                                                         const WGPURenderPipelineDescriptor *desc);
-#endif
 
-#if !defined(WGPU_REMOTE)
 WGPUSamplerId wgpu_device_create_sampler(WGPUDeviceId device_id, const WGPUSamplerDescriptor *desc);
-#endif
 
-#if !defined(WGPU_REMOTE)
 WGPUShaderModuleId wgpu_device_create_shader_module(WGPUDeviceId device_id,
                                                     const WGPUShaderModuleDescriptor *desc);
-#endif
 
-#if !defined(WGPU_REMOTE)
 WGPUSwapChainId wgpu_device_create_swap_chain(WGPUDeviceId device_id,
                                               WGPUSurfaceId surface_id,
                                               const WGPUSwapChainDescriptor *desc);
-#endif
 
-#if !defined(WGPU_REMOTE)
 WGPUTextureId wgpu_device_create_texture(WGPUDeviceId device_id, const WGPUTextureDescriptor *desc);
-#endif
 
 void wgpu_device_destroy(WGPUDeviceId device_id);
 
-#if !defined(WGPU_REMOTE)
 void wgpu_device_get_limits(WGPUDeviceId _device_id, WGPULimits *limits);
-#endif
+
+WGPUQueueId wgpu_device_get_dedicated_queue(WGPUDeviceId device_id,
+                                            WGPUQueueType ty,
+                                            uint32_t index);
 
 WGPUQueueId wgpu_device_get_queue(WGPUDeviceId device_id);
 
 void wgpu_device_poll(WGPUDeviceId device_id, bool force_wait);
 
-void wgpu_queue_submit(WGPUQueueId queue_id,
-                       const WGPUCommandBufferId *command_buffers,
-                       uintptr_t command_buffers_length);
+bool wgpu_device_wait_for_submission(WGPUDeviceId device_id,
+                                     WGPUSubmissionIndex submission_index,
+                                     uint64_t timeout_ns);
+
+WGPUCommandEncoderId wgpu_queue_create_command_encoder(WGPUQueueId queue_id,
+                                                       const WGPUCommandEncoderDescriptor *desc);
+
+void wgpu_queue_on_submitted_work_done(WGPUQueueId queue_id,
+                                       WGPUSubmittedWorkDoneCallback callback,
+                                       uint8_t *userdata);
+
+WGPUSubmissionIndex wgpu_queue_submit(WGPUQueueId queue_id,
+                                      const WGPUCommandBufferId *command_buffers,
+                                      uintptr_t command_buffers_length);
+
+void wgpu_queue_write_buffer(WGPUQueueId queue_id,
+                             WGPUBufferId buffer_id,
+                             WGPUBufferAddress buffer_offset,
+                             const uint8_t *data,
+                             uintptr_t data_length);
+
+void wgpu_queue_write_texture(WGPUQueueId queue_id,
+                              const WGPUTextureCopyView *destination,
+                              const uint8_t *data,
+                              uintptr_t data_length,
+                              const WGPUTextureDataLayout *data_layout,
+                              WGPUExtent3d size);
 
 void wgpu_render_pass_draw(WGPURenderPassId pass_id,
                            uint32_t vertex_count,
@@ -892,18 +1135,25 @@ void wgpu_render_pass_set_viewport(WGPURenderPassId pass_id,
                                    float min_depth,
                                    float max_depth);
 
-#if !defined(WGPU_REMOTE)
 WGPUAdapterId wgpu_request_adapter(const WGPURequestAdapterOptions *desc);
-#endif
+
+void wgpu_surface_capabilities_free(WGPUSurfaceCapabilities capabilities);
+
+WGPUSurfaceCapabilities wgpu_surface_get_capabilities(WGPUSurfaceId surface_id,
+                                                      WGPUAdapterId adapter_id);
+
+WGPUSwapChainStatus wgpu_swap_chain_acquire_next_texture(WGPUSwapChainId swap_chain_id,
+                                                         uint64_t timeout_ns,
+                                                         WGPUSwapChainOutput *output);
+
+void wgpu_swap_chain_destroy(WGPUSwapChainId swap_chain_id);
 
 WGPUSwapChainOutput wgpu_swap_chain_get_next_texture(WGPUSwapChainId swap_chain_id);
 
 void wgpu_swap_chain_present(WGPUSwapChainId swap_chain_id);
 
-#if !defined(WGPU_REMOTE)
 WGPUTextureViewId wgpu_texture_create_view(WGPUTextureId texture_id,
                                            const WGPUTextureViewDescriptor *desc);
-#endif
 
 void wgpu_texture_destroy(WGPUTextureId texture_id);
 
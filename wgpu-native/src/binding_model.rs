@@ -1,5 +1,5 @@
 use crate::{
-    resource::TextureViewDimension,
+    resource::{TextureFormat, TextureViewDimension},
     track::TrackerSet,
     BindGroupLayoutId,
     BufferAddress,
@@ -41,6 +41,14 @@ pub enum BindingType {
     StorageTexture = 5,
 }
 
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum StorageTextureAccess {
+    ReadOnly = 0,
+    WriteOnly = 1,
+    ReadWrite = 2,
+}
+
 #[repr(C)]
 #[derive(Clone, Debug, Hash)]
 pub struct BindGroupLayoutBinding {
@@ -50,6 +58,65 @@ pub struct BindGroupLayoutBinding {
     pub texture_dimension: TextureViewDimension,
     pub multisampled: bool,
     pub dynamic: bool,
+    /// Format of the storage texture, has to match the format of the bound view.
+    /// Only used by `BindingType::StorageTexture` bindings.
+    pub storage_texture_format: TextureFormat,
+    /// Shader access to the storage texture.
+    /// Only used by `BindingType::StorageTexture` bindings.
+    pub storage_texture_access: StorageTextureAccess,
+    /// Number of array elements in the binding.
+    /// Zero is treated the same as one, i.e. a single non-array binding.
+    pub count: u32,
+    /// Samplers baked into the layout of a `BindingType::Sampler` binding,
+    /// one per array element. Bind groups created with this layout
+    /// don't provide an entry for it.
+    pub immutable_samplers: *const SamplerId,
+    pub immutable_samplers_length: usize,
+}
+
+impl BindGroupLayoutBinding {
+    pub(crate) fn array_count(&self) -> u32 {
+        self.count.max(1)
+    }
+
+    pub(crate) fn has_immutable_samplers(&self) -> bool {
+        self.immutable_samplers_length != 0
+    }
+}
+
+/// A binding of a created `BindGroupLayout`, which keeps its immutable samplers alive.
+#[derive(Clone, Debug)]
+pub(crate) struct BindGroupLayoutEntry {
+    pub binding: u32,
+    pub ty: BindingType,
+    pub storage_texture_format: TextureFormat,
+    pub storage_texture_access: StorageTextureAccess,
+    pub count: u32,
+    pub immutable_samplers: Vec<Stored<SamplerId>>,
+}
+
+impl BindGroupLayoutEntry {
+    pub(crate) fn new(
+        binding: &BindGroupLayoutBinding,
+        immutable_samplers: Vec<Stored<SamplerId>>,
+    ) -> Self {
+        BindGroupLayoutEntry {
+            binding: binding.binding,
+            ty: binding.ty,
+            storage_texture_format: binding.storage_texture_format,
+            storage_texture_access: binding.storage_texture_access,
+            count: binding.count,
+            immutable_samplers,
+        }
+    }
+
+    pub(crate) fn array_count(&self) -> u32 {
+        self.count.max(1)
+    }
+
+    pub(crate) fn has_immutable_samplers(&self) -> bool {
+        !self.immutable_samplers.is_empty()
+    }
 }
 
 #[repr(C)]
@@ -62,7 +129,8 @@ pub struct BindGroupLayoutDescriptor {
 #[derive(Debug)]
 pub struct BindGroupLayout<B: hal::Backend> {
     pub(crate) raw: B::DescriptorSetLayout,
-    pub(crate) bindings: Vec<BindGroupLayoutBinding>,
+    pub(crate) device_id: Stored<DeviceId>,
+    pub(crate) bindings: Vec<BindGroupLayoutEntry>,
     pub(crate) desc_ranges: DescriptorRanges,
     pub(crate) dynamic_count: usize,
 }
@@ -77,6 +145,7 @@ pub struct PipelineLayoutDescriptor {
 #[derive(Debug)]
 pub struct PipelineLayout<B: hal::Backend> {
     pub(crate) raw: B::PipelineLayout,
+    pub(crate) device_id: Stored<DeviceId>,
     pub(crate) bind_group_layout_ids: ArrayVec<[BindGroupLayoutId; MAX_BIND_GROUPS]>,
 }
 
@@ -94,6 +163,9 @@ pub enum BindingResource {
     Buffer(BufferBinding),
     Sampler(SamplerId),
     TextureView(TextureViewId),
+    BufferArray(*const BufferBinding, usize),
+    SamplerArray(*const SamplerId, usize),
+    TextureViewArray(*const TextureViewId, usize),
 }
 
 #[repr(C)]
@@ -1,5 +1,13 @@
 use super::CommandBuffer;
-use crate::{hub::GfxBackend, track::TrackerSet, DeviceId, LifeGuard, Stored, SubmissionIndex};
+use crate::{
+    device::{QueueIndex, QueueType},
+    hub::GfxBackend,
+    track::TrackerSet,
+    DeviceId,
+    LifeGuard,
+    Stored,
+    SubmissionIndex,
+};
 
 use hal::{command::RawCommandBuffer, pool::RawCommandPool, Device};
 use log::trace;
@@ -45,13 +53,21 @@ impl<B: hal::Backend> Inner<B> {
 #[derive(Debug)]
 pub struct CommandAllocator<B: hal::Backend> {
     queue_family: hal::queue::QueueFamilyId,
+    queue_index: QueueIndex,
+    queue_type: QueueType,
     inner: Mutex<Inner<B>>,
 }
 
 impl<B: GfxBackend> CommandAllocator<B> {
-    pub fn new(queue_family: hal::queue::QueueFamilyId) -> Self {
+    pub fn new(
+        queue_family: hal::queue::QueueFamilyId,
+        queue_index: QueueIndex,
+        queue_type: QueueType,
+    ) -> Self {
         CommandAllocator {
             queue_family,
+            queue_index,
+            queue_type,
             inner: Mutex::new(Inner {
                 pools: HashMap::new(),
                 pending: Vec::new(),
@@ -85,9 +101,14 @@ impl<B: GfxBackend> CommandAllocator<B> {
             is_recording: true,
             recorded_thread_id: thread_id,
             device_id,
+            queue_index: self.queue_index,
+            queue_type: self.queue_type,
             life_guard: LifeGuard::new(),
             trackers: TrackerSet::new(B::VARIANT),
             swap_chain_links: Vec::new(),
+            buffer_init_writes: Vec::new(),
+            texture_init_writes: Vec::new(),
+            temporaries: Vec::new(),
         }
     }
 
@@ -105,6 +126,8 @@ impl<B: GfxBackend> CommandAllocator<B> {
 
     pub fn after_submit(&self, mut cmd_buf: CommandBuffer<B>, submit_index: SubmissionIndex) {
         cmd_buf.trackers.clear();
+        cmd_buf.buffer_init_writes.clear();
+        cmd_buf.texture_init_writes.clear();
         cmd_buf
             .life_guard
             .submission_index
@@ -2,7 +2,7 @@ use crate::{
     command::bind::{Binder, LayoutChange},
     device::all_buffer_stages,
     gfx_select,
-    hub::{GfxBackend, Token},
+    hub::{GfxBackend, Global, Token, GLOBAL},
     track::{Stitch, TrackerSet},
     BindGroupId,
     BufferAddress,
@@ -25,9 +25,10 @@ use std::{iter, slice};
 #[derive(Debug)]
 pub struct ComputePass<B: hal::Backend> {
     raw: B::CommandBuffer,
-    cmb_id: Stored<CommandBufferId>,
+    pub(crate) cmb_id: Stored<CommandBufferId>,
     binder: Binder,
     trackers: TrackerSet,
+    stages: hal::pso::PipelineStage,
 }
 
 impl<B: hal::Backend> ComputePass<B> {
@@ -35,21 +36,23 @@ impl<B: hal::Backend> ComputePass<B> {
         raw: B::CommandBuffer,
         cmb_id: Stored<CommandBufferId>,
         trackers: TrackerSet,
+        stages: hal::pso::PipelineStage,
     ) -> Self {
         ComputePass {
             raw,
             cmb_id,
             binder: Binder::default(),
             trackers,
+            stages,
         }
     }
 }
 
 // Common routines between render/compute
 
-pub fn compute_pass_end_pass<B: GfxBackend>(pass_id: ComputePassId) {
+pub fn compute_pass_end_pass<B: GfxBackend>(global: &Global, pass_id: ComputePassId) {
     let mut token = Token::root();
-    let hub = B::hub();
+    let hub = B::hub(global);
     let (mut cmb_guard, mut token) = hub.command_buffers.write(&mut token);
     let (pass, _) = hub.compute_passes.unregister(pass_id, &mut token);
     let cmb = &mut cmb_guard[pass.cmb_id.value];
@@ -62,16 +65,17 @@ pub fn compute_pass_end_pass<B: GfxBackend>(pass_id: ComputePassId) {
 
 #[no_mangle]
 pub extern "C" fn wgpu_compute_pass_end_pass(pass_id: ComputePassId) {
-    gfx_select!(pass_id => compute_pass_end_pass(pass_id))
+    gfx_select!(pass_id => compute_pass_end_pass(&*GLOBAL, pass_id))
 }
 
 pub fn compute_pass_set_bind_group<B: GfxBackend>(
+    global: &Global,
     pass_id: ComputePassId,
     index: u32,
     bind_group_id: BindGroupId,
     offsets: &[BufferAddress],
 ) {
-    let hub = B::hub();
+    let hub = B::hub(global);
     let mut token = Token::root();
 
     let (pipeline_layout_guard, mut token) = hub.pipeline_layouts.read(&mut token);
@@ -114,6 +118,7 @@ pub fn compute_pass_set_bind_group<B: GfxBackend>(
         &mut pass.trackers,
         &bind_group.used,
         Stitch::Last,
+        pass.stages,
         &*buffer_guard,
         &*texture_guard,
     );
@@ -151,7 +156,7 @@ pub extern "C" fn wgpu_compute_pass_set_bind_group(
     } else {
         &[]
     };
-    gfx_select!(pass_id => compute_pass_set_bind_group(pass_id, index, bind_group_id, offsets))
+    gfx_select!(pass_id => compute_pass_set_bind_group(&*GLOBAL, pass_id, index, bind_group_id, offsets))
 }
 
 #[no_mangle]
@@ -174,8 +179,14 @@ pub extern "C" fn wgpu_compute_pass_insert_debug_marker(
 
 // Compute-specific routines
 
-pub fn compute_pass_dispatch<B: GfxBackend>(pass_id: ComputePassId, x: u32, y: u32, z: u32) {
-    let hub = B::hub();
+pub fn compute_pass_dispatch<B: GfxBackend>(
+    global: &Global,
+    pass_id: ComputePassId,
+    x: u32,
+    y: u32,
+    z: u32,
+) {
+    let hub = B::hub(global);
     let mut token = Token::root();
     let (mut pass_guard, _) = hub.compute_passes.write(&mut token);
     unsafe {
@@ -185,15 +196,16 @@ pub fn compute_pass_dispatch<B: GfxBackend>(pass_id: ComputePassId, x: u32, y: u
 
 #[no_mangle]
 pub extern "C" fn wgpu_compute_pass_dispatch(pass_id: ComputePassId, x: u32, y: u32, z: u32) {
-    gfx_select!(pass_id => compute_pass_dispatch(pass_id, x, y, z))
+    gfx_select!(pass_id => compute_pass_dispatch(&*GLOBAL, pass_id, x, y, z))
 }
 
 pub fn compute_pass_dispatch_indirect<B: GfxBackend>(
+    global: &Global,
     pass_id: ComputePassId,
     indirect_buffer_id: BufferId,
     indirect_offset: BufferAddress,
 ) {
-    let hub = B::hub();
+    let hub = B::hub(global);
     let mut token = Token::root();
     let (buffer_guard, _) = hub.buffers.read(&mut token);
     let (mut pass_guard, _) = hub.compute_passes.write(&mut token);
@@ -213,9 +225,10 @@ pub fn compute_pass_dispatch_indirect<B: GfxBackend>(
         range: None .. None,
     });
 
+    let stages = all_buffer_stages() & pass.stages;
     unsafe {
         pass.raw.pipeline_barrier(
-            all_buffer_stages() .. all_buffer_stages(),
+            stages .. stages,
             hal::memory::Dependencies::empty(),
             barriers,
         );
@@ -229,14 +242,15 @@ pub extern "C" fn wgpu_compute_pass_dispatch_indirect(
     indirect_buffer_id: BufferId,
     indirect_offset: BufferAddress,
 ) {
-    gfx_select!(pass_id => compute_pass_dispatch_indirect(pass_id, indirect_buffer_id, indirect_offset))
+    gfx_select!(pass_id => compute_pass_dispatch_indirect(&*GLOBAL, pass_id, indirect_buffer_id, indirect_offset))
 }
 
 pub fn compute_pass_set_pipeline<B: GfxBackend>(
+    global: &Global,
     pass_id: ComputePassId,
     pipeline_id: ComputePipelineId,
 ) {
-    let hub = B::hub();
+    let hub = B::hub(global);
     let mut token = Token::root();
     let (pipeline_layout_guard, mut token) = hub.pipeline_layouts.read(&mut token);
     let (bind_group_guard, mut token) = hub.bind_groups.read(&mut token);
@@ -291,5 +305,5 @@ pub extern "C" fn wgpu_compute_pass_set_pipeline(
     pass_id: ComputePassId,
     pipeline_id: ComputePipelineId,
 ) {
-    gfx_select!(pass_id => compute_pass_set_pipeline(pass_id, pipeline_id))
+    gfx_select!(pass_id => compute_pass_set_pipeline(&*GLOBAL, pass_id, pipeline_id))
 }
@@ -0,0 +1,232 @@
+use crate::{conv, device::NativeResource, resource::Texture};
+
+use arrayvec::ArrayVec;
+use hal::{self, backend::FastHashMap, command::RawCommandBuffer, Device as _};
+use rendy_descriptor::{DescriptorAllocator, DescriptorRanges};
+
+use std::{io, iter};
+
+const SHADER_FLOAT: &[u8] = include_bytes!("../../shaders/mipmap_float.comp.spv");
+const SHADER_SINT: &[u8] = include_bytes!("../../shaders/mipmap_sint.comp.spv");
+const SHADER_UINT: &[u8] = include_bytes!("../../shaders/mipmap_uint.comp.spv");
+/// Work group size of the shaders, along X and Y.
+const GROUP_SIZE: u32 = 8;
+
+/// Sampled type of a texture format, which selects the shader variant.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum SampledType {
+    Float,
+    Sint,
+    Uint,
+}
+
+impl SampledType {
+    fn of(format: hal::format::Format) -> Self {
+        match format.base_format().1 {
+            hal::format::ChannelType::Sint => SampledType::Sint,
+            hal::format::ChannelType::Uint => SampledType::Uint,
+            _ => SampledType::Float,
+        }
+    }
+
+    fn shader(self) -> &'static [u8] {
+        match self {
+            SampledType::Float => SHADER_FLOAT,
+            SampledType::Sint => SHADER_SINT,
+            SampledType::Uint => SHADER_UINT,
+        }
+    }
+}
+
+/// Compute pipelines filling the mip levels of the formats that can't be
+/// blitted, by writing to them as storage images. Created on first use.
+#[derive(Debug)]
+pub(crate) struct MipmapPipelines<B: hal::Backend> {
+    set_layout: B::DescriptorSetLayout,
+    desc_ranges: DescriptorRanges,
+    layout: B::PipelineLayout,
+    pipelines: FastHashMap<SampledType, B::ComputePipeline>,
+}
+
+impl<B: hal::Backend> MipmapPipelines<B> {
+    pub(crate) fn new(device: &B::Device) -> Self {
+        let bindings = [
+            hal::pso::DescriptorSetLayoutBinding {
+                binding: 0,
+                ty: hal::pso::DescriptorType::SampledImage,
+                count: 1,
+                stage_flags: hal::pso::ShaderStageFlags::COMPUTE,
+                immutable_samplers: false,
+            },
+            hal::pso::DescriptorSetLayoutBinding {
+                binding: 1,
+                ty: hal::pso::DescriptorType::StorageImage,
+                count: 1,
+                stage_flags: hal::pso::ShaderStageFlags::COMPUTE,
+                immutable_samplers: false,
+            },
+        ];
+        // the extents of the source and destination levels
+        let push_constants = (hal::pso::ShaderStageFlags::COMPUTE, 0 .. 16);
+        unsafe {
+            let set_layout = device.create_descriptor_set_layout(&bindings, &[]).unwrap();
+            let layout = device
+                .create_pipeline_layout(iter::once(&set_layout), iter::once(push_constants))
+                .unwrap();
+            MipmapPipelines {
+                set_layout,
+                desc_ranges: DescriptorRanges::from_bindings(&bindings),
+                layout,
+                pipelines: FastHashMap::default(),
+            }
+        }
+    }
+
+    fn create_pipeline(
+        device: &B::Device,
+        layout: &B::PipelineLayout,
+        ty: SampledType,
+    ) -> B::ComputePipeline {
+        unsafe {
+            let spv = hal::pso::read_spirv(io::Cursor::new(ty.shader())).unwrap();
+            let module = device.create_shader_module(&spv).unwrap();
+            let pipeline = device
+                .create_compute_pipeline(
+                    &hal::pso::ComputePipelineDesc {
+                        shader: hal::pso::EntryPoint {
+                            entry: "main",
+                            module: &module,
+                            specialization: hal::pso::Specialization::EMPTY,
+                        },
+                        layout,
+                        flags: hal::pso::PipelineCreationFlags::empty(),
+                        parent: hal::pso::BasePipeline::None,
+                    },
+                    None,
+                )
+                .unwrap();
+            device.destroy_shader_module(module);
+            pipeline
+        }
+    }
+
+    /// Record the generation of the mip level `level` of a 2D color `texture`
+    /// from the previous one, for all the array layers.
+    ///
+    /// The previous level has to be in the `ShaderReadOnlyOptimal` layout,
+    /// and the generated one in the `General` layout. The image views and the
+    /// descriptor set created for it are added to `temporaries`, to be freed
+    /// once the command buffer is done.
+    pub(crate) fn record(
+        &mut self,
+        device: &B::Device,
+        desc_allocator: &mut DescriptorAllocator<B>,
+        raw: &mut B::CommandBuffer,
+        texture: &Texture<B>,
+        level: hal::image::Level,
+        temporaries: &mut Vec<NativeResource<B>>,
+    ) {
+        let format = conv::map_texture_format(texture.format);
+        let layers = texture.full_range.layers.clone();
+        let view = |level: hal::image::Level| unsafe {
+            device
+                .create_image_view(
+                    &texture.raw,
+                    hal::image::ViewKind::D2Array,
+                    format,
+                    hal::format::Swizzle::NO,
+                    hal::image::SubresourceRange {
+                        aspects: hal::format::Aspects::COLOR,
+                        levels: level .. level + 1,
+                        layers: layers.clone(),
+                    },
+                )
+                .unwrap()
+        };
+        let src_view = view(level - 1);
+        let dst_view = view(level);
+
+        let desc_set = unsafe {
+            let mut desc_sets = ArrayVec::<[_; 1]>::new();
+            desc_allocator
+                .allocate(
+                    device,
+                    &self.set_layout,
+                    self.desc_ranges,
+                    1,
+                    &mut desc_sets,
+                )
+                .unwrap();
+            desc_sets.pop().unwrap()
+        };
+        unsafe {
+            device.write_descriptor_sets(vec![
+                hal::pso::DescriptorSetWrite {
+                    set: desc_set.raw(),
+                    binding: 0,
+                    array_offset: 0,
+                    descriptors: iter::once(hal::pso::Descriptor::Image(
+                        &src_view,
+                        hal::image::Layout::ShaderReadOnlyOptimal,
+                    )),
+                },
+                hal::pso::DescriptorSetWrite {
+                    set: desc_set.raw(),
+                    binding: 1,
+                    array_offset: 0,
+                    descriptors: iter::once(hal::pso::Descriptor::Image(
+                        &dst_view,
+                        hal::image::Layout::General,
+                    )),
+                },
+            ]);
+        }
+
+        let src_extent = texture.kind.extent().at_level(level - 1);
+        let dst_extent = texture.kind.extent().at_level(level);
+        let ty = SampledType::of(format);
+        let layout = &self.layout;
+        let pipeline = self
+            .pipelines
+            .entry(ty)
+            .or_insert_with(|| Self::create_pipeline(device, layout, ty));
+        unsafe {
+            raw.bind_compute_pipeline(pipeline);
+            raw.bind_compute_descriptor_sets(
+                layout,
+                0,
+                iter::once(desc_set.raw()),
+                iter::empty::<hal::command::DescriptorSetOffset>(),
+            );
+            raw.push_compute_constants(
+                layout,
+                0,
+                &[
+                    src_extent.width,
+                    src_extent.height,
+                    dst_extent.width,
+                    dst_extent.height,
+                ],
+            );
+            raw.dispatch([
+                (dst_extent.width - 1) / GROUP_SIZE + 1,
+                (dst_extent.height - 1) / GROUP_SIZE + 1,
+                (layers.end - layers.start) as u32,
+            ]);
+        }
+
+        temporaries.push(NativeResource::ImageView(src_view));
+        temporaries.push(NativeResource::ImageView(dst_view));
+        temporaries.push(NativeResource::DescriptorSet(desc_set));
+    }
+
+    pub(crate) fn destroy(self, device: &B::Device) {
+        unsafe {
+            for (_, pipeline) in self.pipelines {
+                device.destroy_compute_pipeline(pipeline);
+            }
+            device.destroy_pipeline_layout(self.layout);
+            device.destroy_descriptor_set_layout(self.set_layout);
+        }
+    }
+}
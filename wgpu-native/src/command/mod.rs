@@ -1,30 +1,36 @@
 mod allocator;
 mod bind;
 mod compute;
+mod mipmap;
 mod render;
 mod transfer;
 
 pub(crate) use self::allocator::CommandAllocator;
 pub use self::compute::*;
+pub(crate) use self::mipmap::MipmapPipelines;
 pub use self::render::*;
 pub use self::transfer::*;
 
 use crate::{
     conv,
     device::{
-        all_buffer_stages,
         all_image_stages,
+        queue_stages,
         FramebufferKey,
+        NativeResource,
+        QueueIndex,
+        QueueType,
         RenderPassContext,
         RenderPassKey,
     },
     gfx_select,
-    hub::{GfxBackend, Storage, Token},
-    id::{Input, Output},
+    hub::{GfxBackend, Global, Storage, Token, GLOBAL},
+    id::Input,
     resource::TexturePlacement,
     swap_chain::{SwapChainLink, SwapImageEpoch},
     track::{Stitch, TrackerSet},
     Buffer,
+    BufferAddress,
     BufferId,
     Color,
     CommandBufferId,
@@ -37,6 +43,7 @@ use crate::{
     Texture,
     TextureId,
     TextureUsage,
+    TextureView,
     TextureViewId,
 };
 
@@ -44,9 +51,7 @@ use arrayvec::ArrayVec;
 use hal::{adapter::PhysicalDevice, command::RawCommandBuffer, Device as _};
 use log::trace;
 
-#[cfg(not(feature = "remote"))]
-use std::marker::PhantomData;
-use std::{collections::hash_map::Entry, iter, mem, ptr, slice, thread::ThreadId};
+use std::{collections::hash_map::Entry, iter, mem, ops::Range, ptr, slice, thread::ThreadId};
 
 
 pub struct RenderBundle<B: hal::Backend> {
@@ -108,10 +113,18 @@ pub struct CommandBuffer<B: hal::Backend> {
     pub(crate) raw: Vec<B::CommandBuffer>,
     is_recording: bool,
     recorded_thread_id: ThreadId,
-    device_id: Stored<DeviceId>,
+    pub(crate) device_id: Stored<DeviceId>,
+    pub(crate) queue_index: QueueIndex,
+    pub(crate) queue_type: QueueType,
     pub(crate) life_guard: LifeGuard,
     pub(crate) trackers: TrackerSet,
     pub(crate) swap_chain_links: Vec<SwapChainLink<SwapImageEpoch>>,
+    /// Resources that are fully overwritten before being read by this
+    /// command buffer, so they don't need to be zero-initialized.
+    pub(crate) buffer_init_writes: Vec<(BufferId, Range<BufferAddress>)>,
+    pub(crate) texture_init_writes: Vec<(TextureId, hal::image::SubresourceRange)>,
+    /// Native objects created while recording, freed with the submission.
+    pub(crate) temporaries: Vec<NativeResource<B>>,
 }
 
 impl<B: GfxBackend> CommandBuffer<B> {
@@ -120,6 +133,7 @@ impl<B: GfxBackend> CommandBuffer<B> {
         base: &mut TrackerSet,
         head: &TrackerSet,
         stitch: Stitch,
+        stages: hal::pso::PipelineStage,
         buffer_guard: &Storage<Buffer<B>, BufferId>,
         texture_guard: &Storage<Texture<B>, TextureId>,
     ) {
@@ -154,7 +168,6 @@ impl<B: GfxBackend> CommandBuffer<B> {
         base.views.merge_extend(&head.views).unwrap();
         base.bind_groups.merge_extend(&head.bind_groups).unwrap();
 
-        let stages = all_buffer_stages() | all_image_stages();
         unsafe {
             raw.pipeline_barrier(
                 stages .. stages,
@@ -180,10 +193,11 @@ pub struct CommandBufferDescriptor {
 }
 
 pub fn command_encoder_finish<B: GfxBackend>(
+    global: &Global,
     encoder_id: CommandEncoderId,
     _desc: &CommandBufferDescriptor,
 ) -> CommandBufferId {
-    let hub = B::hub();
+    let hub = B::hub(global);
     let mut token = Token::root();
     //TODO: actually close the last recorded command buffer
     let (mut comb_guard, _) = hub.command_buffers.write(&mut token);
@@ -197,15 +211,16 @@ pub extern "C" fn wgpu_command_encoder_finish(
     desc: Option<&CommandBufferDescriptor>,
 ) -> CommandBufferId {
     let desc = &desc.cloned().unwrap_or_default();
-    gfx_select!(encoder_id => command_encoder_finish(encoder_id, desc))
+    gfx_select!(encoder_id => command_encoder_finish(&*GLOBAL, encoder_id, desc))
 }
 
 pub fn command_encoder_begin_render_pass<B: GfxBackend>(
+    global: &Global,
     encoder_id: CommandEncoderId,
     desc: &RenderPassDescriptor,
     id_in: Input<RenderPassId>,
-) -> Output<RenderPassId> {
-    let hub = B::hub();
+) -> RenderPassId {
+    let hub = B::hub(global);
     let mut token = Token::root();
 
     let (adapter_guard, mut token) = hub.adapters.read(&mut token);
@@ -213,6 +228,11 @@ pub fn command_encoder_begin_render_pass<B: GfxBackend>(
     let (mut cmb_guard, mut token) = hub.command_buffers.write(&mut token);
     let cmb = &mut cmb_guard[encoder_id];
     let device = &device_guard[cmb.device_id.value];
+    assert_eq!(
+        cmb.queue_type,
+        QueueType::General,
+        "Render passes can only be recorded for the general queue"
+    );
 
     let limits = adapter_guard[device.adapter_id]
         .raw
@@ -220,7 +240,7 @@ pub fn command_encoder_begin_render_pass<B: GfxBackend>(
         .limits();
     let samples_count_limit = limits.framebuffer_color_sample_counts;
 
-    let mut current_comb = device.com_allocator.extend(cmb);
+    let mut current_comb = device.command_allocator(cmb.queue_index).extend(cmb);
     unsafe {
         current_comb.begin(
             hal::command::CommandBufferFlags::ONE_TIME_SUBMIT,
@@ -270,6 +290,17 @@ pub fn command_encoder_begin_render_pass<B: GfxBackend>(
         let rp_key = {
             let trackers = &mut cmb.trackers;
             let swap_chain_links = &mut cmb.swap_chain_links;
+            let texture_init_writes = &mut cmb.texture_init_writes;
+            // the attachments that are cleared or resolved into don't need to be
+            // zero-initialized, unless their textures have been used already
+            let mut overwrite = |trackers: &TrackerSet, view: &TextureView<B>| {
+                let range = &view.range;
+                if range.layers.end - range.layers.start == 1 &&
+                    !trackers.textures.contains(view.texture_id.value)
+                {
+                    texture_init_writes.push((view.texture_id.value, range.clone()));
+                }
+            };
 
             let depth_stencil = depth_stencil_attachment.map(|at| {
                 let view = trackers
@@ -281,6 +312,12 @@ pub fn command_encoder_begin_render_pass<B: GfxBackend>(
                 } else {
                     extent = Some(view.extent);
                 }
+                if at.depth_load_op == LoadOp::Clear &&
+                    (at.stencil_load_op == LoadOp::Clear ||
+                        !view.range.aspects.contains(hal::format::Aspects::STENCIL))
+                {
+                    overwrite(trackers, view);
+                }
                 let old_layout = match trackers
                     .textures
                     .query(view.texture_id.value, view.range.clone())
@@ -338,7 +375,7 @@ pub fn command_encoder_begin_render_pass<B: GfxBackend>(
 
                 if view.is_owned_by_swap_chain {
                     let link = match texture_guard[view.texture_id.value].placement {
-                        TexturePlacement::SwapChain(ref link) => SwapChainLink {
+                        TexturePlacement::SwapChain(ref link, _) => SwapChainLink {
                             swap_chain_id: link.swap_chain_id.clone(),
                             epoch: *link.epoch.lock(),
                             image_index: link.image_index,
@@ -347,6 +384,9 @@ pub fn command_encoder_begin_render_pass<B: GfxBackend>(
                     };
                     swap_chain_links.push(link);
                 }
+                if at.load_op == LoadOp::Clear {
+                    overwrite(trackers, view);
+                }
 
                 let old_layout = match trackers
                     .textures
@@ -396,7 +436,7 @@ pub fn command_encoder_begin_render_pass<B: GfxBackend>(
 
                     if view.is_owned_by_swap_chain {
                         let link = match texture_guard[view.texture_id.value].placement {
-                            TexturePlacement::SwapChain(ref link) => SwapChainLink {
+                            TexturePlacement::SwapChain(ref link, _) => SwapChainLink {
                                 swap_chain_id: link.swap_chain_id.clone(),
                                 epoch: *link.epoch.lock(),
                                 image_index: link.image_index,
@@ -405,6 +445,7 @@ pub fn command_encoder_begin_render_pass<B: GfxBackend>(
                         };
                         swap_chain_links.push(link);
                     }
+                    overwrite(trackers, view);
 
                     let old_layout = match trackers
                         .textures
@@ -567,25 +608,7 @@ pub fn command_encoder_begin_render_pass<B: GfxBackend>(
                 match at.load_op {
                     LoadOp::Load => None,
                     LoadOp::Clear => {
-                        use hal::format::ChannelType;
-                        //TODO: validate sign/unsign and normalized ranges of the color values
-                        let value = match key.format.unwrap().base_format().1 {
-                            ChannelType::Unorm
-                            | ChannelType::Snorm
-                            | ChannelType::Ufloat
-                            | ChannelType::Sfloat
-                            | ChannelType::Uscaled
-                            | ChannelType::Sscaled
-                            | ChannelType::Srgb => hal::command::ClearColor::Sfloat(
-                                conv::map_color_f32(&at.clear_color),
-                            ),
-                            ChannelType::Sint => {
-                                hal::command::ClearColor::Sint(conv::map_color_i32(&at.clear_color))
-                            }
-                            ChannelType::Uint => {
-                                hal::command::ClearColor::Uint(conv::map_color_u32(&at.clear_color))
-                            }
-                        };
+                        let value = conv::map_clear_color(key.format.unwrap(), &at.clear_color);
                         Some(hal::command::ClearValueRaw::from(
                             hal::command::ClearValue::Color(value),
                         ))
@@ -649,25 +672,30 @@ pub fn command_encoder_begin_render_pass<B: GfxBackend>(
     hub.render_passes.register_identity(id_in, pass, &mut token)
 }
 
-#[cfg(not(feature = "remote"))]
 #[no_mangle]
 pub extern "C" fn wgpu_command_encoder_begin_render_pass(
     encoder_id: CommandEncoderId,
     desc: &RenderPassDescriptor,
 ) -> RenderPassId {
-    gfx_select!(encoder_id => command_encoder_begin_render_pass(encoder_id, desc, PhantomData))
+    gfx_select!(encoder_id => command_encoder_begin_render_pass(&*GLOBAL, encoder_id, desc, None))
 }
 
 pub fn command_encoder_begin_compute_pass<B: GfxBackend>(
+    global: &Global,
     encoder_id: CommandEncoderId,
     _desc: &ComputePassDescriptor,
     id_in: Input<ComputePassId>,
-) -> Output<ComputePassId> {
-    let hub = B::hub();
+) -> ComputePassId {
+    let hub = B::hub(global);
     let mut token = Token::root();
 
     let (mut cmb_guard, mut token) = hub.command_buffers.write(&mut token);
     let cmb = &mut cmb_guard[encoder_id];
+    assert_ne!(
+        cmb.queue_type,
+        QueueType::Transfer,
+        "Compute passes can't be recorded for a transfer queue"
+    );
 
     let raw = cmb.raw.pop().unwrap();
     let trackers = mem::replace(&mut cmb.trackers, TrackerSet::new(encoder_id.backend()));
@@ -676,17 +704,16 @@ pub fn command_encoder_begin_compute_pass<B: GfxBackend>(
         ref_count: cmb.life_guard.ref_count.clone(),
     };
 
-    let pass = ComputePass::new(raw, stored, trackers);
+    let pass = ComputePass::new(raw, stored, trackers, queue_stages(cmb.queue_type));
     hub.compute_passes
         .register_identity(id_in, pass, &mut token)
 }
 
-#[cfg(not(feature = "remote"))]
 #[no_mangle]
 pub extern "C" fn wgpu_command_encoder_begin_compute_pass(
     encoder_id: CommandEncoderId,
     desc: Option<&ComputePassDescriptor>,
 ) -> ComputePassId {
     let desc = &desc.cloned().unwrap_or_default();
-    gfx_select!(encoder_id => command_encoder_begin_compute_pass(encoder_id, desc, PhantomData))
+    gfx_select!(encoder_id => command_encoder_begin_compute_pass(&*GLOBAL, encoder_id, desc, None))
 }
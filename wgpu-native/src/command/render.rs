@@ -1,9 +1,15 @@
 use crate::{
     command::bind::{Binder, LayoutChange},
     conv,
-    device::{RenderPassContext, BIND_BUFFER_ALIGNMENT, MAX_VERTEX_BUFFERS},
+    device::{
+        queue_stages,
+        QueueType,
+        RenderPassContext,
+        BIND_BUFFER_ALIGNMENT,
+        MAX_VERTEX_BUFFERS,
+    },
     gfx_select,
-    hub::{GfxBackend, Token},
+    hub::{GfxBackend, Global, Token, GLOBAL},
     pipeline::{IndexFormat, InputStepMode, PipelineFlags},
     resource::BufferUsage,
     track::{Stitch, TrackerSet},
@@ -115,7 +121,7 @@ impl VertexState {
 #[derive(Debug)]
 pub struct RenderPass<B: hal::Backend> {
     raw: B::CommandBuffer,
-    cmb_id: Stored<CommandBufferId>,
+    pub(crate) cmb_id: Stored<CommandBufferId>,
     context: RenderPassContext,
     binder: Binder,
     trackers: TrackerSet,
@@ -176,8 +182,8 @@ impl<B: GfxBackend> RenderPass<B> {
 
 // Common routines between render/compute
 
-pub fn render_pass_end_pass<B: GfxBackend>(pass_id: RenderPassId) {
-    let hub = B::hub();
+pub fn render_pass_end_pass<B: GfxBackend>(global: &Global, pass_id: RenderPassId) {
+    let hub = B::hub(global);
     let mut token = Token::root();
     let (mut cmb_guard, mut token) = hub.command_buffers.write(&mut token);
     let (mut pass, mut token) = hub.render_passes.unregister(pass_id, &mut token);
@@ -197,6 +203,7 @@ pub fn render_pass_end_pass<B: GfxBackend>(pass_id: RenderPassId) {
                 &mut cmb.trackers,
                 &pass.trackers,
                 Stitch::Last,
+                queue_stages(QueueType::General),
                 &*buffer_guard,
                 &*texture_guard,
             );
@@ -212,16 +219,17 @@ pub fn render_pass_end_pass<B: GfxBackend>(pass_id: RenderPassId) {
 
 #[no_mangle]
 pub extern "C" fn wgpu_render_pass_end_pass(pass_id: RenderPassId) {
-    gfx_select!(pass_id => render_pass_end_pass(pass_id))
+    gfx_select!(pass_id => render_pass_end_pass(&*GLOBAL, pass_id))
 }
 
 pub fn render_pass_set_bind_group<B: GfxBackend>(
+    global: &Global,
     pass_id: RenderPassId,
     index: u32,
     bind_group_id: BindGroupId,
     offsets: &[BufferAddress],
 ) {
-    let hub = B::hub();
+    let hub = B::hub(global);
     let mut token = Token::root();
     let (pipeline_layout_guard, mut token) = hub.pipeline_layouts.read(&mut token);
     let (bind_group_guard, mut token) = hub.bind_groups.read(&mut token);
@@ -284,7 +292,7 @@ pub extern "C" fn wgpu_render_pass_set_bind_group(
     } else {
         &[]
     };
-    gfx_select!(pass_id => render_pass_set_bind_group(pass_id, index, bind_group_id, offsets))
+    gfx_select!(pass_id => render_pass_set_bind_group(&*GLOBAL, pass_id, index, bind_group_id, offsets))
 }
 
 #[no_mangle]
@@ -305,11 +313,12 @@ pub extern "C" fn wgpu_render_pass_insert_debug_marker(_pass_id: RenderPassId, _
 // Render-specific routines
 
 pub fn render_pass_set_index_buffer<B: GfxBackend>(
+    global: &Global,
     pass_id: RenderPassId,
     buffer_id: BufferId,
     offset: BufferAddress,
 ) {
-    let hub = B::hub();
+    let hub = B::hub(global);
     let mut token = Token::root();
     let (mut pass_guard, mut token) = hub.render_passes.write(&mut token);
     let (buffer_guard, _) = hub.buffers.read(&mut token);
@@ -341,16 +350,17 @@ pub extern "C" fn wgpu_render_pass_set_index_buffer(
     buffer_id: BufferId,
     offset: BufferAddress,
 ) {
-    gfx_select!(pass_id => render_pass_set_index_buffer(pass_id, buffer_id, offset))
+    gfx_select!(pass_id => render_pass_set_index_buffer(&*GLOBAL, pass_id, buffer_id, offset))
 }
 
 pub fn render_pass_set_vertex_buffers<B: GfxBackend>(
+    global: &Global,
     pass_id: RenderPassId,
     start_slot: u32,
     buffers: &[BufferId],
     offsets: &[BufferAddress],
 ) {
-    let hub = B::hub();
+    let hub = B::hub(global);
     let mut token = Token::root();
     assert_eq!(buffers.len(), offsets.len());
 
@@ -392,17 +402,18 @@ pub extern "C" fn wgpu_render_pass_set_vertex_buffers(
 ) {
     let buffers = unsafe { slice::from_raw_parts(buffers, length) };
     let offsets = unsafe { slice::from_raw_parts(offsets, length) };
-    gfx_select!(pass_id => render_pass_set_vertex_buffers(pass_id, start_slot, buffers, offsets))
+    gfx_select!(pass_id => render_pass_set_vertex_buffers(&*GLOBAL, pass_id, start_slot, buffers, offsets))
 }
 
 pub fn render_pass_draw<B: GfxBackend>(
+    global: &Global,
     pass_id: RenderPassId,
     vertex_count: u32,
     instance_count: u32,
     first_vertex: u32,
     first_instance: u32,
 ) {
-    let hub = B::hub();
+    let hub = B::hub(global);
     let mut token = Token::root();
     let (mut pass_guard, _) = hub.render_passes.write(&mut token);
     let pass = &mut pass_guard[pass_id];
@@ -433,15 +444,16 @@ pub extern "C" fn wgpu_render_pass_draw(
     first_vertex: u32,
     first_instance: u32,
 ) {
-    gfx_select!(pass_id => render_pass_draw(pass_id, vertex_count, instance_count, first_vertex, first_instance))
+    gfx_select!(pass_id => render_pass_draw(&*GLOBAL, pass_id, vertex_count, instance_count, first_vertex, first_instance))
 }
 
 pub fn render_pass_draw_indirect<B: GfxBackend>(
+    global: &Global,
     pass_id: RenderPassId,
     indirect_buffer_id: BufferId,
     indirect_offset: BufferAddress,
 ) {
-    let hub = B::hub();
+    let hub = B::hub(global);
     let mut token = Token::root();
     let (mut pass_guard, _) = hub.render_passes.write(&mut token);
     let (buffer_guard, _) = hub.buffers.read(&mut token);
@@ -470,10 +482,11 @@ pub extern "C" fn wgpu_render_pass_draw_indirect(
     indirect_buffer_id: BufferId,
     indirect_offset: BufferAddress,
 ) {
-    gfx_select!(pass_id => render_pass_draw_indirect(pass_id, indirect_buffer_id, indirect_offset))
+    gfx_select!(pass_id => render_pass_draw_indirect(&*GLOBAL, pass_id, indirect_buffer_id, indirect_offset))
 }
 
 pub fn render_pass_draw_indexed<B: GfxBackend>(
+    global: &Global,
     pass_id: RenderPassId,
     index_count: u32,
     instance_count: u32,
@@ -481,7 +494,7 @@ pub fn render_pass_draw_indexed<B: GfxBackend>(
     base_vertex: i32,
     first_instance: u32,
 ) {
-    let hub = B::hub();
+    let hub = B::hub(global);
     let mut token = Token::root();
     let (mut pass_guard, _) = hub.render_passes.write(&mut token);
     let pass = &mut pass_guard[pass_id];
@@ -515,15 +528,16 @@ pub extern "C" fn wgpu_render_pass_draw_indexed(
     base_vertex: i32,
     first_instance: u32,
 ) {
-    gfx_select!(pass_id => render_pass_draw_indexed(pass_id, index_count, instance_count, first_index, base_vertex, first_instance))
+    gfx_select!(pass_id => render_pass_draw_indexed(&*GLOBAL, pass_id, index_count, instance_count, first_index, base_vertex, first_instance))
 }
 
 pub fn render_pass_draw_indexed_indirect<B: GfxBackend>(
+    global: &Global,
     pass_id: RenderPassId,
     indirect_buffer_id: BufferId,
     indirect_offset: BufferAddress,
 ) {
-    let hub = B::hub();
+    let hub = B::hub(global);
     let mut token = Token::root();
     let (mut pass_guard, _) = hub.render_passes.write(&mut token);
     let (buffer_guard, _) = hub.buffers.read(&mut token);
@@ -553,14 +567,15 @@ pub extern "C" fn wgpu_render_pass_draw_indexed_indirect(
     indirect_buffer_id: BufferId,
     indirect_offset: BufferAddress,
 ) {
-    gfx_select!(pass_id => render_pass_draw_indexed_indirect(pass_id, indirect_buffer_id, indirect_offset))
+    gfx_select!(pass_id => render_pass_draw_indexed_indirect(&*GLOBAL, pass_id, indirect_buffer_id, indirect_offset))
 }
 
 pub fn render_pass_set_pipeline<B: GfxBackend>(
+    global: &Global,
     pass_id: RenderPassId,
     pipeline_id: RenderPipelineId,
 ) {
-    let hub = B::hub();
+    let hub = B::hub(global);
     let mut token = Token::root();
     let (pipeline_layout_guard, mut token) = hub.pipeline_layouts.read(&mut token);
     let (bind_group_guard, mut token) = hub.bind_groups.read(&mut token);
@@ -669,11 +684,15 @@ pub extern "C" fn wgpu_render_pass_set_pipeline(
     pass_id: RenderPassId,
     pipeline_id: RenderPipelineId,
 ) {
-    gfx_select!(pass_id => render_pass_set_pipeline(pass_id, pipeline_id))
+    gfx_select!(pass_id => render_pass_set_pipeline(&*GLOBAL, pass_id, pipeline_id))
 }
 
-pub fn render_pass_set_blend_color<B: GfxBackend>(pass_id: RenderPassId, color: &Color) {
-    let hub = B::hub();
+pub fn render_pass_set_blend_color<B: GfxBackend>(
+    global: &Global,
+    pass_id: RenderPassId,
+    color: &Color,
+) {
+    let hub = B::hub(global);
     let mut token = Token::root();
     let (mut pass_guard, _) = hub.render_passes.write(&mut token);
     let pass = &mut pass_guard[pass_id];
@@ -687,11 +706,15 @@ pub fn render_pass_set_blend_color<B: GfxBackend>(pass_id: RenderPassId, color:
 
 #[no_mangle]
 pub extern "C" fn wgpu_render_pass_set_blend_color(pass_id: RenderPassId, color: &Color) {
-    gfx_select!(pass_id => render_pass_set_blend_color(pass_id, color))
+    gfx_select!(pass_id => render_pass_set_blend_color(&*GLOBAL, pass_id, color))
 }
 
-pub fn render_pass_set_stencil_reference<B: GfxBackend>(pass_id: RenderPassId, value: u32) {
-    let hub = B::hub();
+pub fn render_pass_set_stencil_reference<B: GfxBackend>(
+    global: &Global,
+    pass_id: RenderPassId,
+    value: u32,
+) {
+    let hub = B::hub(global);
     let mut token = Token::root();
     let (mut pass_guard, _) = hub.render_passes.write(&mut token);
     let pass = &mut pass_guard[pass_id];
@@ -705,10 +728,12 @@ pub fn render_pass_set_stencil_reference<B: GfxBackend>(pass_id: RenderPassId, v
 
 #[no_mangle]
 pub extern "C" fn wgpu_render_pass_set_stencil_reference(pass_id: RenderPassId, value: u32) {
-    gfx_select!(pass_id => render_pass_set_stencil_reference(pass_id, value))
+    gfx_select!(pass_id => render_pass_set_stencil_reference(&*GLOBAL, pass_id, value))
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn render_pass_set_viewport<B: GfxBackend>(
+    global: &Global,
     pass_id: RenderPassId,
     x: f32,
     y: f32,
@@ -717,7 +742,7 @@ pub fn render_pass_set_viewport<B: GfxBackend>(
     min_depth: f32,
     max_depth: f32,
 ) {
-    let hub = B::hub();
+    let hub = B::hub(global);
     let mut token = Token::root();
     let (mut pass_guard, _) = hub.render_passes.write(&mut token);
     let pass = &mut pass_guard[pass_id];
@@ -751,17 +776,18 @@ pub extern "C" fn wgpu_render_pass_set_viewport(
     min_depth: f32,
     max_depth: f32,
 ) {
-    gfx_select!(pass_id => render_pass_set_viewport(pass_id, x, y, w, h, min_depth, max_depth))
+    gfx_select!(pass_id => render_pass_set_viewport(&*GLOBAL, pass_id, x, y, w, h, min_depth, max_depth))
 }
 
 pub fn render_pass_set_scissor_rect<B: GfxBackend>(
+    global: &Global,
     pass_id: RenderPassId,
     x: u32,
     y: u32,
     w: u32,
     h: u32,
 ) {
-    let hub = B::hub();
+    let hub = B::hub(global);
     let mut token = Token::root();
     let (mut pass_guard, _) = hub.render_passes.write(&mut token);
     let pass = &mut pass_guard[pass_id];
@@ -790,7 +816,7 @@ pub extern "C" fn wgpu_render_pass_set_scissor_rect(
     w: u32,
     h: u32,
 ) {
-    gfx_select!(pass_id => render_pass_set_scissor_rect(pass_id, x, y, w, h))
+    gfx_select!(pass_id => render_pass_set_scissor_rect(&*GLOBAL, pass_id, x, y, w, h))
 }
 
 #[no_mangle]
@@ -1,26 +1,34 @@
 use crate::{
+    command::MipmapPipelines,
     conv,
-    device::{all_buffer_stages, all_image_stages},
+    device::{all_buffer_stages, all_image_stages, queue_stages, QueueType},
     gfx_select,
-    hub::{GfxBackend, Token},
-    resource::TexturePlacement,
+    hub::{GfxBackend, Global, Token, GLOBAL},
+    resource::{TextureAspect, TexturePlacement, TextureSubresourceRange},
     swap_chain::SwapChainLink,
     BufferAddress,
     BufferId,
     BufferUsage,
+    Color,
     CommandEncoderId,
     Extent3d,
     Origin3d,
+    TextureFormat,
     TextureId,
     TextureUsage,
 };
 
 use copyless::VecHelper as _;
-use hal::command::RawCommandBuffer;
+use hal::{command::RawCommandBuffer, PhysicalDevice as _};
 
 use std::iter;
 
-const BITS_PER_BYTE: u32 = 8;
+pub(crate) const BITS_PER_BYTE: u32 = 8;
+/// Alignment of the offsets and sizes of buffer copies, writes and clears.
+///
+/// Buffers are zero-initialized lazily with word fills, so the parts that are
+/// overwritten and the ones left uninitialized have to be whole words.
+pub const COPY_BUFFER_ALIGNMENT: BufferAddress = 4;
 
 #[repr(C)]
 #[derive(Debug)]
@@ -31,6 +39,15 @@ pub struct BufferCopyView {
     pub image_height: u32,
 }
 
+/// Layout of the texel data passed to `queue_write_texture`.
+#[repr(C)]
+#[derive(Debug)]
+pub struct TextureDataLayout {
+    pub offset: BufferAddress,
+    pub row_pitch: u32,
+    pub image_height: u32,
+}
+
 #[repr(C)]
 #[derive(Debug)]
 pub struct TextureCopyView {
@@ -43,7 +60,7 @@ pub struct TextureCopyView {
 impl TextureCopyView {
     //TODO: we currently access each texture twice for a transfer,
     // once only to get the aspect flags, which is unfortunate.
-    fn to_selector(&self, aspects: hal::format::Aspects) -> hal::image::SubresourceRange {
+    pub(crate) fn to_selector(&self, aspects: hal::format::Aspects) -> hal::image::SubresourceRange {
         let level = self.mip_level as hal::image::Level;
         let layer = self.array_layer as hal::image::Layer;
         hal::image::SubresourceRange {
@@ -53,7 +70,18 @@ impl TextureCopyView {
         }
     }
 
-    fn to_sub_layers(&self, aspects: hal::format::Aspects) -> hal::image::SubresourceLayers {
+    /// Check if a copy of `size` covers the whole subresource.
+    pub(crate) fn covers_subresource(&self, kind: hal::image::Kind, size: Extent3d) -> bool {
+        let extent = kind.extent().at_level(self.mip_level as hal::image::Level);
+        self.origin.x == 0.0 &&
+            self.origin.y == 0.0 &&
+            self.origin.z == 0.0 &&
+            size.width == extent.width &&
+            size.height == extent.height &&
+            size.depth == extent.depth
+    }
+
+    pub(crate) fn to_sub_layers(&self, aspects: hal::format::Aspects) -> hal::image::SubresourceLayers {
         let layer = self.array_layer as hal::image::Layer;
         hal::image::SubresourceLayers {
             aspects,
@@ -63,7 +91,61 @@ impl TextureCopyView {
     }
 }
 
+/// Validate the texel block alignment of a copy between a buffer and a texture.
+/// Returns the width of the buffer rows, in texels.
+pub(crate) fn validate_buffer_texture_copy(
+    texture_kind: hal::image::Kind,
+    format: TextureFormat,
+    view: &TextureCopyView,
+    row_pitch: u32,
+    image_height: u32,
+    size: Extent3d,
+) -> u32 {
+    let desc = conv::map_texture_format(format).surface_desc();
+    let block_size = desc.bits as u32 / BITS_PER_BYTE;
+    let (block_width, block_height) = (desc.dim.0 as u32, desc.dim.1 as u32);
+    assert_eq!(
+        row_pitch % block_size,
+        0,
+        "Row pitch {} is not a multiple of the texel block size {}",
+        row_pitch,
+        block_size
+    );
+
+    let (x, y) = (view.origin.x as u32, view.origin.y as u32);
+    assert!(
+        x % block_width == 0 && y % block_height == 0,
+        "Copy origin {:?} is not aligned to the {}x{} texel blocks",
+        view.origin,
+        block_width,
+        block_height
+    );
+    // partial blocks are only allowed at the edges of the mip level
+    let mip_extent = texture_kind.extent().at_level(view.mip_level as hal::image::Level);
+    assert!(
+        (size.width % block_width == 0 || x + size.width == mip_extent.width) &&
+            (size.height % block_height == 0 || y + size.height == mip_extent.height),
+        "Copy size {:?} is not aligned to the {}x{} texel blocks",
+        size,
+        block_width,
+        block_height
+    );
+    assert_eq!(
+        image_height % block_height,
+        0,
+        "Image height {} is not a multiple of the texel block height {}",
+        image_height,
+        block_height
+    );
+
+    row_pitch / block_size * block_width
+}
+
+/// Copy `size` bytes between buffers.
+///
+/// The offsets and the size must be multiples of `COPY_BUFFER_ALIGNMENT`.
 pub fn command_encoder_copy_buffer_to_buffer<B: GfxBackend>(
+    global: &Global,
     command_encoder_id: CommandEncoderId,
     source: BufferId,
     source_offset: BufferAddress,
@@ -71,12 +153,34 @@ pub fn command_encoder_copy_buffer_to_buffer<B: GfxBackend>(
     destination_offset: BufferAddress,
     size: BufferAddress,
 ) {
-    let hub = B::hub();
+    let hub = B::hub(global);
     let mut token = Token::root();
 
     let (mut cmb_guard, mut token) = hub.command_buffers.write(&mut token);
     let cmb = &mut cmb_guard[command_encoder_id];
     let (buffer_guard, _) = hub.buffers.read(&mut token);
+    assert!(
+        source_offset % COPY_BUFFER_ALIGNMENT == 0 &&
+            destination_offset % COPY_BUFFER_ALIGNMENT == 0 &&
+            size % COPY_BUFFER_ALIGNMENT == 0,
+        "Buffer copy offsets and size must be multiples of {}",
+        COPY_BUFFER_ALIGNMENT
+    );
+    let is_in_bounds = |offset: BufferAddress, buffer: BufferId| {
+        offset
+            .checked_add(size)
+            .filter(|&end| end <= buffer_guard[buffer].size)
+            .is_some()
+    };
+    assert!(
+        is_in_bounds(source_offset, source) && is_in_bounds(destination_offset, destination),
+        "Buffer copy of {} bytes is out of the buffer bounds",
+        size
+    );
+    if !cmb.trackers.buffers.contains(destination) {
+        cmb.buffer_init_writes
+            .push((destination, destination_offset .. destination_offset + size));
+    }
     // we can't hold both src_pending and dst_pending in scope because they
     // borrow the buffer tracker mutably...
     let mut barriers = Vec::new();
@@ -108,10 +212,11 @@ pub fn command_encoder_copy_buffer_to_buffer<B: GfxBackend>(
         dst: destination_offset,
         size,
     };
+    let stages = all_buffer_stages() & queue_stages(cmb.queue_type);
     let cmb_raw = cmb.raw.last_mut().unwrap();
     unsafe {
         cmb_raw.pipeline_barrier(
-            all_buffer_stages() .. all_buffer_stages(),
+            stages .. stages,
             hal::memory::Dependencies::empty(),
             barriers,
         );
@@ -129,6 +234,7 @@ pub extern "C" fn wgpu_command_encoder_copy_buffer_to_buffer(
     size: BufferAddress,
 ) {
     gfx_select!(command_encoder_id => command_encoder_copy_buffer_to_buffer(
+        &*GLOBAL,
         command_encoder_id,
         source, source_offset,
         destination,
@@ -137,12 +243,13 @@ pub extern "C" fn wgpu_command_encoder_copy_buffer_to_buffer(
 }
 
 pub fn command_encoder_copy_buffer_to_texture<B: GfxBackend>(
+    global: &Global,
     command_encoder_id: CommandEncoderId,
     source: &BufferCopyView,
     destination: &TextureCopyView,
     copy_size: Extent3d,
 ) {
-    let hub = B::hub();
+    let hub = B::hub(global);
     let mut token = Token::root();
 
     let (mut cmb_guard, mut token) = hub.command_buffers.write(&mut token);
@@ -150,6 +257,12 @@ pub fn command_encoder_copy_buffer_to_texture<B: GfxBackend>(
     let (buffer_guard, mut token) = hub.buffers.read(&mut token);
     let (texture_guard, _) = hub.textures.read(&mut token);
     let aspects = texture_guard[destination.texture].full_range.aspects;
+    if destination.covers_subresource(texture_guard[destination.texture].kind, copy_size) &&
+        !cmb.trackers.textures.contains(destination.texture)
+    {
+        cmb.texture_init_writes
+            .push((destination.texture, destination.to_selector(aspects)));
+    }
 
     let (src_buffer, src_pending) =
         cmb.trackers
@@ -175,7 +288,7 @@ pub fn command_encoder_copy_buffer_to_texture<B: GfxBackend>(
         range: pending.selector,
     });
 
-    if let TexturePlacement::SwapChain(ref link) = dst_texture.placement {
+    if let TexturePlacement::SwapChain(ref link, _) = dst_texture.placement {
         cmb.swap_chain_links.alloc().init(SwapChainLink {
             swap_chain_id: link.swap_chain_id.clone(),
             epoch: *link.epoch.lock(),
@@ -184,12 +297,14 @@ pub fn command_encoder_copy_buffer_to_texture<B: GfxBackend>(
     }
 
     let aspects = dst_texture.full_range.aspects;
-    let bytes_per_texel = conv::map_texture_format(dst_texture.format)
-        .surface_desc()
-        .bits as u32
-        / BITS_PER_BYTE;
-    let buffer_width = source.row_pitch / bytes_per_texel;
-    assert_eq!(source.row_pitch % bytes_per_texel, 0);
+    let buffer_width = validate_buffer_texture_copy(
+        dst_texture.kind,
+        dst_texture.format,
+        destination,
+        source.row_pitch,
+        source.image_height,
+        copy_size,
+    );
     let region = hal::command::BufferImageCopy {
         buffer_offset: source.offset,
         buffer_width,
@@ -198,8 +313,8 @@ pub fn command_encoder_copy_buffer_to_texture<B: GfxBackend>(
         image_offset: conv::map_origin(destination.origin),
         image_extent: conv::map_extent(copy_size),
     };
+    let stages = (all_buffer_stages() | all_image_stages()) & queue_stages(cmb.queue_type);
     let cmb_raw = cmb.raw.last_mut().unwrap();
-    let stages = all_buffer_stages() | all_image_stages();
     unsafe {
         cmb_raw.pipeline_barrier(
             stages .. stages,
@@ -223,6 +338,7 @@ pub extern "C" fn wgpu_command_encoder_copy_buffer_to_texture(
     copy_size: Extent3d,
 ) {
     gfx_select!(command_encoder_id => command_encoder_copy_buffer_to_texture(
+        &*GLOBAL,
         command_encoder_id,
         source,
         destination,
@@ -230,12 +346,13 @@ pub extern "C" fn wgpu_command_encoder_copy_buffer_to_texture(
 }
 
 pub fn command_encoder_copy_texture_to_buffer<B: GfxBackend>(
+    global: &Global,
     command_encoder_id: CommandEncoderId,
     source: &TextureCopyView,
     destination: &BufferCopyView,
     copy_size: Extent3d,
 ) {
-    let hub = B::hub();
+    let hub = B::hub(global);
     let mut token = Token::root();
     let (mut cmb_guard, mut token) = hub.command_buffers.write(&mut token);
     let cmb = &mut cmb_guard[command_encoder_id];
@@ -255,9 +372,12 @@ pub fn command_encoder_copy_texture_to_buffer<B: GfxBackend>(
         families: None,
         range: pending.selector,
     });
-    match src_texture.placement {
-        TexturePlacement::SwapChain(_) => unimplemented!(),
-        TexturePlacement::Memory(_) => (),
+    if let TexturePlacement::SwapChain(ref link, _) = src_texture.placement {
+        cmb.swap_chain_links.alloc().init(SwapChainLink {
+            swap_chain_id: link.swap_chain_id.clone(),
+            epoch: *link.epoch.lock(),
+            image_index: link.image_index,
+        });
     }
 
     let (dst_buffer, dst_barriers) = cmb.trackers.buffers.use_replace(
@@ -274,12 +394,14 @@ pub fn command_encoder_copy_texture_to_buffer<B: GfxBackend>(
     });
 
     let aspects = src_texture.full_range.aspects;
-    let bytes_per_texel = conv::map_texture_format(src_texture.format)
-        .surface_desc()
-        .bits as u32
-        / BITS_PER_BYTE;
-    let buffer_width = destination.row_pitch / bytes_per_texel;
-    assert_eq!(destination.row_pitch % bytes_per_texel, 0);
+    let buffer_width = validate_buffer_texture_copy(
+        src_texture.kind,
+        src_texture.format,
+        source,
+        destination.row_pitch,
+        destination.image_height,
+        copy_size,
+    );
     let region = hal::command::BufferImageCopy {
         buffer_offset: destination.offset,
         buffer_width,
@@ -288,8 +410,8 @@ pub fn command_encoder_copy_texture_to_buffer<B: GfxBackend>(
         image_offset: conv::map_origin(source.origin),
         image_extent: conv::map_extent(copy_size),
     };
+    let stages = (all_buffer_stages() | all_image_stages()) & queue_stages(cmb.queue_type);
     let cmb_raw = cmb.raw.last_mut().unwrap();
-    let stages = all_buffer_stages() | all_image_stages();
     unsafe {
         cmb_raw.pipeline_barrier(
             stages .. stages,
@@ -313,6 +435,7 @@ pub extern "C" fn wgpu_command_encoder_copy_texture_to_buffer(
     copy_size: Extent3d,
 ) {
     gfx_select!(command_encoder_id => command_encoder_copy_texture_to_buffer(
+        &*GLOBAL,
         command_encoder_id,
         source,
         destination,
@@ -320,12 +443,13 @@ pub extern "C" fn wgpu_command_encoder_copy_texture_to_buffer(
 }
 
 pub fn command_encoder_copy_texture_to_texture<B: GfxBackend>(
+    global: &Global,
     command_encoder_id: CommandEncoderId,
     source: &TextureCopyView,
     destination: &TextureCopyView,
     copy_size: Extent3d,
 ) {
-    let hub = B::hub();
+    let hub = B::hub(global);
     let mut token = Token::root();
 
     let (mut cmb_guard, mut token) = hub.command_buffers.write(&mut token);
@@ -337,6 +461,13 @@ pub fn command_encoder_copy_texture_to_texture<B: GfxBackend>(
     let mut barriers = Vec::new();
     let aspects = texture_guard[source.texture].full_range.aspects
         & texture_guard[destination.texture].full_range.aspects;
+    if aspects == texture_guard[destination.texture].full_range.aspects &&
+        destination.covers_subresource(texture_guard[destination.texture].kind, copy_size) &&
+        !cmb.trackers.textures.contains(destination.texture)
+    {
+        cmb.texture_init_writes
+            .push((destination.texture, destination.to_selector(aspects)));
+    }
 
     let (src_texture, src_pending) = cmb.trackers.textures.use_replace(
         &*texture_guard,
@@ -364,12 +495,14 @@ pub fn command_encoder_copy_texture_to_texture<B: GfxBackend>(
         range: pending.selector,
     }));
 
-    if let TexturePlacement::SwapChain(ref link) = dst_texture.placement {
-        cmb.swap_chain_links.alloc().init(SwapChainLink {
-            swap_chain_id: link.swap_chain_id.clone(),
-            epoch: *link.epoch.lock(),
-            image_index: link.image_index,
-        });
+    for texture in &[src_texture, dst_texture] {
+        if let TexturePlacement::SwapChain(ref link, _) = texture.placement {
+            cmb.swap_chain_links.alloc().init(SwapChainLink {
+                swap_chain_id: link.swap_chain_id.clone(),
+                epoch: *link.epoch.lock(),
+                image_index: link.image_index,
+            });
+        }
     }
 
     let aspects = src_texture.full_range.aspects & dst_texture.full_range.aspects;
@@ -380,10 +513,11 @@ pub fn command_encoder_copy_texture_to_texture<B: GfxBackend>(
         dst_offset: conv::map_origin(destination.origin),
         extent: conv::map_extent(copy_size),
     };
+    let stages = all_image_stages() & queue_stages(cmb.queue_type);
     let cmb_raw = cmb.raw.last_mut().unwrap();
     unsafe {
         cmb_raw.pipeline_barrier(
-            all_image_stages() .. all_image_stages(),
+            stages .. stages,
             hal::memory::Dependencies::empty(),
             barriers,
         );
@@ -405,8 +539,441 @@ pub extern "C" fn wgpu_command_encoder_copy_texture_to_texture(
     copy_size: Extent3d,
 ) {
     gfx_select!(command_encoder_id => command_encoder_copy_texture_to_texture(
+        &*GLOBAL,
         command_encoder_id,
         source,
         destination,
         copy_size))
 }
+
+/// Fill the mip levels `base_level + 1 .. base_level + level_count` of the texture,
+/// each one by downsampling the previous level.
+///
+/// A `level_count` of zero means all the levels till the end of the mip chain.
+///
+/// The levels are generated with blits when the texture format supports
+/// blitting with optimal tiling, which requires `COPY_SRC` and `COPY_DST` usage.
+/// The other formats fall back to a compute shader writing the levels as
+/// storage images, which requires `SAMPLED` and `STORAGE` usage, a 2D color
+/// texture, and a device able to write storage images without a format.
+/// Multisampled textures and swap chain frames are not accepted.
+pub fn command_encoder_generate_mipmaps<B: GfxBackend>(
+    global: &Global,
+    command_encoder_id: CommandEncoderId,
+    texture_id: TextureId,
+    base_level: u32,
+    level_count: u32,
+) {
+    use hal::format::ImageFeature as If;
+
+    let hub = B::hub(global);
+    let mut token = Token::root();
+    let (adapter_guard, mut token) = hub.adapters.read(&mut token);
+    let (device_guard, mut token) = hub.devices.read(&mut token);
+    let (mut cmb_guard, mut token) = hub.command_buffers.write(&mut token);
+    let cmb = &mut cmb_guard[command_encoder_id];
+    let (_, mut token) = hub.buffers.read(&mut token); //skip token
+    let (texture_guard, _) = hub.textures.read(&mut token);
+
+    assert_eq!(
+        cmb.queue_type,
+        QueueType::General,
+        "Mipmaps can only be generated on the general queue"
+    );
+    let texture = &texture_guard[texture_id];
+    assert_eq!(
+        texture.kind.num_samples(),
+        1,
+        "Unable to generate mipmaps for the multisampled texture {:?}",
+        texture_id
+    );
+    if let TexturePlacement::SwapChain(..) = texture.placement {
+        panic!(
+            "Unable to generate mipmaps for the swap chain frame {:?}",
+            texture_id
+        );
+    }
+    let full_level_count = texture.full_range.levels.end as u32;
+    let end_level = if level_count == 0 {
+        full_level_count
+    } else {
+        base_level.saturating_add(level_count)
+    };
+    assert!(
+        base_level < end_level && end_level <= full_level_count,
+        "Mip levels {}..{} are out of the texture range 0..{}",
+        base_level,
+        end_level,
+        full_level_count
+    );
+
+    let device = &device_guard[cmb.device_id.value];
+    let features = adapter_guard[device.adapter_id]
+        .raw
+        .physical_device
+        .format_properties(Some(conv::map_texture_format(texture.format)))
+        .optimal_tiling;
+    let aspects = texture.full_range.aspects;
+    let is_blittable = features.contains(If::BLIT_SRC | If::BLIT_DST);
+    let (src_usage, dst_usage) = if is_blittable {
+        assert!(
+            texture
+                .usage
+                .contains(TextureUsage::COPY_SRC | TextureUsage::COPY_DST),
+            "Generating mipmaps with blits requires COPY_SRC and COPY_DST usage, got {:?}",
+            texture.usage
+        );
+        (TextureUsage::COPY_SRC, TextureUsage::COPY_DST)
+    } else {
+        match texture.kind {
+            hal::image::Kind::D2(..) => {}
+            _ => panic!(
+                "Unable to generate mipmaps of a non-2D texture with the non-blittable format {:?}",
+                texture.format
+            ),
+        }
+        assert!(
+            aspects == hal::format::Aspects::COLOR &&
+                features.contains(If::SAMPLED | If::STORAGE) &&
+                device
+                    .features
+                    .contains(hal::Features::SHADER_STORAGE_IMAGE_WRITE_WITHOUT_FORMAT),
+            "Texture format {:?} can't be blitted nor written by a compute shader, {}",
+            texture.format,
+            "unable to generate mipmaps"
+        );
+        assert!(
+            texture
+                .usage
+                .contains(TextureUsage::SAMPLED | TextureUsage::STORAGE),
+            "Generating mipmaps of {:?} requires SAMPLED and STORAGE usage, got {:?}",
+            texture.format,
+            texture.usage
+        );
+        (TextureUsage::SAMPLED, TextureUsage::STORAGE)
+    };
+    // depth and stencil can only be blitted with nearest filtering
+    let filter = if features.contains(If::SAMPLED_LINEAR) &&
+        aspects == hal::format::Aspects::COLOR
+    {
+        hal::image::Filter::Linear
+    } else {
+        hal::image::Filter::Nearest
+    };
+
+    let layers = texture.full_range.layers.clone();
+    let level_range = |level: hal::image::Level| hal::image::SubresourceRange {
+        aspects,
+        levels: level .. level + 1,
+        layers: layers.clone(),
+    };
+    let level_layers = |level: hal::image::Level| hal::image::SubresourceLayers {
+        aspects,
+        level,
+        layers: layers.clone(),
+    };
+    let stages = all_image_stages() & queue_stages(cmb.queue_type);
+
+    for level in base_level + 1 .. end_level {
+        let src_level = (level - 1) as hal::image::Level;
+        let dst_level = level as hal::image::Level;
+        // the barriers borrow the tracker, so they have to be collected
+        // before the next level is transitioned
+        let mut barriers = Vec::new();
+        for (selector, usage) in &[
+            (level_range(src_level), src_usage),
+            (level_range(dst_level), dst_usage),
+        ] {
+            let (_, pending) = cmb.trackers.textures.use_replace(
+                &*texture_guard,
+                texture_id,
+                selector.clone(),
+                *usage,
+            );
+            barriers.extend(pending.map(|pending| hal::memory::Barrier::Image {
+                states: pending.to_states(),
+                target: &texture.raw,
+                families: None,
+                range: pending.selector,
+            }));
+        }
+
+        let cmb_raw = cmb.raw.last_mut().unwrap();
+        unsafe {
+            cmb_raw.pipeline_barrier(
+                stages .. stages,
+                hal::memory::Dependencies::empty(),
+                barriers,
+            );
+        }
+        if is_blittable {
+            let region = hal::command::ImageBlit {
+                src_subresource: level_layers(src_level),
+                src_bounds: hal::image::Offset::ZERO
+                    .into_bounds(&texture.kind.extent().at_level(src_level)),
+                dst_subresource: level_layers(dst_level),
+                dst_bounds: hal::image::Offset::ZERO
+                    .into_bounds(&texture.kind.extent().at_level(dst_level)),
+            };
+            unsafe {
+                cmb_raw.blit_image(
+                    &texture.raw,
+                    hal::image::Layout::TransferSrcOptimal,
+                    &texture.raw,
+                    hal::image::Layout::TransferDstOptimal,
+                    filter,
+                    iter::once(region),
+                );
+            }
+        } else {
+            device
+                .mipmap_pipelines
+                .lock()
+                .get_or_insert_with(|| MipmapPipelines::new(&*device.raw))
+                .record(
+                    &*device.raw,
+                    &mut *device.desc_allocator.lock(),
+                    cmb_raw,
+                    texture,
+                    dst_level,
+                    &mut cmb.temporaries,
+                );
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wgpu_command_encoder_generate_mipmaps(
+    command_encoder_id: CommandEncoderId,
+    texture_id: TextureId,
+    base_level: u32,
+    level_count: u32,
+) {
+    gfx_select!(command_encoder_id => command_encoder_generate_mipmaps(
+        &*GLOBAL,
+        command_encoder_id,
+        texture_id,
+        base_level,
+        level_count))
+}
+
+pub fn command_encoder_clear_buffer<B: GfxBackend>(
+    global: &Global,
+    command_encoder_id: CommandEncoderId,
+    buffer_id: BufferId,
+    offset: BufferAddress,
+    size: BufferAddress,
+    value: u32,
+) {
+    let hub = B::hub(global);
+    let mut token = Token::root();
+
+    let (mut cmb_guard, mut token) = hub.command_buffers.write(&mut token);
+    let cmb = &mut cmb_guard[command_encoder_id];
+    let (buffer_guard, _) = hub.buffers.read(&mut token);
+
+    let buffer = &buffer_guard[buffer_id];
+    assert!(
+        buffer.usage.contains(BufferUsage::COPY_DST),
+        "Clearing a buffer requires COPY_DST usage, got {:?}",
+        buffer.usage
+    );
+    assert!(
+        offset % COPY_BUFFER_ALIGNMENT == 0 && size % COPY_BUFFER_ALIGNMENT == 0,
+        "Buffer clear offset {} and size {} are not aligned to {} bytes",
+        offset,
+        size,
+        COPY_BUFFER_ALIGNMENT
+    );
+    let end = match offset.checked_add(size) {
+        Some(end) if end <= buffer.size => end,
+        _ => panic!(
+            "Buffer clear range at {} of size {} is out of the buffer size {}",
+            offset, size, buffer.size
+        ),
+    };
+    // empty fills are not allowed by the backends
+    if size == 0 {
+        return;
+    }
+
+    if !cmb.trackers.buffers.contains(buffer_id) {
+        cmb.buffer_init_writes.push((buffer_id, offset .. end));
+    }
+    let (dst_buffer, dst_pending) =
+        cmb.trackers
+            .buffers
+            .use_replace(&*buffer_guard, buffer_id, (), BufferUsage::COPY_DST);
+    let barriers = dst_pending.map(|pending| hal::memory::Barrier::Buffer {
+        states: pending.to_states(),
+        target: &dst_buffer.raw,
+        families: None,
+        range: None .. None,
+    });
+
+    let stages = all_buffer_stages() & queue_stages(cmb.queue_type);
+    let cmb_raw = cmb.raw.last_mut().unwrap();
+    unsafe {
+        cmb_raw.pipeline_barrier(
+            stages .. stages,
+            hal::memory::Dependencies::empty(),
+            barriers,
+        );
+        cmb_raw.fill_buffer(&dst_buffer.raw, offset .. end, value);
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wgpu_command_encoder_clear_buffer(
+    command_encoder_id: CommandEncoderId,
+    buffer_id: BufferId,
+    offset: BufferAddress,
+    size: BufferAddress,
+    value: u32,
+) {
+    gfx_select!(command_encoder_id => command_encoder_clear_buffer(
+        &*GLOBAL,
+        command_encoder_id,
+        buffer_id,
+        offset,
+        size,
+        value))
+}
+
+pub fn command_encoder_clear_texture<B: GfxBackend>(
+    global: &Global,
+    command_encoder_id: CommandEncoderId,
+    texture_id: TextureId,
+    range: &TextureSubresourceRange,
+    clear_color: Color,
+    clear_depth: f32,
+    clear_stencil: u32,
+) {
+    let hub = B::hub(global);
+    let mut token = Token::root();
+
+    let (mut cmb_guard, mut token) = hub.command_buffers.write(&mut token);
+    let cmb = &mut cmb_guard[command_encoder_id];
+    let (_, mut token) = hub.buffers.read(&mut token); //skip token
+    let (texture_guard, _) = hub.textures.read(&mut token);
+
+    let texture = &texture_guard[texture_id];
+    assert!(
+        texture.usage.contains(TextureUsage::COPY_DST),
+        "Clearing a texture requires COPY_DST usage, got {:?}",
+        texture.usage
+    );
+    let aspects = match range.aspect {
+        TextureAspect::All => texture.full_range.aspects,
+        TextureAspect::DepthOnly => hal::format::Aspects::DEPTH,
+        TextureAspect::StencilOnly => hal::format::Aspects::STENCIL,
+    };
+    assert!(
+        texture.full_range.aspects.contains(aspects),
+        "Texture aspects {:?} don't contain the cleared aspects {:?}",
+        texture.full_range.aspects,
+        aspects
+    );
+    assert!(
+        !conv::map_texture_format(texture.format)
+            .surface_desc()
+            .is_compressed(),
+        "Unable to clear the texture of compressed format {:?}",
+        texture.format
+    );
+    let end_level = if range.level_count == 0 {
+        texture.full_range.levels.end as u32
+    } else {
+        range.base_mip_level.saturating_add(range.level_count)
+    };
+    let end_layer = if range.array_layer_count == 0 {
+        texture.full_range.layers.end as u32
+    } else {
+        range.base_array_layer.saturating_add(range.array_layer_count)
+    };
+    assert!(
+        range.base_mip_level < end_level && end_level <= texture.full_range.levels.end as u32,
+        "Cleared mip levels {}..{} are out of the texture range {:?}",
+        range.base_mip_level,
+        end_level,
+        texture.full_range.levels
+    );
+    assert!(
+        range.base_array_layer < end_layer && end_layer <= texture.full_range.layers.end as u32,
+        "Cleared array layers {}..{} are out of the texture range {:?}",
+        range.base_array_layer,
+        end_layer,
+        texture.full_range.layers
+    );
+    let selector = hal::image::SubresourceRange {
+        aspects,
+        levels: range.base_mip_level as hal::image::Level .. end_level as hal::image::Level,
+        layers: range.base_array_layer as hal::image::Layer .. end_layer as hal::image::Layer,
+    };
+
+    if aspects == texture.full_range.aspects && !cmb.trackers.textures.contains(texture_id) {
+        cmb.texture_init_writes.push((texture_id, selector.clone()));
+    }
+    let (dst_texture, dst_pending) = cmb.trackers.textures.use_replace(
+        &*texture_guard,
+        texture_id,
+        selector.clone(),
+        TextureUsage::COPY_DST,
+    );
+    let barriers = dst_pending.map(|pending| hal::memory::Barrier::Image {
+        states: pending.to_states(),
+        target: &dst_texture.raw,
+        families: None,
+        range: pending.selector,
+    });
+
+    if let TexturePlacement::SwapChain(ref link, _) = dst_texture.placement {
+        cmb.swap_chain_links.alloc().init(SwapChainLink {
+            swap_chain_id: link.swap_chain_id.clone(),
+            epoch: *link.epoch.lock(),
+            image_index: link.image_index,
+        });
+    }
+
+    let color = if aspects.contains(hal::format::Aspects::COLOR) {
+        conv::map_clear_color(conv::map_texture_format(dst_texture.format), &clear_color)
+    } else {
+        hal::command::ClearColor::Sfloat([0.0; 4])
+    };
+    let depth_stencil = hal::command::ClearDepthStencil(clear_depth, clear_stencil);
+    let stages = all_image_stages() & queue_stages(cmb.queue_type);
+    let cmb_raw = cmb.raw.last_mut().unwrap();
+    unsafe {
+        cmb_raw.pipeline_barrier(
+            stages .. stages,
+            hal::memory::Dependencies::empty(),
+            barriers,
+        );
+        cmb_raw.clear_image(
+            &dst_texture.raw,
+            hal::image::Layout::TransferDstOptimal,
+            color.into(),
+            depth_stencil.into(),
+            iter::once(selector),
+        );
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wgpu_command_encoder_clear_texture(
+    command_encoder_id: CommandEncoderId,
+    texture_id: TextureId,
+    range: &TextureSubresourceRange,
+    clear_color: Color,
+    clear_depth: f32,
+    clear_stencil: u32,
+) {
+    gfx_select!(command_encoder_id => command_encoder_clear_texture(
+        &*GLOBAL,
+        command_encoder_id,
+        texture_id,
+        range,
+        clear_color,
+        clear_depth,
+        clear_stencil))
+}
@@ -58,7 +58,7 @@ pub fn map_texture_usage(
     if usage.contains(W::SAMPLED) {
         value |= U::SAMPLED;
     }
-    if usage.contains(W::STORAGE) {
+    if usage.intersects(W::STORAGE | W::STORAGE_READ) {
         value |= U::STORAGE;
     }
     if usage.contains(W::OUTPUT_ATTACHMENT) {
@@ -73,6 +73,29 @@ pub fn map_texture_usage(
     value
 }
 
+pub fn map_hal_texture_usage(usage: hal::image::Usage) -> resource::TextureUsage {
+    use crate::resource::TextureUsage as W;
+    use hal::image::Usage as U;
+
+    let mut value = W::empty();
+    if usage.contains(U::TRANSFER_SRC) {
+        value |= W::COPY_SRC;
+    }
+    if usage.contains(U::TRANSFER_DST) {
+        value |= W::COPY_DST;
+    }
+    if usage.contains(U::SAMPLED) {
+        value |= W::SAMPLED;
+    }
+    if usage.contains(U::STORAGE) {
+        value |= W::STORAGE | W::STORAGE_READ;
+    }
+    if usage.intersects(U::COLOR_ATTACHMENT | U::DEPTH_STENCIL_ATTACHMENT) {
+        value |= W::OUTPUT_ATTACHMENT;
+    }
+    value
+}
+
 pub fn map_binding_type(
     binding: &binding_model::BindGroupLayoutBinding,
 ) -> hal::pso::DescriptorType {
@@ -369,9 +392,129 @@ pub fn map_texture_format(texture_format: resource::TextureFormat) -> hal::forma
         Tf::Depth32Float => H::D32Sfloat,
         Tf::Depth24Plus => H::D24UnormS8Uint, //TODO: substitute
         Tf::Depth24PlusStencil8 => H::D24UnormS8Uint, //TODO: substitute
+
+        // BC compressed formats
+        Tf::Bc1RgbaUnorm => H::Bc1RgbaUnorm,
+        Tf::Bc1RgbaUnormSrgb => H::Bc1RgbaSrgb,
+        Tf::Bc2RgbaUnorm => H::Bc2Unorm,
+        Tf::Bc2RgbaUnormSrgb => H::Bc2Srgb,
+        Tf::Bc3RgbaUnorm => H::Bc3Unorm,
+        Tf::Bc3RgbaUnormSrgb => H::Bc3Srgb,
+        Tf::Bc4RUnorm => H::Bc4Unorm,
+        Tf::Bc4RSnorm => H::Bc4Snorm,
+        Tf::Bc5RgUnorm => H::Bc5Unorm,
+        Tf::Bc5RgSnorm => H::Bc5Snorm,
+        Tf::Bc6hRgbUfloat => H::Bc6hUfloat,
+        Tf::Bc6hRgbSfloat => H::Bc6hSfloat,
+        Tf::Bc7RgbaUnorm => H::Bc7Unorm,
+        Tf::Bc7RgbaUnormSrgb => H::Bc7Srgb,
+
+        // ETC2 and EAC compressed formats
+        Tf::Etc2Rgb8Unorm => H::Etc2R8g8b8Unorm,
+        Tf::Etc2Rgb8UnormSrgb => H::Etc2R8g8b8Srgb,
+        Tf::Etc2Rgb8A1Unorm => H::Etc2R8g8b8a1Unorm,
+        Tf::Etc2Rgb8A1UnormSrgb => H::Etc2R8g8b8a1Srgb,
+        Tf::Etc2Rgba8Unorm => H::Etc2R8g8b8a8Unorm,
+        Tf::Etc2Rgba8UnormSrgb => H::Etc2R8g8b8a8Srgb,
+        Tf::EacR11Unorm => H::EacR11Unorm,
+        Tf::EacR11Snorm => H::EacR11Snorm,
+        Tf::EacRg11Unorm => H::EacR11g11Unorm,
+        Tf::EacRg11Snorm => H::EacR11g11Snorm,
+
+        // ASTC compressed formats
+        Tf::Astc4x4RgbaUnorm => H::Astc4x4Unorm,
+        Tf::Astc4x4RgbaUnormSrgb => H::Astc4x4Srgb,
+        Tf::Astc5x4RgbaUnorm => H::Astc5x4Unorm,
+        Tf::Astc5x4RgbaUnormSrgb => H::Astc5x4Srgb,
+        Tf::Astc5x5RgbaUnorm => H::Astc5x5Unorm,
+        Tf::Astc5x5RgbaUnormSrgb => H::Astc5x5Srgb,
+        Tf::Astc6x5RgbaUnorm => H::Astc6x5Unorm,
+        Tf::Astc6x5RgbaUnormSrgb => H::Astc6x5Srgb,
+        Tf::Astc6x6RgbaUnorm => H::Astc6x6Unorm,
+        Tf::Astc6x6RgbaUnormSrgb => H::Astc6x6Srgb,
+        Tf::Astc8x5RgbaUnorm => H::Astc8x5Unorm,
+        Tf::Astc8x5RgbaUnormSrgb => H::Astc8x5Srgb,
+        Tf::Astc8x6RgbaUnorm => H::Astc8x6Unorm,
+        Tf::Astc8x6RgbaUnormSrgb => H::Astc8x6Srgb,
+        Tf::Astc8x8RgbaUnorm => H::Astc8x8Unorm,
+        Tf::Astc8x8RgbaUnormSrgb => H::Astc8x8Srgb,
+        Tf::Astc10x5RgbaUnorm => H::Astc10x5Unorm,
+        Tf::Astc10x5RgbaUnormSrgb => H::Astc10x5Srgb,
+        Tf::Astc10x6RgbaUnorm => H::Astc10x6Unorm,
+        Tf::Astc10x6RgbaUnormSrgb => H::Astc10x6Srgb,
+        Tf::Astc10x8RgbaUnorm => H::Astc10x8Unorm,
+        Tf::Astc10x8RgbaUnormSrgb => H::Astc10x8Srgb,
+        Tf::Astc10x10RgbaUnorm => H::Astc10x10Unorm,
+        Tf::Astc10x10RgbaUnormSrgb => H::Astc10x10Srgb,
+        Tf::Astc12x10RgbaUnorm => H::Astc12x10Unorm,
+        Tf::Astc12x10RgbaUnormSrgb => H::Astc12x10Srgb,
+        Tf::Astc12x12RgbaUnorm => H::Astc12x12Unorm,
+        Tf::Astc12x12RgbaUnormSrgb => H::Astc12x12Srgb,
     }
 }
 
+pub fn map_hal_texture_format(format: hal::format::Format) -> Option<resource::TextureFormat> {
+    use crate::resource::TextureFormat as Tf;
+    use hal::format::Format as H;
+    Some(match format {
+        // Normal 8 bit formats
+        H::R8Unorm => Tf::R8Unorm,
+        H::R8Snorm => Tf::R8Snorm,
+        H::R8Uint => Tf::R8Uint,
+        H::R8Sint => Tf::R8Sint,
+
+        // Normal 16 bit formats
+        H::R16Unorm => Tf::R16Unorm,
+        H::R16Snorm => Tf::R16Snorm,
+        H::R16Uint => Tf::R16Uint,
+        H::R16Sint => Tf::R16Sint,
+        H::R16Sfloat => Tf::R16Float,
+
+        H::Rg8Unorm => Tf::Rg8Unorm,
+        H::Rg8Snorm => Tf::Rg8Snorm,
+        H::Rg8Uint => Tf::Rg8Uint,
+        H::Rg8Sint => Tf::Rg8Sint,
+
+        // Normal 32 bit formats
+        H::R32Uint => Tf::R32Uint,
+        H::R32Sint => Tf::R32Sint,
+        H::R32Sfloat => Tf::R32Float,
+        H::Rg16Unorm => Tf::Rg16Unorm,
+        H::Rg16Snorm => Tf::Rg16Snorm,
+        H::Rg16Uint => Tf::Rg16Uint,
+        H::Rg16Sint => Tf::Rg16Sint,
+        H::Rg16Sfloat => Tf::Rg16Float,
+        H::Rgba8Unorm => Tf::Rgba8Unorm,
+        H::Rgba8Srgb => Tf::Rgba8UnormSrgb,
+        H::Rgba8Snorm => Tf::Rgba8Snorm,
+        H::Rgba8Uint => Tf::Rgba8Uint,
+        H::Rgba8Sint => Tf::Rgba8Sint,
+        H::Bgra8Unorm => Tf::Bgra8Unorm,
+        H::Bgra8Srgb => Tf::Bgra8UnormSrgb,
+
+        // Packed 32 bit formats
+        H::A2r10g10b10Unorm => Tf::Rgb10a2Unorm,
+        H::B10g11r11Ufloat => Tf::Rg11b10Float,
+
+        // Normal 64 bit formats
+        H::Rg32Uint => Tf::Rg32Uint,
+        H::Rg32Sint => Tf::Rg32Sint,
+        H::Rg32Sfloat => Tf::Rg32Float,
+        H::Rgba16Unorm => Tf::Rgba16Unorm,
+        H::Rgba16Snorm => Tf::Rgba16Snorm,
+        H::Rgba16Uint => Tf::Rgba16Uint,
+        H::Rgba16Sint => Tf::Rgba16Sint,
+        H::Rgba16Sfloat => Tf::Rgba16Float,
+
+        // Normal 128 bit formats
+        H::Rgba32Uint => Tf::Rgba32Uint,
+        H::Rgba32Sint => Tf::Rgba32Sint,
+        H::Rgba32Sfloat => Tf::Rgba32Float,
+
+        _ => return None,
+    })
+}
+
 pub fn map_vertex_format(vertex_format: pipeline::VertexFormat) -> hal::format::Format {
     use crate::pipeline::VertexFormat as Vf;
     use hal::format::Format as H;
@@ -530,6 +673,9 @@ pub fn map_texture_state(
     if usage.contains(W::SAMPLED) {
         access |= A::SHADER_READ;
     }
+    if usage.contains(W::STORAGE_READ) {
+        access |= A::SHADER_READ;
+    }
     if usage.contains(W::STORAGE) {
         access |= A::SHADER_WRITE;
     }
@@ -586,6 +732,22 @@ pub fn map_color_u32(color: &Color) -> [u32; 4] {
     ]
 }
 
+pub fn map_clear_color(format: hal::format::Format, color: &Color) -> hal::command::ClearColor {
+    use hal::format::ChannelType;
+    //TODO: validate sign/unsign and normalized ranges of the color values
+    match format.base_format().1 {
+        ChannelType::Unorm
+        | ChannelType::Snorm
+        | ChannelType::Ufloat
+        | ChannelType::Sfloat
+        | ChannelType::Uscaled
+        | ChannelType::Sscaled
+        | ChannelType::Srgb => hal::command::ClearColor::Sfloat(map_color_f32(color)),
+        ChannelType::Sint => hal::command::ClearColor::Sint(map_color_i32(color)),
+        ChannelType::Uint => hal::command::ClearColor::Uint(map_color_u32(color)),
+    }
+}
+
 pub fn map_filter(filter: resource::FilterMode) -> hal::image::Filter {
     match filter {
         resource::FilterMode::Nearest => hal::image::Filter::Nearest,
@@ -1,12 +1,12 @@
-#[cfg(not(feature = "remote"))]
 use crate::instance::Limits;
+use crate::instance::{texture_format_features, TextureFormatFeatureFlags, TextureFormatFeatures};
 use crate::{
     binding_model,
     command,
     conv,
     gfx_select,
-    hub::{GfxBackend, Token, GLOBAL},
-    id::{Input, Output},
+    hub::{GfxBackend, Global, Hub, Storage, Token, GLOBAL},
+    id::Input,
     pipeline,
     resource,
     swap_chain,
@@ -22,6 +22,8 @@ use crate::{
     CommandEncoderId,
     ComputePipelineId,
     DeviceId,
+    Extensions,
+    Extent3d,
     LifeGuard,
     PipelineLayoutId,
     QueueId,
@@ -48,25 +50,35 @@ use hal::{
     Device as _,
     Surface as _,
 };
-use log::{info, trace};
+use log::{info, trace, warn};
 use parking_lot::Mutex;
 use rendy_descriptor::{DescriptorAllocator, DescriptorRanges, DescriptorSet};
 use rendy_memory::{Block, Heaps, MemoryBlock};
 
-#[cfg(not(feature = "remote"))]
-use std::marker::PhantomData;
 use std::{
     collections::hash_map::Entry,
     ffi,
+    future::Future,
     iter,
-    ops::Range,
+    marker::PhantomData,
+    mem,
+    ops::{Deref, DerefMut, Range},
+    pin::Pin,
     ptr,
     slice,
-    sync::atomic::{AtomicBool, Ordering},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll, Wake, Waker},
+    thread,
 };
 
 
 const CLEANUP_WAIT_MS: u64 = 5000;
+/// How often the maintenance thread checks if it's asked to stop
+/// while waiting for the GPU.
+const MAINTENANCE_WAIT_MS: u64 = 100;
 pub const MAX_COLOR_TARGETS: usize = 4;
 pub const MAX_MIP_LEVELS: usize = 16;
 pub const MAX_VERTEX_BUFFERS: usize = 8;
@@ -95,6 +107,16 @@ pub fn all_image_stages() -> hal::pso::PipelineStage {
         | Ps::TRANSFER
 }
 
+/// Pipeline stages that can be used in barriers on a queue of the given type.
+pub fn queue_stages(ty: QueueType) -> hal::pso::PipelineStage {
+    use hal::pso::PipelineStage as Ps;
+    match ty {
+        QueueType::General => all_buffer_stages() | all_image_stages(),
+        QueueType::Compute => Ps::DRAW_INDIRECT | Ps::COMPUTE_SHADER | Ps::TRANSFER | Ps::HOST,
+        QueueType::Transfer => Ps::TRANSFER | Ps::HOST,
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 enum HostMap {
     Read,
@@ -137,22 +159,55 @@ enum ResourceId {
 }
 
 #[derive(Debug)]
-enum NativeResource<B: hal::Backend> {
+pub(crate) enum NativeResource<B: hal::Backend> {
     Buffer(B::Buffer, MemoryBlock<B>),
     Image(B::Image, MemoryBlock<B>),
     ImageView(B::ImageView),
     Framebuffer(B::Framebuffer),
     DescriptorSet(DescriptorSet<B>),
+    /// A reference to the shared buffer of zeros, destroyed with the last one.
+    Zeros(Arc<ZeroBuffer<B>>),
+}
+
+/// A host-visible buffer filled with zeros, used as the source of the
+/// zero-initialization copies on all the queues.
+#[derive(Debug)]
+pub(crate) struct ZeroBuffer<B: hal::Backend> {
+    raw: B::Buffer,
+    memory: MemoryBlock<B>,
+    size: BufferAddress,
 }
 
 #[derive(Debug)]
 struct ActiveSubmission<B: hal::Backend> {
     index: SubmissionIndex,
-    fence: B::Fence,
+    queue_index: QueueIndex,
+    /// Shared with the threads waiting on it without holding the locks.
+    fence: Arc<B::Fence>,
+    semaphores: Vec<B::Semaphore>,
     // Note: we keep the associated ID here in order to be able to check
     // at any point what resources are used in a submission.
     resources: Vec<(Option<ResourceId>, NativeResource<B>)>,
     mapped: Vec<BufferId>,
+    work_done: Vec<SubmittedWorkDoneClosure>,
+}
+
+pub type SubmittedWorkDoneCallback = extern "C" fn(userdata: *mut u8);
+
+#[derive(Debug)]
+struct SubmittedWorkDoneClosure {
+    callback: SubmittedWorkDoneCallback,
+    userdata: *mut u8,
+}
+
+unsafe impl Send for SubmittedWorkDoneClosure {}
+
+/// The user callbacks collected by `Device::maintain`, to be fired
+/// after all the locks are released.
+#[derive(Debug, Default)]
+struct MaintainCallbacks {
+    mappings: Vec<BufferMapPendingCallback>,
+    work_done: Vec<SubmittedWorkDoneClosure>,
 }
 
 /// A struct responsible for tracking resource lifetimes.
@@ -179,6 +234,10 @@ struct PendingResources<B: hal::Backend> {
     /// actual deletion.
     free: Vec<NativeResource<B>>,
     ready_to_map: Vec<BufferId>,
+    /// Work-done callbacks of the retired submissions.
+    ready_work_done: Vec<SubmittedWorkDoneClosure>,
+    /// Fences of the retired submissions that are still being waited on.
+    retired_fences: Vec<Arc<B::Fence>>,
 }
 
 impl<B: GfxBackend> PendingResources<B> {
@@ -194,6 +253,22 @@ impl<B: GfxBackend> PendingResources<B> {
         });
     }
 
+    /// Get the fence of the oldest active submission, if any,
+    /// to be waited on after the lock is released.
+    fn oldest_fence(&self) -> Option<Arc<B::Fence>> {
+        self.active.first().map(|a| Arc::clone(&a.fence))
+    }
+
+    /// Get the fences of the submissions up to and including `index`,
+    /// to be waited on after the lock is released.
+    fn fences_until(&self, index: SubmissionIndex) -> Vec<Arc<B::Fence>> {
+        self.active
+            .iter()
+            .take_while(|a| a.index <= index)
+            .map(|a| Arc::clone(&a.fence))
+            .collect()
+    }
+
     /// Returns the last submission index that is done.
     fn cleanup(
         &mut self,
@@ -205,7 +280,7 @@ impl<B: GfxBackend> PendingResources<B> {
         if force_wait && !self.active.is_empty() {
             let status = unsafe {
                 device.wait_for_fences(
-                    self.active.iter().map(|a| &a.fence),
+                    self.active.iter().map(|a| &*a.fence),
                     hal::device::WaitFor::All,
                     CLEANUP_WAIT_MS * 1_000_000,
                 )
@@ -223,15 +298,25 @@ impl<B: GfxBackend> PendingResources<B> {
         let last_done = if done_count != 0 {
             self.active[done_count - 1].index
         } else {
-            return 0;
+            0
         };
 
         for a in self.active.drain(.. done_count) {
             trace!("Active submission {} is done", a.index);
             self.free.extend(a.resources.into_iter().map(|(_, r)| r));
             self.ready_to_map.extend(a.mapped);
+            self.ready_work_done.extend(a.work_done);
+            self.retired_fences.push(a.fence);
             unsafe {
-                device.destroy_fence(a.fence);
+                for semaphore in a.semaphores {
+                    device.destroy_semaphore(semaphore);
+                }
+            }
+        }
+        for fence in mem::take(&mut self.retired_fences) {
+            match Arc::try_unwrap(fence) {
+                Ok(fence) => unsafe { device.destroy_fence(fence) },
+                Err(fence) => self.retired_fences.push(fence),
             }
         }
 
@@ -256,13 +341,26 @@ impl<B: GfxBackend> PendingResources<B> {
                 NativeResource::DescriptorSet(raw) => unsafe {
                     descriptor_allocator.free(iter::once(raw));
                 },
+                NativeResource::Zeros(zeros) => {
+                    if let Ok(zeros) = Arc::try_unwrap(zeros) {
+                        unsafe {
+                            device.destroy_buffer(zeros.raw);
+                        }
+                        heaps.free(device, zeros.memory);
+                    }
+                }
             }
         }
 
         last_done
     }
 
-    fn triage_referenced(&mut self, trackers: &mut TrackerSet, mut token: &mut Token<Device<B>>) {
+    fn triage_referenced(
+        &mut self,
+        hub: &Hub<B>,
+        trackers: &mut TrackerSet,
+        mut token: &mut Token<Device<B>>,
+    ) {
         // Before destruction, a resource is expected to have the following strong refs:
         //  - in resource itself
         //  - in the device tracker
@@ -273,7 +371,6 @@ impl<B: GfxBackend> PendingResources<B> {
             return;
         }
 
-        let hub = B::hub();
         //TODO: lock less, if possible
         let (mut bind_group_guard, mut token) = hub.bind_groups.write(&mut token);
         let (mut buffer_guard, mut token) = hub.buffers.write(&mut token);
@@ -291,23 +388,22 @@ impl<B: GfxBackend> PendingResources<B> {
                 );
                 let (life_guard, resource) = match resource_id {
                     ResourceId::Buffer(id) => {
-                        if buffer_guard[id].pending_map_operation.is_some() {
+                        if !buffer_guard[id].pending_map_operations.is_empty() {
                             continue;
                         }
                         trackers.buffers.remove(id);
                         let buf = buffer_guard.remove(id);
-                        #[cfg(not(feature = "remote"))]
-                        hub.buffers.identity.lock().free(id);
+                        hub.buffers.identity.free(id);
                         (buf.life_guard, NativeResource::Buffer(buf.raw, buf.memory))
                     }
                     ResourceId::Texture(id) => {
                         trackers.textures.remove(id);
                         let tex = texture_guard.remove(id);
-                        #[cfg(not(feature = "remote"))]
-                        hub.textures.identity.lock().free(id);
+                        hub.textures.identity.free(id);
                         let memory = match tex.placement {
                             // swapchain-owned images don't need explicit destruction
-                            resource::TexturePlacement::SwapChain(_) => continue,
+                            resource::TexturePlacement::SwapChain(_, None) => continue,
+                            resource::TexturePlacement::SwapChain(_, Some(mem)) |
                             resource::TexturePlacement::Memory(mem) => mem,
                         };
                         (tex.life_guard, NativeResource::Image(tex.raw, memory))
@@ -315,15 +411,13 @@ impl<B: GfxBackend> PendingResources<B> {
                     ResourceId::TextureView(id) => {
                         trackers.views.remove(id);
                         let view = teview_view_guard.remove(id);
-                        #[cfg(not(feature = "remote"))]
-                        hub.texture_views.identity.lock().free(id);
+                        hub.texture_views.identity.free(id);
                         (view.life_guard, NativeResource::ImageView(view.raw))
                     }
                     ResourceId::BindGroup(id) => {
                         trackers.bind_groups.remove(id);
                         let bind_group = bind_group_guard.remove(id);
-                        #[cfg(not(feature = "remote"))]
-                        hub.bind_groups.identity.lock().free(id);
+                        hub.bind_groups.identity.free(id);
                         (
                             bind_group.life_guard,
                             NativeResource::DescriptorSet(bind_group.raw),
@@ -342,11 +436,11 @@ impl<B: GfxBackend> PendingResources<B> {
         }
     }
 
-    fn triage_mapped(&mut self, token: &mut Token<Device<B>>) {
+    fn triage_mapped(&mut self, hub: &Hub<B>, token: &mut Token<Device<B>>) {
         if self.mapped.is_empty() {
             return;
         }
-        let (buffer_guard, _) = B::hub().buffers.read(token);
+        let (buffer_guard, _) = hub.buffers.read(token);
 
         for stored in self.mapped.drain(..) {
             let resource_id = stored.value;
@@ -370,10 +464,11 @@ impl<B: GfxBackend> PendingResources<B> {
 
     fn triage_framebuffers(
         &mut self,
+        hub: &Hub<B>,
         framebuffers: &mut FastHashMap<FramebufferKey, B::Framebuffer>,
         token: &mut Token<Device<B>>,
     ) {
-        let (texture_view_guard, _) = B::hub().texture_views.read(token);
+        let (texture_view_guard, _) = hub.texture_views.read(token);
         let remove_list = framebuffers
             .keys()
             .filter_map(|key| {
@@ -408,29 +503,34 @@ impl<B: GfxBackend> PendingResources<B> {
 
     fn handle_mapping(
         &mut self,
+        hub: &Hub<B>,
         raw: &B::Device,
         token: &mut Token<Device<B>>,
     ) -> Vec<BufferMapPendingCallback> {
         if self.ready_to_map.is_empty() {
             return Vec::new();
         }
-        let (mut buffer_guard, _) = B::hub().buffers.write(token);
-        self.ready_to_map
-            .drain(..)
-            .map(|buffer_id| {
-                let buffer = &mut buffer_guard[buffer_id];
-                let operation = buffer.pending_map_operation.take().unwrap();
-                let result = match operation {
-                    BufferMapOperation::Read(ref range, ..) => {
-                        map_buffer(raw, buffer, range.clone(), HostMap::Read)
+        let (mut buffer_guard, _) = hub.buffers.write(token);
+        let mut callbacks = Vec::new();
+        // Note: a buffer with several pending operations can be listed multiple
+        // times, in which case all of them are handled on the first entry.
+        for buffer_id in self.ready_to_map.drain(..) {
+            let buffer = &mut buffer_guard[buffer_id];
+            let operations = mem::take(&mut buffer.pending_map_operations);
+            for operation in operations {
+                let kind = match operation {
+                    BufferMapOperation::Read(..) | BufferMapOperation::ReadFuture(..) => {
+                        HostMap::Read
                     }
-                    BufferMapOperation::Write(ref range, ..) => {
-                        map_buffer(raw, buffer, range.clone(), HostMap::Write)
+                    BufferMapOperation::Write(..) | BufferMapOperation::WriteFuture(..) => {
+                        HostMap::Write
                     }
                 };
-                (operation, result)
-            })
-            .collect()
+                let result = map_buffer(raw, buffer, operation.range(), kind);
+                callbacks.push((operation, result));
+            }
+        }
+        callbacks
     }
 }
 
@@ -447,42 +547,131 @@ fn map_buffer<B: hal::Backend>(
         .memory
         .properties()
         .contains(hal::memory::Properties::COHERENT);
+    // Map the whole buffer first, so that all the mapped sub-ranges
+    // share the same mapping and don't invalidate each other.
+    buffer.memory.map(raw, 0 .. align_buffer_size(buffer.size))?;
+    // The uninitialized parts of the range are zeroed on the host side,
+    // taking the whole words they start and end in, which are not
+    // initialized either.
+    let mask = command::COPY_BUFFER_ALIGNMENT - 1;
+    let zero_range = buffer_range.start & !mask .. align_buffer_size(buffer_range.end);
+    for range in buffer.uninitialized.lock().take(zero_range) {
+        let (ptr, mapped_range) = {
+            let mapped = buffer.memory.map(raw, range.clone())?;
+            (mapped.ptr(), mapped.range())
+        };
+        unsafe {
+            ptr::write_bytes(ptr.as_ptr(), 0, (range.end - range.start) as usize);
+            if !is_coherent {
+                raw.flush_mapped_memory_ranges(iter::once((buffer.memory.memory(), mapped_range)))
+                    .unwrap();
+            }
+        }
+    }
     let (ptr, mapped_range) = {
-        let mapped = buffer.memory.map(raw, buffer_range)?;
+        let mapped = buffer.memory.map(raw, buffer_range.clone())?;
         (mapped.ptr(), mapped.range())
     };
 
-    if !is_coherent {
-        match kind {
-            HostMap::Read => unsafe {
+    let flush_range = match kind {
+        _ if is_coherent => None,
+        HostMap::Read => {
+            unsafe {
                 raw.invalidate_mapped_memory_ranges(iter::once((
                     buffer.memory.memory(),
                     mapped_range,
                 )))
                 .unwrap();
-            },
-            HostMap::Write => {
-                buffer.mapped_write_ranges.push(mapped_range);
             }
+            None
         }
-    }
+        HostMap::Write => Some(mapped_range),
+    };
+    buffer.mapped_ranges.push(resource::BufferMappedRange {
+        range: buffer_range,
+        ptr: ptr.as_ptr(),
+        flush_range,
+    });
 
     Ok(ptr.as_ptr())
 }
 
+/// Round the size up to a multiple of `COPY_BUFFER_ALIGNMENT`, so that
+/// the whole buffer can be zero-initialized with word fills.
+fn align_buffer_size(size: BufferAddress) -> BufferAddress {
+    let mask = command::COPY_BUFFER_ALIGNMENT - 1;
+    (size + mask) & !mask
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum QueueType {
+    General = 0,
+    Compute = 1,
+    Transfer = 2,
+}
+
+impl QueueType {
+    pub(crate) fn to_hal(self) -> hal::QueueType {
+        match self {
+            QueueType::General => hal::QueueType::General,
+            QueueType::Compute => hal::QueueType::Compute,
+            QueueType::Transfer => hal::QueueType::Transfer,
+        }
+    }
+}
+
+/// Index of a queue within its device.
+/// The general queue is always at zero, followed by the dedicated queues.
+pub(crate) type QueueIndex = usize;
+
+/// A compute-only or transfer-only queue opened alongside the general one.
+#[derive(Debug)]
+pub(crate) struct DedicatedQueue<B: hal::Backend> {
+    pub(crate) raw: B::CommandQueue,
+    pub(crate) ty: QueueType,
+    pub(crate) family: hal::queue::QueueFamilyId,
+    pub(crate) com_allocator: command::CommandAllocator<B>,
+}
+
+/// Data uploads recorded by `queue_write_*`, to be executed
+/// right before the next submission on the queue.
+#[derive(Debug)]
+struct PendingWrites<B: hal::Backend> {
+    command_buffer: command::CommandBuffer<B>,
+    staging: Vec<NativeResource<B>>,
+}
+
+/// A handle to one of the queues of a device.
+#[derive(Debug)]
+pub struct Queue {
+    pub(crate) device_id: Stored<DeviceId>,
+    pub(crate) index: QueueIndex,
+}
+
 #[derive(Debug)]
 pub struct Device<B: hal::Backend> {
-    pub(crate) raw: B::Device,
+    /// Shared with the threads waiting on the fences without holding the locks.
+    pub(crate) raw: Arc<B::Device>,
     pub(crate) adapter_id: AdapterId,
     pub(crate) queue_group: hal::QueueGroup<B, hal::General>,
+    pub(crate) dedicated_queues: Vec<DedicatedQueue<B>>,
+    pub(crate) extensions: Extensions,
+    /// Features enabled on the raw device.
+    pub(crate) features: hal::Features,
     pub(crate) com_allocator: command::CommandAllocator<B>,
     mem_allocator: Mutex<Heaps<B>>,
-    desc_allocator: Mutex<DescriptorAllocator<B>>,
+    pub(crate) desc_allocator: Mutex<DescriptorAllocator<B>>,
     life_guard: LifeGuard,
     pub(crate) trackers: Mutex<TrackerSet>,
     pub(crate) render_passes: Mutex<FastHashMap<RenderPassKey, B::RenderPass>>,
     pub(crate) framebuffers: Mutex<FastHashMap<FramebufferKey, B::Framebuffer>>,
     pending: Mutex<PendingResources<B>>,
+    pending_writes: Mutex<FastHashMap<QueueIndex, PendingWrites<B>>>,
+    queue_ids: Mutex<FastHashMap<QueueIndex, QueueId>>,
+    zeros: Mutex<Option<Arc<ZeroBuffer<B>>>>,
+    pub(crate) mipmap_pipelines: Mutex<Option<command::MipmapPipelines<B>>>,
+    pub(crate) maintenance: Mutex<Option<MaintenanceThread>>,
 }
 
 impl<B: GfxBackend> Device<B> {
@@ -490,7 +679,10 @@ impl<B: GfxBackend> Device<B> {
         raw: B::Device,
         adapter_id: AdapterId,
         queue_group: hal::QueueGroup<B, hal::General>,
+        dedicated_queues: Vec<DedicatedQueue<B>>,
         mem_props: hal::MemoryProperties,
+        extensions: Extensions,
+        features: hal::Features,
     ) -> Self {
         // don't start submission index at zero
         let life_guard = LifeGuard::new();
@@ -519,12 +711,19 @@ impl<B: GfxBackend> Device<B> {
         };
 
         Device {
-            raw,
+            raw: Arc::new(raw),
             adapter_id,
-            com_allocator: command::CommandAllocator::new(queue_group.family()),
+            com_allocator: command::CommandAllocator::new(
+                queue_group.family(),
+                0,
+                QueueType::General,
+            ),
             mem_allocator: Mutex::new(heaps),
             desc_allocator: Mutex::new(DescriptorAllocator::new()),
             queue_group,
+            dedicated_queues,
+            extensions,
+            features,
             life_guard,
             trackers: Mutex::new(TrackerSet::new(B::VARIANT)),
             render_passes: Mutex::new(FastHashMap::default()),
@@ -535,24 +734,39 @@ impl<B: GfxBackend> Device<B> {
                 active: Vec::new(),
                 free: Vec::new(),
                 ready_to_map: Vec::new(),
+                ready_work_done: Vec::new(),
+                retired_fences: Vec::new(),
             }),
+            pending_writes: Mutex::new(FastHashMap::default()),
+            queue_ids: Mutex::new(FastHashMap::default()),
+            zeros: Mutex::new(None),
+            mipmap_pipelines: Mutex::new(None),
+            maintenance: Mutex::new(None),
         }
     }
 
-    fn maintain(&self, force_wait: bool, token: &mut Token<Self>) -> Vec<BufferMapPendingCallback> {
+    fn maintain(
+        &self,
+        hub: &Hub<B>,
+        force_wait: bool,
+        token: &mut Token<Self>,
+    ) -> MaintainCallbacks {
         let mut pending = self.pending.lock();
         let mut trackers = self.trackers.lock();
 
-        pending.triage_referenced(&mut *trackers, token);
-        pending.triage_mapped(token);
-        pending.triage_framebuffers(&mut *self.framebuffers.lock(), token);
+        pending.triage_referenced(hub, &mut *trackers, token);
+        pending.triage_mapped(hub, token);
+        pending.triage_framebuffers(hub, &mut *self.framebuffers.lock(), token);
         let last_done = pending.cleanup(
             &self.raw,
             &self.mem_allocator,
             &self.desc_allocator,
             force_wait,
         );
-        let callbacks = pending.handle_mapping(&self.raw, token);
+        let callbacks = MaintainCallbacks {
+            mappings: pending.handle_mapping(hub, &self.raw, token),
+            work_done: pending.ready_work_done.drain(..).collect(),
+        };
 
         unsafe {
             self.desc_allocator.lock().cleanup(&self.raw);
@@ -560,13 +774,131 @@ impl<B: GfxBackend> Device<B> {
 
         if last_done != 0 {
             self.com_allocator.maintain(last_done);
+            for queue in &self.dedicated_queues {
+                queue.com_allocator.maintain(last_done);
+            }
         }
 
         callbacks
     }
 
-    //Note: this logic is specifically moved out of `handle_mapping()` in order to
+    /// Wake the maintenance thread up, if any, to handle new work.
+    fn wake_maintenance(&self) {
+        if let Some(ref maintenance) = *self.maintenance.lock() {
+            maintenance.handle.thread().unpark();
+        }
+    }
+
+    pub(crate) fn queue_type(&self, index: QueueIndex) -> QueueType {
+        match index {
+            0 => QueueType::General,
+            _ => self.dedicated_queues[index - 1].ty,
+        }
+    }
+
+    pub(crate) fn queue_family(&self, index: QueueIndex) -> hal::queue::QueueFamilyId {
+        match index {
+            0 => self.queue_group.family(),
+            _ => self.dedicated_queues[index - 1].family,
+        }
+    }
+
+    pub(crate) fn command_allocator(&self, index: QueueIndex) -> &command::CommandAllocator<B> {
+        match index {
+            0 => &self.com_allocator,
+            _ => &self.dedicated_queues[index - 1].com_allocator,
+        }
+    }
+
+    unsafe fn raw_queue_mut(&mut self, index: QueueIndex) -> &mut B::CommandQueue {
+        match index {
+            0 => self.queue_group.queues[0].as_raw_mut(),
+            _ => &mut self.dedicated_queues[index - 1].raw,
+        }
+    }
+
+    /// Destroy the native objects of a swap chain. Its images and views
+    /// are destroyed along with the other textures.
+    pub(crate) fn dispose_swap_chain(&self, swap_chain: swap_chain::SwapChain<B>) {
+        unsafe {
+            for frame in swap_chain.frames {
+                self.raw.destroy_fence(frame.fence);
+                self.raw.destroy_semaphore(frame.sem_available);
+                self.raw.destroy_semaphore(frame.sem_present);
+            }
+            self.raw.destroy_semaphore(swap_chain.sem_available);
+            self.raw
+                .destroy_command_pool(swap_chain.command_pool.into_raw());
+            if let Some(raw) = swap_chain.raw {
+                self.raw.destroy_swapchain(raw);
+            }
+        }
+        if let Some(headless) = swap_chain.headless {
+            self.pending
+                .lock()
+                .free
+                .push(NativeResource::Buffer(headless.readback, headless.memory));
+        }
+    }
+
+    /// Destroy the device, once the objects created from it are destroyed.
+    fn dispose(self) {
+        let mut pending = self.pending.into_inner();
+        pending.referenced.clear();
+        pending.mapped.clear();
+        for (_, writes) in self.pending_writes.into_inner() {
+            pending.free.extend(writes.staging);
+        }
+        pending
+            .free
+            .extend(self.zeros.into_inner().map(NativeResource::Zeros));
+        pending.cleanup(&self.raw, &self.mem_allocator, &self.desc_allocator, true);
+        // the retired fences are signaled, so the threads still waiting on them
+        // are about to release them
+        for mut fence in pending.retired_fences.drain(..) {
+            let fence = loop {
+                match Arc::try_unwrap(fence) {
+                    Ok(fence) => break fence,
+                    Err(shared) => {
+                        fence = shared;
+                        thread::yield_now();
+                    }
+                }
+            };
+            unsafe {
+                self.raw.destroy_fence(fence);
+            }
+        }
+        if let Some(pipelines) = self.mipmap_pipelines.into_inner() {
+            pipelines.destroy(&self.raw);
+        }
+        unsafe {
+            for (_, framebuffer) in self.framebuffers.into_inner() {
+                self.raw.destroy_framebuffer(framebuffer);
+            }
+            for (_, render_pass) in self.render_passes.into_inner() {
+                self.raw.destroy_render_pass(render_pass);
+            }
+        }
+        self.com_allocator.destroy(&self.raw);
+        for queue in self.dedicated_queues {
+            queue.com_allocator.destroy(&self.raw);
+        }
+        unsafe {
+            self.desc_allocator.into_inner().dispose(&self.raw);
+        }
+        self.mem_allocator.into_inner().dispose(&self.raw);
+    }
+
+    //Note: this logic is specifically moved out of `maintain()` in order to
     // have nothing locked by the time we execute users callback code.
+    fn fire_callbacks(callbacks: MaintainCallbacks) {
+        Self::fire_map_callbacks(callbacks.mappings);
+        for closure in callbacks.work_done {
+            (closure.callback)(closure.userdata);
+        }
+    }
+
     fn fire_map_callbacks<I: IntoIterator<Item = BufferMapPendingCallback>>(callbacks: I) {
         for (operation, result) in callbacks {
             let (status, ptr) = match result {
@@ -576,9 +908,19 @@ impl<B: GfxBackend> Device<B> {
                     (BufferMapAsyncStatus::Error, ptr::null_mut())
                 }
             };
+            let future_result = match status {
+                BufferMapAsyncStatus::Success => Ok(ptr),
+                other => Err(other),
+            };
             match operation {
                 BufferMapOperation::Read(_, on_read, userdata) => on_read(status, ptr, userdata),
                 BufferMapOperation::Write(_, on_write, userdata) => on_write(status, ptr, userdata),
+                BufferMapOperation::ReadFuture(_, shared) => {
+                    resource::complete_map_future(&shared, future_result)
+                }
+                BufferMapOperation::WriteFuture(_, shared) => {
+                    resource::complete_map_future(&shared, future_result)
+                }
             }
         }
     }
@@ -590,6 +932,9 @@ impl<B: GfxBackend> Device<B> {
     ) -> resource::Buffer<B> {
         debug_assert_eq!(self_id.backend(), B::VARIANT);
         let (usage, _memory_properties) = conv::map_buffer_usage(desc.usage);
+        // uninitialized contents are zeroed with transfers, on whole words
+        let usage = usage | hal::buffer::Usage::TRANSFER_DST;
+        let aligned_size = align_buffer_size(desc.size);
 
         let rendy_usage = {
             use rendy_memory::MemoryUsageValue as Muv;
@@ -606,7 +951,7 @@ impl<B: GfxBackend> Device<B> {
             }
         };
 
-        let mut buffer = unsafe { self.raw.create_buffer(desc.size, usage).unwrap() };
+        let mut buffer = unsafe { self.raw.create_buffer(aligned_size, usage).unwrap() };
         let requirements = unsafe { self.raw.get_buffer_requirements(&buffer) };
         let memory = self
             .mem_allocator
@@ -634,18 +979,401 @@ impl<B: GfxBackend> Device<B> {
             },
             memory,
             size: desc.size,
-            mapped_write_ranges: Vec::new(),
-            pending_map_operation: None,
+            usage: desc.usage,
+            uninitialized: Mutex::new(resource::UninitializedRanges::new(0 .. aligned_size)),
+            mapped_ranges: Vec::new(),
+            pending_map_operations: Vec::new(),
             life_guard: LifeGuard::new(),
         }
     }
 
+    fn pending_writes_mut<'a>(
+        &self,
+        self_id: DeviceId,
+        pending_writes: &'a mut FastHashMap<QueueIndex, PendingWrites<B>>,
+        queue_index: QueueIndex,
+    ) -> &'a mut PendingWrites<B> {
+        pending_writes.entry(queue_index).or_insert_with(|| {
+            let dev_stored = Stored {
+                value: self_id,
+                ref_count: self.life_guard.ref_count.clone(),
+            };
+            let mut command_buffer = self
+                .command_allocator(queue_index)
+                .allocate(dev_stored, &self.raw);
+            unsafe {
+                command_buffer.raw.last_mut().unwrap().begin(
+                    hal::command::CommandBufferFlags::ONE_TIME_SUBMIT,
+                    hal::command::CommandBufferInheritanceInfo::default(),
+                );
+            }
+            PendingWrites {
+                command_buffer,
+                staging: Vec::new(),
+            }
+        })
+    }
+
+    /// Create a host-visible buffer containing `data`, to be used as
+    /// a source of the transfer operations.
+    ///
+    /// Only the buffer object is created per call: the memory is sub-allocated
+    /// by the linear allocator of the upload heaps, which already works as a ring
+    /// of large blocks that are recycled once the submissions using them are done.
+    fn create_staging_buffer(&self, data: &[u8]) -> (B::Buffer, MemoryBlock<B>) {
+        self.create_upload_buffer(data.len() as BufferAddress, |ptr| unsafe {
+            ptr::copy_nonoverlapping(data.as_ptr(), ptr, data.len())
+        })
+    }
+
+    /// Create a host-visible buffer of `size` bytes, with the contents
+    /// written by `fill` through the mapped pointer.
+    fn create_upload_buffer(
+        &self,
+        size: BufferAddress,
+        fill: impl FnOnce(*mut u8),
+    ) -> (B::Buffer, MemoryBlock<B>) {
+        let mut buffer = unsafe {
+            self.raw
+                .create_buffer(size, hal::buffer::Usage::TRANSFER_SRC)
+                .unwrap()
+        };
+        let requirements = unsafe { self.raw.get_buffer_requirements(&buffer) };
+        let mut memory = self
+            .mem_allocator
+            .lock()
+            .allocate(
+                &self.raw,
+                requirements.type_mask as u32,
+                rendy_memory::MemoryUsageValue::Upload,
+                requirements.size,
+                requirements.alignment,
+            )
+            .unwrap();
+
+        unsafe {
+            self.raw
+                .bind_buffer_memory(memory.memory(), memory.range().start, &mut buffer)
+                .unwrap()
+        };
+
+        let is_coherent = memory
+            .properties()
+            .contains(hal::memory::Properties::COHERENT);
+        let (ptr, mapped_range) = {
+            let mapped = memory.map(&self.raw, 0 .. size).unwrap();
+            (mapped.ptr(), mapped.range())
+        };
+        fill(ptr.as_ptr());
+        unsafe {
+            if !is_coherent {
+                self.raw
+                    .flush_mapped_memory_ranges(iter::once((memory.memory(), mapped_range)))
+                    .unwrap();
+            }
+        }
+        memory.unmap(&self.raw);
+
+        (buffer, memory)
+    }
+
+    /// Get the buffer of zeros, growing it to at least `size` bytes.
+    ///
+    /// The buffer is shared by all the zero-initialization copies, and each of
+    /// the pending writes using it keeps a reference until its submission is done.
+    fn zeros(&self, writes: &mut PendingWrites<B>, size: BufferAddress) -> Arc<ZeroBuffer<B>> {
+        let mut zeros = self.zeros.lock();
+        let is_large_enough = match *zeros {
+            Some(ref zeros) => zeros.size >= size,
+            None => false,
+        };
+        if !is_large_enough {
+            let (raw, memory) = self.create_upload_buffer(size, |ptr| unsafe {
+                ptr::write_bytes(ptr, 0, size as usize)
+            });
+            let old = zeros.replace(Arc::new(ZeroBuffer { raw, memory, size }));
+            writes.staging.extend(old.map(NativeResource::Zeros));
+        }
+        let zeros = Arc::clone(zeros.as_ref().unwrap());
+        writes
+            .staging
+            .push(NativeResource::Zeros(Arc::clone(&zeros)));
+        zeros
+    }
+
+    /// Create a host-visible buffer of `size` bytes, to be used as
+    /// a destination of the transfer operations that are read back.
+    fn create_readback_buffer(&self, size: BufferAddress) -> (B::Buffer, MemoryBlock<B>) {
+        let mut buffer = unsafe {
+            self.raw
+                .create_buffer(size, hal::buffer::Usage::TRANSFER_DST)
+                .unwrap()
+        };
+        let requirements = unsafe { self.raw.get_buffer_requirements(&buffer) };
+        let memory = self
+            .mem_allocator
+            .lock()
+            .allocate(
+                &self.raw,
+                requirements.type_mask as u32,
+                rendy_memory::MemoryUsageValue::Download,
+                requirements.size,
+                requirements.alignment,
+            )
+            .unwrap();
+
+        unsafe {
+            self.raw
+                .bind_buffer_memory(memory.memory(), memory.range().start, &mut buffer)
+                .unwrap()
+        };
+        (buffer, memory)
+    }
+
+    /// Record zeroing the `ranges` of a buffer into the pending writes.
+    fn zero_buffer(
+        &self,
+        writes: &mut PendingWrites<B>,
+        buffer_guard: &Storage<resource::Buffer<B>, BufferId>,
+        buffer_id: BufferId,
+        ranges: Vec<Range<BufferAddress>>,
+    ) {
+        // filling buffers on transfer queues requires Vulkan 1.1,
+        // so the zeros are copied there instead
+        let zeros = if writes.command_buffer.queue_type == QueueType::Transfer {
+            let size = ranges.iter().map(|range| range.end - range.start).max();
+            Some(self.zeros(writes, size.unwrap_or(0)))
+        } else {
+            None
+        };
+        let cmb = &mut writes.command_buffer;
+        let (buffer, pending) = cmb.trackers.buffers.use_replace(
+            buffer_guard,
+            buffer_id,
+            (),
+            resource::BufferUsage::COPY_DST,
+        );
+        let barriers = pending.map(|pending| hal::memory::Barrier::Buffer {
+            states: pending.to_states(),
+            target: &buffer.raw,
+            families: None,
+            range: None .. None,
+        });
+        let stages = all_buffer_stages() & queue_stages(cmb.queue_type);
+        let cmb_raw = cmb.raw.last_mut().unwrap();
+        unsafe {
+            cmb_raw.pipeline_barrier(
+                stages .. stages,
+                hal::memory::Dependencies::empty(),
+                barriers,
+            );
+            match zeros {
+                Some(zeros) => {
+                    let regions = ranges.into_iter().map(|range| hal::command::BufferCopy {
+                        src: 0,
+                        dst: range.start,
+                        size: range.end - range.start,
+                    });
+                    cmb_raw.copy_buffer(&zeros.raw, &buffer.raw, regions);
+                }
+                None => {
+                    for range in ranges {
+                        cmb_raw.fill_buffer(&buffer.raw, range, 0);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Record zeroing the subresource `ranges` of a texture into the pending writes.
+    fn zero_texture(
+        &self,
+        writes: &mut PendingWrites<B>,
+        texture_guard: &Storage<resource::Texture<B>, TextureId>,
+        texture_id: TextureId,
+        ranges: Vec<hal::image::SubresourceRange>,
+    ) {
+        let cmb = &mut writes.command_buffer;
+        let mut barriers = Vec::new();
+        for range in &ranges {
+            let (texture, pending) = cmb.trackers.textures.use_replace(
+                texture_guard,
+                texture_id,
+                range.clone(),
+                resource::TextureUsage::COPY_DST,
+            );
+            barriers.extend(pending.map(|pending| hal::memory::Barrier::Image {
+                states: pending.to_states(),
+                target: &texture.raw,
+                families: None,
+                range: pending.selector,
+            }));
+        }
+        let texture = &texture_guard[texture_id];
+        let stages = all_image_stages() & queue_stages(cmb.queue_type);
+        let cmb_raw = cmb.raw.last_mut().unwrap();
+        unsafe {
+            cmb_raw.pipeline_barrier(
+                stages .. stages,
+                hal::memory::Dependencies::empty(),
+                barriers,
+            );
+        }
+
+        let format_desc = conv::map_texture_format(texture.format).surface_desc();
+        // Compressed images can't be cleared, and transfer queues can't clear
+        // any images, so the zeros are copied from a buffer instead.
+        // Depth-stencil images can't be copied to on these queues either,
+        // and are zeroed on the general queue, see `initialize_depth_stencil`.
+        let copy_zeros = format_desc.is_compressed() || cmb.queue_type == QueueType::Transfer;
+        if !copy_zeros {
+            unsafe {
+                cmb_raw.clear_image(
+                    &texture.raw,
+                    hal::image::Layout::TransferDstOptimal,
+                    hal::command::ClearColorRaw { uint32: [0; 4] },
+                    hal::command::ClearDepthStencilRaw {
+                        depth: 0.0,
+                        stencil: 0,
+                    },
+                    ranges,
+                );
+            }
+            return;
+        }
+        debug_assert_eq!(texture.full_range.aspects, hal::format::Aspects::COLOR);
+
+        let block_size = (format_desc.bits as u32 / command::BITS_PER_BYTE) as BufferAddress;
+        let (block_width, block_height) = (format_desc.dim.0 as u32, format_desc.dim.1 as u32);
+        let regions = ranges
+            .into_iter()
+            .map(|range| {
+                let extent = texture.kind.extent().at_level(range.levels.start);
+                let blocks_per_layer = ((extent.width - 1) / block_width + 1) as BufferAddress *
+                    ((extent.height - 1) / block_height + 1) as BufferAddress *
+                    extent.depth as BufferAddress;
+                let layer_count = (range.layers.end - range.layers.start) as BufferAddress;
+                let size = blocks_per_layer * block_size * layer_count;
+                let region = hal::command::BufferImageCopy {
+                    buffer_offset: 0,
+                    buffer_width: 0,
+                    buffer_height: 0,
+                    image_layers: hal::image::SubresourceLayers {
+                        aspects: range.aspects,
+                        level: range.levels.start,
+                        layers: range.layers,
+                    },
+                    image_offset: hal::image::Offset::ZERO,
+                    image_extent: extent,
+                };
+                (size, region)
+            })
+            .collect::<Vec<_>>();
+        let size = regions.iter().map(|&(size, _)| size).max().unwrap_or(0);
+        let zeros = self.zeros(writes, size);
+        let cmb_raw = writes.command_buffer.raw.last_mut().unwrap();
+        unsafe {
+            cmb_raw.copy_buffer_to_image(
+                &zeros.raw,
+                &texture.raw,
+                hal::image::Layout::TransferDstOptimal,
+                regions.into_iter().map(|(_, region)| region),
+            );
+        }
+    }
+
+    /// Record zeroing the uninitialized depth-stencil textures used by a command
+    /// buffer of a dedicated queue into the pending writes of the general queue.
+    ///
+    /// These textures can't be cleared or copied to on the other queues, so the
+    /// general queue zeroes them, and the dedicated queue takes them over.
+    /// Returns `true` if anything was recorded.
+    fn initialize_depth_stencil(
+        &self,
+        self_id: DeviceId,
+        pending_writes: &mut FastHashMap<QueueIndex, PendingWrites<B>>,
+        comb: &mut command::CommandBuffer<B>,
+        texture_guard: &Storage<resource::Texture<B>, TextureId>,
+    ) -> bool {
+        for (id, range) in comb.texture_init_writes.drain(..) {
+            texture_guard[id].take_uninitialized(&range);
+        }
+        let mut is_recorded = false;
+        for id in comb.trackers.textures.used() {
+            let texture = &texture_guard[id];
+            if texture.full_range.aspects == hal::format::Aspects::COLOR {
+                continue;
+            }
+            let ranges = texture.take_uninitialized(&texture.full_range);
+            if !ranges.is_empty() {
+                let writes = self.pending_writes_mut(self_id, pending_writes, 0);
+                self.zero_texture(writes, texture_guard, id, ranges);
+                is_recorded = true;
+            }
+        }
+        is_recorded
+    }
+
+    /// Record zero-initializing the parts of the resources used by the
+    /// command buffer that haven't been written yet into the pending writes.
+    ///
+    /// The resources that the command buffer overwrites before reading them
+    /// are marked as initialized instead.
+    fn initialize_used_resources(
+        &self,
+        self_id: DeviceId,
+        pending_writes: &mut FastHashMap<QueueIndex, PendingWrites<B>>,
+        comb: &mut command::CommandBuffer<B>,
+        buffer_guard: &Storage<resource::Buffer<B>, BufferId>,
+        texture_guard: &Storage<resource::Texture<B>, TextureId>,
+    ) {
+        for (id, range) in comb.buffer_init_writes.drain(..) {
+            buffer_guard[id].uninitialized.lock().take(range);
+        }
+        for (id, range) in comb.texture_init_writes.drain(..) {
+            texture_guard[id].take_uninitialized(&range);
+        }
+
+        for id in comb.trackers.buffers.used() {
+            let buffer = &buffer_guard[id];
+            let ranges = buffer
+                .uninitialized
+                .lock()
+                .take(0 .. align_buffer_size(buffer.size));
+            if !ranges.is_empty() {
+                let writes = self.pending_writes_mut(self_id, pending_writes, comb.queue_index);
+                self.zero_buffer(writes, buffer_guard, id, ranges);
+            }
+        }
+        for id in comb.trackers.textures.used() {
+            let texture = &texture_guard[id];
+            let ranges = texture.take_uninitialized(&texture.full_range);
+            if !ranges.is_empty() {
+                let writes = self.pending_writes_mut(self_id, pending_writes, comb.queue_index);
+                self.zero_texture(writes, texture_guard, id, ranges);
+            }
+        }
+    }
+
     fn create_texture(
         &self,
         self_id: DeviceId,
         desc: &resource::TextureDescriptor,
+        format_features: &TextureFormatFeatures,
     ) -> resource::Texture<B> {
         debug_assert_eq!(self_id.backend(), B::VARIANT);
+        assert!(
+            format_features.allowed_usages.contains(desc.usage),
+            "Texture format {:?} doesn't support usage {:?}",
+            desc.format,
+            desc.usage - format_features.allowed_usages
+        );
+        assert!(
+            desc.sample_count == 1 || format_features.sample_counts & desc.sample_count != 0,
+            "Texture format {:?} doesn't support {} samples",
+            desc.format,
+            desc.sample_count
+        );
         let kind = conv::map_texture_dimension_size(
             desc.dimension,
             desc.size,
@@ -653,19 +1381,53 @@ impl<B: GfxBackend> Device<B> {
             desc.sample_count,
         );
         let format = conv::map_texture_format(desc.format);
-        let aspects = format.surface_desc().aspects;
-        let usage = conv::map_texture_usage(desc.usage, aspects);
+        let format_desc = format.surface_desc();
+        let aspects = format_desc.aspects;
+        // uninitialized subresources are zeroed with transfers
+        let usage = conv::map_texture_usage(desc.usage, aspects) | hal::image::Usage::TRANSFER_DST;
+
+        let required_features = desc.format.required_features();
+        assert!(
+            self.extensions.to_hal_features().contains(required_features),
+            "Texture format {:?} requires extension features {:?}",
+            desc.format,
+            required_features
+        );
+        let (block_width, block_height) = format_desc.dim;
+        if (block_width, block_height) != (1, 1) {
+            assert!(
+                desc.size.width % block_width as u32 == 0 &&
+                    desc.size.height % block_height as u32 == 0,
+                "Size {:?} of the compressed texture is not a multiple of the {}x{} block size",
+                desc.size,
+                block_width,
+                block_height
+            );
+            assert!(
+                !desc.usage.intersects(
+                    resource::TextureUsage::OUTPUT_ATTACHMENT |
+                        resource::TextureUsage::STORAGE |
+                        resource::TextureUsage::STORAGE_READ
+                ),
+                "Compressed textures can't be used as attachments or storage, got {:?}",
+                desc.usage
+            );
+        }
 
         assert!((desc.mip_level_count as usize) < MAX_MIP_LEVELS);
         let mut view_capabilities = hal::image::ViewCapabilities::empty();
 
         // 2D textures with array layer counts that are multiples of 6 could be cubemaps
         // Following gpuweb/gpuweb#68 always add the hint in that case
-        if desc.dimension == TextureDimension::D2 && desc.array_layer_count % 6 == 0 {
+        if desc.dimension == TextureDimension::D2 &&
+            desc.array_layer_count % 6 == 0 &&
+            desc.size.width == desc.size.height
+        {
             view_capabilities |= hal::image::ViewCapabilities::KIND_CUBE;
         };
 
-        // TODO: 2D arrays, cubemap arrays
+        // Note: 2D array views of 2D textures don't need any capabilities,
+        // and cube array views are covered by `KIND_CUBE` as well.
 
         let mut image = unsafe {
             self.raw.create_image(
@@ -705,19 +1467,29 @@ impl<B: GfxBackend> Device<B> {
                 ref_count: self.life_guard.ref_count.clone(),
             },
             kind,
+            view_capabilities,
             format: desc.format,
+            usage: desc.usage,
             full_range: hal::image::SubresourceRange {
                 aspects,
                 levels: 0 .. desc.mip_level_count as hal::image::Level,
                 layers: 0 .. desc.array_layer_count as hal::image::Layer,
             },
+            uninitialized: Mutex::new(
+                (0 .. desc.mip_level_count)
+                    .map(|_| {
+                        resource::UninitializedRanges::new(
+                            0 .. desc.array_layer_count as hal::image::Layer,
+                        )
+                    })
+                    .collect(),
+            ),
             placement: resource::TexturePlacement::Memory(memory),
             life_guard: LifeGuard::new(),
         }
     }
 }
 
-#[cfg(not(feature = "remote"))]
 #[no_mangle]
 pub extern "C" fn wgpu_device_get_limits(_device_id: DeviceId, limits: &mut Limits) {
     *limits = Limits::default(); // TODO
@@ -726,21 +1498,23 @@ pub extern "C" fn wgpu_device_get_limits(_device_id: DeviceId, limits: &mut Limi
 #[derive(Debug)]
 pub struct ShaderModule<B: hal::Backend> {
     pub(crate) raw: B::ShaderModule,
+    pub(crate) device_id: Stored<DeviceId>,
 }
 
 pub fn device_create_buffer<B: GfxBackend>(
+    global: &Global,
     device_id: DeviceId,
     desc: &resource::BufferDescriptor,
     id_in: Input<BufferId>,
-) -> Output<BufferId> {
-    let hub = B::hub();
+) -> BufferId {
+    let hub = B::hub(global);
     let mut token = Token::root();
 
     let (device_guard, _) = hub.devices.read(&mut token);
     let device = &device_guard[device_id];
     let buffer = device.create_buffer(device_id, desc);
 
-    let (id, id_out) = hub.buffers.new_identity(id_in);
+    let id = hub.buffers.new_identity(id_in);
     let ok = device.trackers.lock().buffers.init(
         id,
         &buffer.life_guard.ref_count,
@@ -750,25 +1524,25 @@ pub fn device_create_buffer<B: GfxBackend>(
     assert!(ok);
 
     hub.buffers.register(id, buffer, &mut token);
-    id_out
+    id
 }
 
-#[cfg(not(feature = "remote"))]
 #[no_mangle]
 pub extern "C" fn wgpu_device_create_buffer(
     device_id: DeviceId,
     desc: &resource::BufferDescriptor,
 ) -> BufferId {
-    gfx_select!(device_id => device_create_buffer(device_id, desc, PhantomData))
+    gfx_select!(device_id => device_create_buffer(&*GLOBAL, device_id, desc, None))
 }
 
 pub fn device_create_buffer_mapped<B: GfxBackend>(
+    global: &Global,
     device_id: DeviceId,
     desc: &resource::BufferDescriptor,
     mapped_ptr_out: *mut *mut u8,
     id_in: Input<BufferId>,
-) -> Output<BufferId> {
-    let hub = B::hub();
+) -> BufferId {
+    let hub = B::hub(global);
     let mut token = Token::root();
     let mut desc = desc.clone();
     desc.usage |= resource::BufferUsage::MAP_WRITE;
@@ -777,7 +1551,7 @@ pub fn device_create_buffer_mapped<B: GfxBackend>(
     let device = &device_guard[device_id];
     let mut buffer = device.create_buffer(device_id, &desc);
 
-    match map_buffer(&device.raw, &mut buffer, 0 .. desc.size, HostMap::Write) {
+    match map_buffer(&*device.raw, &mut buffer, 0 .. desc.size, HostMap::Write) {
         Ok(ptr) => unsafe {
             *mapped_ptr_out = ptr;
         },
@@ -789,7 +1563,7 @@ pub fn device_create_buffer_mapped<B: GfxBackend>(
         }
     }
 
-    let (id, id_out) = hub.buffers.new_identity(id_in);
+    let id = hub.buffers.new_identity(id_in);
     let ok = device.trackers.lock().buffers.init(
         id,
         &buffer.life_guard.ref_count,
@@ -799,21 +1573,20 @@ pub fn device_create_buffer_mapped<B: GfxBackend>(
     assert!(ok);
 
     hub.buffers.register(id, buffer, &mut token);
-    id_out
+    id
 }
 
-#[cfg(not(feature = "remote"))]
 #[no_mangle]
 pub extern "C" fn wgpu_device_create_buffer_mapped(
     device_id: DeviceId,
     desc: &resource::BufferDescriptor,
     mapped_ptr_out: *mut *mut u8,
 ) -> BufferId {
-    gfx_select!(device_id => device_create_buffer_mapped(device_id, desc, mapped_ptr_out, PhantomData))
+    gfx_select!(device_id => device_create_buffer_mapped(&*GLOBAL, device_id, desc, mapped_ptr_out, None))
 }
 
-pub fn buffer_destroy<B: GfxBackend>(buffer_id: BufferId) {
-    let hub = B::hub();
+pub fn buffer_destroy<B: GfxBackend>(global: &Global, buffer_id: BufferId) {
+    let hub = B::hub(global);
     let mut token = Token::root();
     let (device_guard, mut token) = hub.devices.read(&mut token);
     let (buffer_guard, _) = hub.buffers.read(&mut token);
@@ -826,22 +1599,28 @@ pub fn buffer_destroy<B: GfxBackend>(buffer_id: BufferId) {
 
 #[no_mangle]
 pub extern "C" fn wgpu_buffer_destroy(buffer_id: BufferId) {
-    gfx_select!(buffer_id => buffer_destroy(buffer_id))
+    gfx_select!(buffer_id => buffer_destroy(&*GLOBAL, buffer_id))
 }
 
 pub fn device_create_texture<B: GfxBackend>(
+    global: &Global,
     device_id: DeviceId,
     desc: &resource::TextureDescriptor,
     id_in: Input<TextureId>,
-) -> Output<TextureId> {
-    let hub = B::hub();
+) -> TextureId {
+    let hub = B::hub(global);
     let mut token = Token::root();
 
-    let (device_guard, _) = hub.devices.read(&mut token);
+    let (adapter_guard, mut token) = hub.adapters.read(&mut token);
+    let (device_guard, mut token) = hub.devices.read(&mut token);
     let device = &device_guard[device_id];
-    let texture = device.create_texture(device_id, desc);
+    let format_features = texture_format_features::<B>(
+        &adapter_guard[device.adapter_id].raw.physical_device,
+        desc.format,
+    );
+    let texture = device.create_texture(device_id, desc, &format_features);
 
-    let (id, id_out) = hub.textures.new_identity(id_in);
+    let id = hub.textures.new_identity(id_in);
     let ok = device.trackers.lock().textures.init(
         id,
         &texture.life_guard.ref_count,
@@ -851,24 +1630,75 @@ pub fn device_create_texture<B: GfxBackend>(
     assert!(ok);
 
     hub.textures.register(id, texture, &mut token);
-    id_out
+    id
 }
 
-#[cfg(not(feature = "remote"))]
 #[no_mangle]
 pub extern "C" fn wgpu_device_create_texture(
     device_id: DeviceId,
     desc: &resource::TextureDescriptor,
 ) -> TextureId {
-    gfx_select!(device_id => device_create_texture(device_id, desc, PhantomData))
+    gfx_select!(device_id => device_create_texture(&*GLOBAL, device_id, desc, None))
+}
+
+fn validate_view_dimension(
+    dimension: resource::TextureViewDimension,
+    texture_kind: hal::image::Kind,
+    view_capabilities: hal::image::ViewCapabilities,
+    layer_count: u32,
+    features: hal::Features,
+) {
+    use crate::resource::TextureViewDimension as Tvd;
+    use hal::image::Kind;
+
+    let is_kind_valid = match texture_kind {
+        Kind::D1(..) => dimension == Tvd::D1,
+        Kind::D2(..) => dimension != Tvd::D1 && dimension != Tvd::D3,
+        Kind::D3(..) => dimension == Tvd::D3,
+    };
+    assert!(
+        is_kind_valid,
+        "View dimension {:?} is not compatible with the texture of kind {:?}",
+        dimension,
+        texture_kind
+    );
+
+    match dimension {
+        Tvd::D1 | Tvd::D2 | Tvd::D3 => assert_eq!(
+            layer_count, 1,
+            "View dimension {:?} requires a single array layer",
+            dimension
+        ),
+        Tvd::D2Array => {}
+        Tvd::Cube | Tvd::CubeArray => {
+            assert!(
+                view_capabilities.contains(hal::image::ViewCapabilities::KIND_CUBE),
+                "Texture can't be viewed as a cube, it has to be square with a multiple of 6 array layers"
+            );
+            if dimension == Tvd::Cube {
+                assert_eq!(layer_count, 6, "Cube views require exactly 6 array layers");
+            } else {
+                assert!(
+                    features.contains(hal::Features::IMAGE_CUBE_ARRAY),
+                    "Cube array views are not supported by the adapter"
+                );
+                assert!(
+                    layer_count != 0 && layer_count % 6 == 0,
+                    "Cube array views require a multiple of 6 array layers, got {}",
+                    layer_count
+                );
+            }
+        }
+    }
 }
 
 pub fn texture_create_view<B: GfxBackend>(
+    global: &Global,
     texture_id: TextureId,
     desc: Option<&resource::TextureViewDescriptor>,
     id_in: Input<TextureViewId>,
-) -> Output<TextureViewId> {
-    let hub = B::hub();
+) -> TextureViewId {
+    let hub = B::hub(global);
     let mut token = Token::root();
 
     let (device_guard, mut token) = hub.devices.read(&mut token);
@@ -879,24 +1709,48 @@ pub fn texture_create_view<B: GfxBackend>(
     let (format, view_kind, range) = match desc {
         Some(desc) => {
             let kind = conv::map_texture_view_dimension(desc.dimension);
+            // the ranges are validated before narrowing them to the hal types
             let end_level = if desc.level_count == 0 {
-                texture.full_range.levels.end
+                texture.full_range.levels.end as u32
             } else {
-                (desc.base_mip_level + desc.level_count) as u8
+                desc.base_mip_level.saturating_add(desc.level_count)
             };
             let end_layer = if desc.array_layer_count == 0 {
-                texture.full_range.layers.end
+                texture.full_range.layers.end as u32
             } else {
-                (desc.base_array_layer + desc.array_layer_count) as u16
+                desc.base_array_layer.saturating_add(desc.array_layer_count)
             };
+            assert!(
+                desc.base_mip_level < end_level &&
+                    end_level <= texture.full_range.levels.end as u32,
+                "View mip levels {}..{} are out of the texture range {:?}",
+                desc.base_mip_level,
+                end_level,
+                texture.full_range.levels
+            );
+            assert!(
+                desc.base_array_layer < end_layer &&
+                    end_layer <= texture.full_range.layers.end as u32,
+                "View array layers {}..{} are out of the texture range {:?}",
+                desc.base_array_layer,
+                end_layer,
+                texture.full_range.layers
+            );
+            validate_view_dimension(
+                desc.dimension,
+                texture.kind,
+                texture.view_capabilities,
+                end_layer - desc.base_array_layer,
+                device.features,
+            );
             let range = hal::image::SubresourceRange {
                 aspects: match desc.aspect {
                     resource::TextureAspect::All => texture.full_range.aspects,
                     resource::TextureAspect::DepthOnly => hal::format::Aspects::DEPTH,
                     resource::TextureAspect::StencilOnly => hal::format::Aspects::STENCIL,
                 },
-                levels: desc.base_mip_level as u8 .. end_level,
-                layers: desc.base_array_layer as u16 .. end_layer,
+                levels: desc.base_mip_level as u8 .. end_level as u8,
+                layers: desc.base_array_layer as u16 .. end_layer as u16,
             };
             (desc.format, kind, range)
         }
@@ -939,7 +1793,7 @@ pub fn texture_create_view<B: GfxBackend>(
         life_guard: LifeGuard::new(),
     };
 
-    let (id, id_out) = hub.texture_views.new_identity(id_in);
+    let id = hub.texture_views.new_identity(id_in);
     let ok = device
         .trackers
         .lock()
@@ -948,20 +1802,19 @@ pub fn texture_create_view<B: GfxBackend>(
     assert!(ok);
 
     hub.texture_views.register(id, view, &mut token);
-    id_out
+    id
 }
 
-#[cfg(not(feature = "remote"))]
 #[no_mangle]
 pub extern "C" fn wgpu_texture_create_view(
     texture_id: TextureId,
     desc: Option<&resource::TextureViewDescriptor>,
 ) -> TextureViewId {
-    gfx_select!(texture_id => texture_create_view(texture_id, desc, PhantomData))
+    gfx_select!(texture_id => texture_create_view(&*GLOBAL, texture_id, desc, None))
 }
 
-pub fn texture_destroy<B: GfxBackend>(texture_id: TextureId) {
-    let hub = B::hub();
+pub fn texture_destroy<B: GfxBackend>(global: &Global, texture_id: TextureId) {
+    let hub = B::hub(global);
     let mut token = Token::root();
 
     let (device_guard, mut token) = hub.devices.read(&mut token);
@@ -978,11 +1831,11 @@ pub fn texture_destroy<B: GfxBackend>(texture_id: TextureId) {
 
 #[no_mangle]
 pub extern "C" fn wgpu_texture_destroy(texture_id: TextureId) {
-    gfx_select!(texture_id => texture_destroy(texture_id))
+    gfx_select!(texture_id => texture_destroy(&*GLOBAL, texture_id))
 }
 
-pub fn texture_view_destroy<B: GfxBackend>(texture_view_id: TextureViewId) {
-    let hub = B::hub();
+pub fn texture_view_destroy<B: GfxBackend>(global: &Global, texture_view_id: TextureViewId) {
+    let hub = B::hub(global);
     let mut token = Token::root();
     let (device_guard, mut token) = hub.devices.read(&mut token);
     let (texture_guard, mut token) = hub.textures.read(&mut token);
@@ -997,15 +1850,16 @@ pub fn texture_view_destroy<B: GfxBackend>(texture_view_id: TextureViewId) {
 
 #[no_mangle]
 pub extern "C" fn wgpu_texture_view_destroy(texture_view_id: TextureViewId) {
-    gfx_select!(texture_view_id => texture_view_destroy(texture_view_id))
+    gfx_select!(texture_view_id => texture_view_destroy(&*GLOBAL, texture_view_id))
 }
 
 pub fn device_create_sampler<B: GfxBackend>(
+    global: &Global,
     device_id: DeviceId,
     desc: &resource::SamplerDescriptor,
     id_in: Input<SamplerId>,
-) -> Output<SamplerId> {
-    let hub = B::hub();
+) -> SamplerId {
+    let hub = B::hub(global);
     let mut token = Token::root();
     let (device_guard, mut token) = hub.devices.read(&mut token);
     let device = &device_guard[device_id];
@@ -1033,73 +1887,135 @@ pub fn device_create_sampler<B: GfxBackend>(
 
     let sampler = resource::Sampler {
         raw: unsafe { device.raw.create_sampler(info).unwrap() },
+        device_id: Stored {
+            value: device_id,
+            ref_count: device.life_guard.ref_count.clone(),
+        },
+        life_guard: LifeGuard::new(),
     };
     hub.samplers.register_identity(id_in, sampler, &mut token)
 }
 
-#[cfg(not(feature = "remote"))]
 #[no_mangle]
 pub extern "C" fn wgpu_device_create_sampler(
     device_id: DeviceId,
     desc: &resource::SamplerDescriptor,
 ) -> SamplerId {
-    gfx_select!(device_id => device_create_sampler(device_id, desc, PhantomData))
+    gfx_select!(device_id => device_create_sampler(&*GLOBAL, device_id, desc, None))
 }
 
 pub fn device_create_bind_group_layout<B: GfxBackend>(
+    global: &Global,
     device_id: DeviceId,
     desc: &binding_model::BindGroupLayoutDescriptor,
     id_in: Input<BindGroupLayoutId>,
-) -> Output<BindGroupLayoutId> {
+) -> BindGroupLayoutId {
     let mut token = Token::root();
-    let hub = B::hub();
+    let hub = B::hub(global);
     let bindings = unsafe { slice::from_raw_parts(desc.bindings, desc.bindings_length) };
 
+    let mut immutable_samplers = Vec::new();
+    for binding in bindings {
+        if binding.has_immutable_samplers() {
+            assert_eq!(
+                binding.ty,
+                binding_model::BindingType::Sampler,
+                "Immutable samplers are only allowed for sampler bindings, got {:?}",
+                binding
+            );
+            assert_eq!(
+                binding.immutable_samplers_length,
+                binding.array_count() as usize,
+                "Expected an immutable sampler for each array element of {:?}",
+                binding
+            );
+            immutable_samplers.extend_from_slice(unsafe {
+                slice::from_raw_parts(
+                    binding.immutable_samplers,
+                    binding.immutable_samplers_length,
+                )
+            });
+        }
+    }
+
     let raw_bindings = bindings
         .iter()
         .map(|binding| hal::pso::DescriptorSetLayoutBinding {
             binding: binding.binding,
             ty: conv::map_binding_type(binding),
-            count: 1, //TODO: consolidate
+            count: binding.array_count() as hal::pso::DescriptorArrayIndex,
             stage_flags: conv::map_shader_stage_flags(binding.visibility),
-            immutable_samplers: false, // TODO
+            immutable_samplers: binding.has_immutable_samplers(),
         })
         .collect::<Vec<_>>(); //TODO: avoid heap allocation
 
-    let raw = unsafe {
-        let (device_guard, _) = hub.devices.read(&mut token);
-        device_guard[device_id]
-            .raw
-            .create_descriptor_set_layout(&raw_bindings, &[])
-            .unwrap()
+    let (raw, dev_stored, entries) = {
+        let (device_guard, mut token) = hub.devices.read(&mut token);
+        let (sampler_guard, _) = hub.samplers.read(&mut token);
+        let device = &device_guard[device_id];
+        let raw = unsafe {
+            device
+                .raw
+                .create_descriptor_set_layout(
+                    &raw_bindings,
+                    immutable_samplers.iter().map(|&id| &sampler_guard[id].raw),
+                )
+                .unwrap()
+        };
+        let dev_stored = Stored {
+            value: device_id,
+            ref_count: device.life_guard.ref_count.clone(),
+        };
+        // the layout holds on to its immutable samplers
+        let mut sampler_ids = immutable_samplers.into_iter();
+        let entries = bindings
+            .iter()
+            .map(|binding| {
+                let samplers = sampler_ids
+                    .by_ref()
+                    .take(binding.immutable_samplers_length)
+                    .map(|id| Stored {
+                        value: id,
+                        ref_count: sampler_guard[id].life_guard.ref_count.clone(),
+                    })
+                    .collect();
+                binding_model::BindGroupLayoutEntry::new(binding, samplers)
+            })
+            .collect();
+        (raw, dev_stored, entries)
     };
 
     let layout = binding_model::BindGroupLayout {
         raw,
-        bindings: bindings.to_vec(),
+        device_id: dev_stored,
+        bindings: entries,
         desc_ranges: DescriptorRanges::from_bindings(&raw_bindings),
-        dynamic_count: bindings.iter().filter(|b| b.dynamic).count(),
+        dynamic_count: bindings
+            .iter()
+            .filter(|b| b.dynamic)
+            .map(|b| b.array_count() as usize)
+            .sum(),
     };
 
     hub.bind_group_layouts
         .register_identity(id_in, layout, &mut token)
 }
 
-#[cfg(not(feature = "remote"))]
 #[no_mangle]
 pub extern "C" fn wgpu_device_create_bind_group_layout(
     device_id: DeviceId,
     desc: &binding_model::BindGroupLayoutDescriptor,
 ) -> BindGroupLayoutId {
-    gfx_select!(device_id => device_create_bind_group_layout(device_id, desc, PhantomData))
+    gfx_select!(device_id => device_create_bind_group_layout(&*GLOBAL, device_id, desc, None))
 }
 
 pub fn device_create_pipeline_layout<B: GfxBackend>(
+    global: &Global,
     device_id: DeviceId,
     desc: &binding_model::PipelineLayoutDescriptor,
     id_in: Input<PipelineLayoutId>,
-) -> Output<PipelineLayoutId> {
-    let hub = B::hub();
+) -> PipelineLayoutId {
+    let hub = B::hub(global);
     let mut token = Token::root();
 
     let (device_guard, mut token) = hub.devices.read(&mut token);
@@ -1122,27 +2038,129 @@ pub fn device_create_pipeline_layout<B: GfxBackend>(
 
     let layout = binding_model::PipelineLayout {
         raw: pipeline_layout,
+        device_id: Stored {
+            value: device_id,
+            ref_count: device_guard[device_id].life_guard.ref_count.clone(),
+        },
         bind_group_layout_ids: bind_group_layout_ids.iter().cloned().collect(),
     };
     hub.pipeline_layouts
         .register_identity(id_in, layout, &mut token)
 }
 
-#[cfg(not(feature = "remote"))]
 #[no_mangle]
 pub extern "C" fn wgpu_device_create_pipeline_layout(
     device_id: DeviceId,
     desc: &binding_model::PipelineLayoutDescriptor,
 ) -> PipelineLayoutId {
-    gfx_select!(device_id => device_create_pipeline_layout(device_id, desc, PhantomData))
+    gfx_select!(device_id => device_create_pipeline_layout(&*GLOBAL, device_id, desc, None))
+}
+
+fn bind_buffer<'a, B: hal::Backend>(
+    used: &mut TrackerSet,
+    buffer_guard: &'a Storage<resource::Buffer<B>, BufferId>,
+    decl: &binding_model::BindGroupLayoutEntry,
+    bb: &binding_model::BufferBinding,
+) -> hal::pso::Descriptor<'a, B> {
+    let (alignment, usage) = match decl.ty {
+        binding_model::BindingType::UniformBuffer => {
+            (BIND_BUFFER_ALIGNMENT, resource::BufferUsage::UNIFORM)
+        }
+        binding_model::BindingType::StorageBuffer => {
+            (BIND_BUFFER_ALIGNMENT, resource::BufferUsage::STORAGE)
+        }
+        binding_model::BindingType::ReadonlyStorageBuffer => {
+            (BIND_BUFFER_ALIGNMENT, resource::BufferUsage::STORAGE_READ)
+        }
+        binding_model::BindingType::Sampler
+        | binding_model::BindingType::SampledTexture
+        | binding_model::BindingType::StorageTexture => {
+            panic!("Mismatched buffer binding for {:?}", decl)
+        }
+    };
+    assert_eq!(
+        bb.offset as hal::buffer::Offset % alignment,
+        0,
+        "Misaligned buffer offset {}",
+        bb.offset
+    );
+    let buffer = used
+        .buffers
+        .use_extend(buffer_guard, bb.buffer, (), usage)
+        .unwrap();
+
+    let end = if bb.size == 0 {
+        None
+    } else {
+        let end = bb.offset + bb.size;
+        assert!(
+            end <= buffer.size,
+            "Bound buffer range {:?} does not fit in buffer size {}",
+            bb.offset .. end,
+            buffer.size
+        );
+        Some(end)
+    };
+
+    let range = Some(bb.offset) .. end;
+    hal::pso::Descriptor::Buffer(&buffer.raw, range)
+}
+
+fn bind_texture_view<'a, B: hal::Backend>(
+    used: &mut TrackerSet,
+    texture_view_guard: &'a Storage<resource::TextureView<B>, TextureViewId>,
+    decl: &binding_model::BindGroupLayoutEntry,
+    id: TextureViewId,
+) -> hal::pso::Descriptor<'a, B> {
+    let (usage, image_layout) = match decl.ty {
+        binding_model::BindingType::SampledTexture => (
+            resource::TextureUsage::SAMPLED,
+            hal::image::Layout::ShaderReadOnlyOptimal,
+        ),
+        binding_model::BindingType::StorageTexture => {
+            let usage = match decl.storage_texture_access {
+                binding_model::StorageTextureAccess::ReadOnly => {
+                    resource::TextureUsage::STORAGE_READ
+                }
+                binding_model::StorageTextureAccess::WriteOnly => resource::TextureUsage::STORAGE,
+                binding_model::StorageTextureAccess::ReadWrite => {
+                    resource::TextureUsage::STORAGE | resource::TextureUsage::STORAGE_READ
+                }
+            };
+            (usage, hal::image::Layout::General)
+        }
+        _ => panic!("Mismatched texture binding for {:?}", decl),
+    };
+    let view = used
+        .views
+        .use_extend(texture_view_guard, id, (), ())
+        .unwrap();
+    if decl.ty == binding_model::BindingType::StorageTexture {
+        assert_eq!(
+            view.format, decl.storage_texture_format,
+            "Storage texture view format {:?} doesn't match the layout format {:?}",
+            view.format, decl.storage_texture_format
+        );
+        assert_eq!(view.samples, 1, "Storage texture views can't be multisampled");
+    }
+    used.textures
+        .change_extend(
+            view.texture_id.value,
+            &view.texture_id.ref_count,
+            view.range.clone(),
+            usage,
+        )
+        .unwrap();
+    hal::pso::Descriptor::Image(&view.raw, image_layout)
 }
 
 pub fn device_create_bind_group<B: GfxBackend>(
+    global: &Global,
     device_id: DeviceId,
     desc: &binding_model::BindGroupDescriptor,
     id_in: Input<BindGroupId>,
-) -> Output<BindGroupId> {
-    let hub = B::hub();
+) -> BindGroupId {
+    let hub = B::hub(global);
     let mut token = Token::root();
 
     let (device_guard, mut token) = hub.devices.read(&mut token);
@@ -1150,7 +2168,19 @@ pub fn device_create_bind_group<B: GfxBackend>(
     let (bind_group_layout_guard, _) = hub.bind_group_layouts.read(&mut token);
     let bind_group_layout = &bind_group_layout_guard[desc.layout];
     let bindings = unsafe { slice::from_raw_parts(desc.bindings, desc.bindings_length as usize) };
-    assert_eq!(bindings.len(), bind_group_layout.bindings.len());
+    // bindings with immutable samplers are fully defined by the layout
+    let expected_count = bind_group_layout
+        .bindings
+        .iter()
+        .filter(|decl| !decl.has_immutable_samplers())
+        .count();
+    assert_eq!(
+        bindings.len(),
+        expected_count,
+        "Bind group has {} bindings, but its layout expects {}",
+        bindings.len(),
+        expected_count
+    );
 
     let desc_set = unsafe {
         let mut desc_sets = ArrayVec::<[_; 1]>::new();
@@ -1178,88 +2208,68 @@ pub fn device_create_bind_group<B: GfxBackend>(
 
         //TODO: group writes into contiguous sections
         let mut writes = Vec::new();
-        for (b, decl) in bindings.iter().zip(&bind_group_layout.bindings) {
-            let descriptor = match b.resource {
+        for b in bindings {
+            let decl = bind_group_layout
+                .bindings
+                .iter()
+                .find(|decl| decl.binding == b.binding)
+                .unwrap_or_else(|| panic!("Binding {} is not present in the layout", b.binding));
+            let descriptors = match b.resource {
                 binding_model::BindingResource::Buffer(ref bb) => {
-                    let (alignment, usage) = match decl.ty {
-                        binding_model::BindingType::UniformBuffer => {
-                            (BIND_BUFFER_ALIGNMENT, resource::BufferUsage::UNIFORM)
-                        }
-                        binding_model::BindingType::StorageBuffer => {
-                            (BIND_BUFFER_ALIGNMENT, resource::BufferUsage::STORAGE)
-                        }
-                        binding_model::BindingType::ReadonlyStorageBuffer => {
-                            (BIND_BUFFER_ALIGNMENT, resource::BufferUsage::STORAGE_READ)
-                        }
-                        binding_model::BindingType::Sampler
-                        | binding_model::BindingType::SampledTexture
-                        | binding_model::BindingType::StorageTexture => {
-                            panic!("Mismatched buffer binding for {:?}", decl)
-                        }
-                    };
-                    assert_eq!(
-                        bb.offset as hal::buffer::Offset % alignment,
-                        0,
-                        "Misaligned buffer offset {}",
-                        bb.offset
-                    );
-                    let buffer = used
-                        .buffers
-                        .use_extend(&*buffer_guard, bb.buffer, (), usage)
-                        .unwrap();
-
-                    let end = if bb.size == 0 {
-                        None
-                    } else {
-                        let end = bb.offset + bb.size;
-                        assert!(
-                            end <= buffer.size,
-                            "Bound buffer range {:?} does not fit in buffer size {}",
-                            bb.offset .. end,
-                            buffer.size
-                        );
-                        Some(end)
-                    };
-
-                    let range = Some(bb.offset) .. end;
-                    hal::pso::Descriptor::Buffer(&buffer.raw, range)
+                    vec![bind_buffer(&mut used, &*buffer_guard, decl, bb)]
+                }
+                binding_model::BindingResource::BufferArray(bbs, length) => {
+                    unsafe { slice::from_raw_parts(bbs, length) }
+                        .iter()
+                        .map(|bb| bind_buffer(&mut used, &*buffer_guard, decl, bb))
+                        .collect()
                 }
-                binding_model::BindingResource::Sampler(id) => {
+                binding_model::BindingResource::Sampler(_)
+                | binding_model::BindingResource::SamplerArray(..) => {
                     assert_eq!(decl.ty, binding_model::BindingType::Sampler);
-                    let sampler = &sampler_guard[id];
-                    hal::pso::Descriptor::Sampler(&sampler.raw)
+                    assert!(
+                        !decl.has_immutable_samplers(),
+                        "Sampler binding {} is immutable in the layout",
+                        b.binding
+                    );
+                    let ids = match b.resource {
+                        binding_model::BindingResource::SamplerArray(ids, length) => unsafe {
+                            slice::from_raw_parts(ids, length)
+                        },
+                        binding_model::BindingResource::Sampler(ref id) => slice::from_ref(id),
+                        _ => unreachable!(),
+                    };
+                    ids.iter()
+                        .map(|&id| hal::pso::Descriptor::Sampler(&sampler_guard[id].raw))
+                        .collect()
                 }
                 binding_model::BindingResource::TextureView(id) => {
-                    let (usage, image_layout) = match decl.ty {
-                        binding_model::BindingType::SampledTexture => (
-                            resource::TextureUsage::SAMPLED,
-                            hal::image::Layout::ShaderReadOnlyOptimal,
-                        ),
-                        binding_model::BindingType::StorageTexture => {
-                            (resource::TextureUsage::STORAGE, hal::image::Layout::General)
-                        }
-                        _ => panic!("Mismatched texture binding for {:?}", decl),
-                    };
-                    let view = used
-                        .views
-                        .use_extend(&*texture_view_guard, id, (), ())
-                        .unwrap();
-                    used.textures
-                        .change_extend(
-                            view.texture_id.value,
-                            &view.texture_id.ref_count,
-                            view.range.clone(),
-                            usage,
-                        )
-                        .unwrap();
-                    hal::pso::Descriptor::Image(&view.raw, image_layout)
+                    vec![bind_texture_view(&mut used, &*texture_view_guard, decl, id)]
+                }
+                binding_model::BindingResource::TextureViewArray(ids, length) => {
+                    unsafe { slice::from_raw_parts(ids, length) }
+                        .iter()
+                        .map(|&id| bind_texture_view(&mut used, &*texture_view_guard, decl, id))
+                        .collect::<Vec<_>>()
                 }
             };
+
+            // partially bound arrays are not supported by gfx-hal
+            let count = decl.array_count() as usize;
+            assert_eq!(
+                descriptors.len(),
+                count,
+                "Binding {} has {} resources, but the layout expects {}",
+                b.binding,
+                descriptors.len(),
+                count
+            );
+
             writes.alloc().init(hal::pso::DescriptorSetWrite {
                 set: desc_set.raw(),
                 binding: b.binding,
-                array_offset: 0, //TODO
-                descriptors: iter::once(descriptor),
+                array_offset: 0,
+                descriptors,
             });
         }
 
@@ -1279,7 +2289,7 @@ pub fn device_create_bind_group<B: GfxBackend>(
         used,
         dynamic_count: bind_group_layout.dynamic_count,
     };
-    let (id, id_out) = hub.bind_groups.new_identity(id_in);
+    let id = hub.bind_groups.new_identity(id_in);
     let ok = device
         .trackers
         .lock()
@@ -1288,20 +2298,19 @@ pub fn device_create_bind_group<B: GfxBackend>(
     assert!(ok);
 
     hub.bind_groups.register(id, bind_group, &mut token);
-    id_out
+    id
 }
 
-#[cfg(not(feature = "remote"))]
 #[no_mangle]
 pub extern "C" fn wgpu_device_create_bind_group(
     device_id: DeviceId,
     desc: &binding_model::BindGroupDescriptor,
 ) -> BindGroupId {
-    gfx_select!(device_id => device_create_bind_group(device_id, desc, PhantomData))
+    gfx_select!(device_id => device_create_bind_group(&*GLOBAL, device_id, desc, None))
 }
 
-pub fn bind_group_destroy<B: GfxBackend>(bind_group_id: BindGroupId) {
-    let hub = B::hub();
+pub fn bind_group_destroy<B: GfxBackend>(global: &Global, bind_group_id: BindGroupId) {
+    let hub = B::hub(global);
     let mut token = Token::root();
     let (device_guard, mut token) = hub.devices.read(&mut token);
     let (bind_group_guard, _) = hub.bind_groups.read(&mut token);
@@ -1317,26 +2326,27 @@ pub fn bind_group_destroy<B: GfxBackend>(bind_group_id: BindGroupId) {
 
 #[no_mangle]
 pub extern "C" fn wgpu_bind_group_destroy(bind_group_id: BindGroupId) {
-    gfx_select!(bind_group_id => bind_group_destroy(bind_group_id))
+    gfx_select!(bind_group_id => bind_group_destroy(&*GLOBAL, bind_group_id))
 }
 
 pub fn device_create_shader_module<B: GfxBackend>(
+    global: &Global,
     device_id: DeviceId,
     desc: &pipeline::ShaderModuleDescriptor,
     id_in: Input<ShaderModuleId>,
-) -> Output<ShaderModuleId> {
-    let hub = B::hub();
+) -> ShaderModuleId {
+    let hub = B::hub(global);
     let mut token = Token::root();
 
     let spv = unsafe { slice::from_raw_parts(desc.code.bytes, desc.code.length) };
     let shader = {
         let (device_guard, _) = hub.devices.read(&mut token);
+        let device = &device_guard[device_id];
         ShaderModule {
-            raw: unsafe {
-                device_guard[device_id]
-                    .raw
-                    .create_shader_module(spv)
-                    .unwrap()
+            raw: unsafe { device.raw.create_shader_module(spv).unwrap() },
+            device_id: Stored {
+                value: device_id,
+                ref_count: device.life_guard.ref_count.clone(),
             },
         }
     };
@@ -1344,21 +2354,21 @@ pub fn device_create_shader_module<B: GfxBackend>(
         .register_identity(id_in, shader, &mut token)
 }
 
-#[cfg(not(feature = "remote"))]
 #[no_mangle]
 pub extern "C" fn wgpu_device_create_shader_module(
     device_id: DeviceId,
     desc: &pipeline::ShaderModuleDescriptor,
 ) -> ShaderModuleId {
-    gfx_select!(device_id => device_create_shader_module(device_id, desc, PhantomData))
+    gfx_select!(device_id => device_create_shader_module(&*GLOBAL, device_id, desc, None))
 }
 
-pub fn device_create_command_encoder<B: GfxBackend>(
+fn create_command_encoder<B: GfxBackend>(
+    global: &Global,
     device_id: DeviceId,
-    _desc: &command::CommandEncoderDescriptor,
+    queue_index: QueueIndex,
     id_in: Input<CommandEncoderId>,
-) -> Output<CommandEncoderId> {
-    let hub = B::hub();
+) -> CommandEncoderId {
+    let hub = B::hub(global);
     let mut token = Token::root();
 
     let (device_guard, mut token) = hub.devices.read(&mut token);
@@ -1368,7 +2378,9 @@ pub fn device_create_command_encoder<B: GfxBackend>(
         value: device_id,
         ref_count: device.life_guard.ref_count.clone(),
     };
-    let mut comb = device.com_allocator.allocate(dev_stored, &device.raw);
+    let mut comb = device
+        .command_allocator(queue_index)
+        .allocate(dev_stored, &device.raw);
     unsafe {
         comb.raw.last_mut().unwrap().begin(
             hal::command::CommandBufferFlags::ONE_TIME_SUBMIT,
@@ -1380,36 +2392,189 @@ pub fn device_create_command_encoder<B: GfxBackend>(
         .register_identity(id_in, comb, &mut token)
 }
 
-#[cfg(not(feature = "remote"))]
+pub fn device_create_command_encoder<B: GfxBackend>(
+    global: &Global,
+    device_id: DeviceId,
+    _desc: &command::CommandEncoderDescriptor,
+    id_in: Input<CommandEncoderId>,
+) -> CommandEncoderId {
+    create_command_encoder::<B>(global, device_id, 0, id_in)
+}
+
 #[no_mangle]
 pub extern "C" fn wgpu_device_create_command_encoder(
     device_id: DeviceId,
     desc: Option<&command::CommandEncoderDescriptor>,
 ) -> CommandEncoderId {
     let desc = &desc.cloned().unwrap_or_default();
-    gfx_select!(device_id => device_create_command_encoder(device_id, desc, PhantomData))
+    gfx_select!(device_id => device_create_command_encoder(&*GLOBAL, device_id, desc, None))
+}
+
+/// Create a command encoder recording for the given queue,
+/// which can be a dedicated compute or transfer one.
+pub fn queue_create_command_encoder<B: GfxBackend>(
+    global: &Global,
+    queue_id: QueueId,
+    _desc: &command::CommandEncoderDescriptor,
+    id_in: Input<CommandEncoderId>,
+) -> CommandEncoderId {
+    let hub = B::hub(global);
+    let (device_id, queue_index) = {
+        let (queue_guard, _) = hub.queues.read(&mut Token::root());
+        let queue = &queue_guard[queue_id];
+        (queue.device_id.value, queue.index)
+    };
+    create_command_encoder::<B>(global, device_id, queue_index, id_in)
+}
+
+#[no_mangle]
+pub extern "C" fn wgpu_queue_create_command_encoder(
+    queue_id: QueueId,
+    desc: Option<&command::CommandEncoderDescriptor>,
+) -> CommandEncoderId {
+    let desc = &desc.cloned().unwrap_or_default();
+    gfx_select!(queue_id => queue_create_command_encoder(&*GLOBAL, queue_id, desc, None))
+}
+
+/// Get a handle to a device queue.
+///
+/// The general queue is the only one of its type. Dedicated compute
+/// and transfer queues are numbered from zero in the order they were requested
+/// with the `DeviceDescriptor`. Each queue is registered on the first call,
+/// and the following calls return the same ID, unless the caller provides
+/// a different one, which is registered as another handle to the queue.
+pub fn device_get_queue<B: GfxBackend>(
+    global: &Global,
+    device_id: DeviceId,
+    ty: QueueType,
+    index: u32,
+    id_in: Input<QueueId>,
+) -> QueueId {
+    let hub = B::hub(global);
+    let mut token = Token::root();
+
+    let (device_guard, mut token) = hub.devices.read(&mut token);
+    let device = &device_guard[device_id];
+    let queue_index = match ty {
+        QueueType::General => {
+            assert_eq!(index, 0, "There is only one general queue");
+            0
+        }
+        QueueType::Compute | QueueType::Transfer => {
+            let position = device
+                .dedicated_queues
+                .iter()
+                .enumerate()
+                .filter(|&(_, queue)| queue.ty == ty)
+                .nth(index as usize)
+                .map(|(position, _)| position)
+                .unwrap_or_else(|| panic!("Device doesn't have the {:?} queue {}", ty, index));
+            1 + position
+        }
+    };
+
+    let mut queue_ids = device.queue_ids.lock();
+    if let Some(&queue_id) = queue_ids.get(&queue_index) {
+        // a remote caller may provide a new ID for the same queue,
+        // which is registered as another handle to it
+        match id_in {
+            Some(id) if id != queue_id => {}
+            _ => return queue_id,
+        }
+    }
+    let queue = Queue {
+        device_id: Stored {
+            value: device_id,
+            ref_count: device.life_guard.ref_count.clone(),
+        },
+        index: queue_index,
+    };
+    let queue_id = hub.queues.register_identity(id_in, queue, &mut token);
+    queue_ids.entry(queue_index).or_insert(queue_id);
+    queue_id
 }
 
 #[no_mangle]
 pub extern "C" fn wgpu_device_get_queue(device_id: DeviceId) -> QueueId {
-    device_id
+    gfx_select!(device_id => device_get_queue(&*GLOBAL, device_id, QueueType::General, 0, None))
+}
+
+#[no_mangle]
+pub extern "C" fn wgpu_device_get_dedicated_queue(
+    device_id: DeviceId,
+    ty: QueueType,
+    index: u32,
+) -> QueueId {
+    gfx_select!(device_id => device_get_queue(&*GLOBAL, device_id, ty, index, None))
 }
 
-pub fn queue_submit<B: GfxBackend>(queue_id: QueueId, command_buffer_ids: &[CommandBufferId]) {
-    let hub = B::hub();
+pub fn queue_submit<B: GfxBackend>(
+    global: &Global,
+    queue_id: QueueId,
+    command_buffer_ids: &[CommandBufferId],
+) -> SubmissionIndex {
+    let hub = B::hub(global);
+
+    let (device_id, queue_index) = {
+        let (queue_guard, _) = hub.queues.read(&mut Token::root());
+        let queue = &queue_guard[queue_id];
+        (queue.device_id.value, queue.index)
+    };
+
+    // the depth-stencil textures are zeroed by the general queue first,
+    // and taken over like the other resources last used by another queue
+    if queue_index != 0 {
+        let is_recorded = {
+            let mut token = Token::root();
+            let (device_guard, mut token) = hub.devices.read(&mut token);
+            let (mut command_buffer_guard, mut token) = hub.command_buffers.write(&mut token);
+            let (_, mut token) = hub.buffers.read(&mut token); //skip token
+            let (texture_guard, _) = hub.textures.read(&mut token);
+            let device = &device_guard[device_id];
+            let mut pending_writes = device.pending_writes.lock();
+            let mut is_recorded = false;
+            for &cmb_id in command_buffer_ids {
+                is_recorded |= device.initialize_depth_stencil(
+                    device_id,
+                    &mut *pending_writes,
+                    &mut command_buffer_guard[cmb_id],
+                    &*texture_guard,
+                );
+            }
+            is_recorded
+        };
+        if is_recorded {
+            submit::<B>(global, device_id, 0, &[]);
+        }
+    }
+
+    submit::<B>(global, device_id, queue_index, command_buffer_ids)
+}
+
+fn submit<B: GfxBackend>(
+    global: &Global,
+    device_id: DeviceId,
+    queue_index: QueueIndex,
+    command_buffer_ids: &[CommandBufferId],
+) -> SubmissionIndex {
+    let hub = B::hub(global);
 
-    let (submit_index, fence) = {
+    let (submit_index, fence, semaphores, pending_writes) = {
         let mut token = Token::root();
         let (mut device_guard, mut token) = hub.devices.write(&mut token);
         let (swap_chain_guard, mut token) = hub.swap_chains.read(&mut token);
-        let device = &mut device_guard[queue_id];
-        let mut trackers = device.trackers.lock();
+        let device = &mut device_guard[device_id];
+        let queue_family = device.queue_family(queue_index);
+        let stages = queue_stages(device.queue_type(queue_index));
+        // semaphores signaled by the queues we take the resources over from
+        let mut semaphores = Vec::new();
         let mut wait_semaphores = Vec::new();
 
         let submit_index = 1 + device
             .life_guard
             .submission_index
             .fetch_add(1, Ordering::Relaxed);
+        let mut pending_writes;
 
         //TODO: if multiple command buffers are submitted, we can re-use the last
         // native command buffer of the previous chain instead of always creating
@@ -1420,10 +2585,41 @@ pub fn queue_submit<B: GfxBackend>(queue_id: QueueId, command_buffer_ids: &[Comm
             let (buffer_guard, mut token) = hub.buffers.read(&mut token);
             let (texture_guard, mut token) = hub.textures.read(&mut token);
             let (texture_view_guard, _) = hub.texture_views.read(&mut token);
+            let mut trackers = device.trackers.lock();
+            let mut source_queues = Vec::new();
+            let mut releases = Vec::new();
+
+            // zero-initialize the resources before the pending writes are taken
+            {
+                let mut pending_writes_map = device.pending_writes.lock();
+                for &cmb_id in command_buffer_ids {
+                    device.initialize_used_resources(
+                        device_id,
+                        &mut *pending_writes_map,
+                        &mut command_buffer_guard[cmb_id],
+                        &*buffer_guard,
+                        &*texture_guard,
+                    );
+                }
+                pending_writes = pending_writes_map.remove(&queue_index);
+            }
 
-            // finish all the command buffers first
-            for &cmb_id in command_buffer_ids {
-                let comb = &mut command_buffer_guard[cmb_id];
+            // finish all the command buffers first, starting with the pending writes
+            for cmb_id in iter::once(None).chain(command_buffer_ids.iter().cloned().map(Some)) {
+                let comb = match cmb_id {
+                    Some(cmb_id) => &mut command_buffer_guard[cmb_id],
+                    None => match pending_writes {
+                        Some(ref mut writes) => &mut writes.command_buffer,
+                        None => continue,
+                    },
+                };
+                assert_eq!(
+                    comb.queue_index, queue_index,
+                    "Command buffer {:?} was recorded for a different queue",
+                    cmb_id
+                );
+                // swap chain images can be rendered to, copied, or sampled
+                let swap_chain_stages = all_image_stages() & queue_stages(comb.queue_type);
                 for link in comb.swap_chain_links.drain(..) {
                     let swap_chain = &swap_chain_guard[link.swap_chain_id];
                     let frame = &swap_chain.frames[link.image_index as usize];
@@ -1432,10 +2628,10 @@ pub fn queue_submit<B: GfxBackend>(queue_id: QueueId, command_buffer_ids: &[Comm
                             "{}. Image index {} with epoch {} != current epoch {:?}",
                             "Attempting to render to a swapchain output that has already been presented",
                             link.image_index, link.epoch, frame.acquired_epoch);
-                        wait_semaphores.push((
-                            &frame.sem_available,
-                            hal::pso::PipelineStage::COLOR_ATTACHMENT_OUTPUT,
-                        ));
+                        // headless frames are available as soon as they are acquired
+                        if swap_chain.headless.is_none() {
+                            wait_semaphores.push((&frame.sem_available, swap_chain_stages));
+                        }
                     }
                 }
 
@@ -1445,14 +2641,30 @@ pub fn queue_submit<B: GfxBackend>(queue_id: QueueId, command_buffer_ids: &[Comm
                 // update submission IDs
                 for id in comb.trackers.buffers.used() {
                     let buffer = &buffer_guard[id];
-                    assert!(buffer.pending_map_operation.is_none());
+                    assert!(buffer.pending_map_operations.is_empty());
                     buffer
                         .life_guard
                         .submission_index
                         .store(submit_index, Ordering::Release);
                 }
                 for id in comb.trackers.textures.used() {
-                    texture_guard[id]
+                    let texture = &texture_guard[id];
+                    // bind groups don't record swap chain links
+                    if let resource::TexturePlacement::SwapChain(ref link, _) = texture.placement {
+                        let swap_chain = &swap_chain_guard[link.swap_chain_id];
+                        let frame = &swap_chain.frames[link.image_index as usize];
+                        assert!(
+                            frame.acquired_epoch.is_some(),
+                            "Attempting to use a swapchain image {} that is not acquired",
+                            link.image_index
+                        );
+                        if frame.need_waiting.swap(false, Ordering::AcqRel) &&
+                            swap_chain.headless.is_none()
+                        {
+                            wait_semaphores.push((&frame.sem_available, swap_chain_stages));
+                        }
+                    }
+                    texture
                         .life_guard
                         .submission_index
                         .store(submit_index, Ordering::Release);
@@ -1470,13 +2682,82 @@ pub fn queue_submit<B: GfxBackend>(queue_id: QueueId, command_buffer_ids: &[Comm
                         .store(submit_index, Ordering::Release);
                 }
 
+                // take over the resources last used by other queues,
+                // transferring the ownership if their families differ
+                let mut acquires = Vec::new();
+                let buffer_transfers = trackers
+                    .buffers
+                    .transfer_ownership(&comb.trackers.buffers, queue_index);
+                for (source, pending) in buffer_transfers {
+                    if !source_queues.contains(&source) {
+                        source_queues.push(source);
+                    }
+                    let source_family = device.queue_family(source);
+                    if source_family != queue_family {
+                        let states = pending.to_states();
+                        let target = &buffer_guard[pending.id].raw;
+                        let families = source_family .. queue_family;
+                        releases.push((
+                            source,
+                            hal::memory::Barrier::Buffer {
+                                states: states.clone(),
+                                target,
+                                range: None .. None,
+                                families: Some(families.clone()),
+                            },
+                        ));
+                        acquires.push(hal::memory::Barrier::Buffer {
+                            states,
+                            target,
+                            range: None .. None,
+                            families: Some(families),
+                        });
+                    }
+                }
+                let texture_transfers = trackers
+                    .textures
+                    .transfer_ownership(&comb.trackers.textures, queue_index);
+                for (source, pending) in texture_transfers {
+                    if !source_queues.contains(&source) {
+                        source_queues.push(source);
+                    }
+                    let source_family = device.queue_family(source);
+                    if source_family != queue_family {
+                        let states = pending.to_states();
+                        let target = &texture_guard[pending.id].raw;
+                        let families = source_family .. queue_family;
+                        releases.push((
+                            source,
+                            hal::memory::Barrier::Image {
+                                states: states.clone(),
+                                target,
+                                range: pending.selector.clone(),
+                                families: Some(families.clone()),
+                            },
+                        ));
+                        acquires.push(hal::memory::Barrier::Image {
+                            states,
+                            target,
+                            range: pending.selector,
+                            families: Some(families),
+                        });
+                    }
+                }
+
                 // execute resource transitions
-                let mut transit = device.com_allocator.extend(comb);
+                let mut transit = device.command_allocator(queue_index).extend(comb);
                 unsafe {
                     transit.begin(
                         hal::command::CommandBufferFlags::ONE_TIME_SUBMIT,
                         hal::command::CommandBufferInheritanceInfo::default(),
                     );
+                    if !acquires.is_empty() {
+                        transit.pipeline_barrier(
+                            stages .. stages,
+                            hal::memory::Dependencies::empty(),
+                            acquires,
+                        );
+                    }
                 }
                 trace!("Stitching command buffer {:?} before submission", cmb_id);
                 command::CommandBuffer::insert_barriers(
@@ -1484,6 +2765,7 @@ pub fn queue_submit<B: GfxBackend>(queue_id: QueueId, command_buffer_ids: &[Comm
                     &mut *trackers,
                     &comb.trackers,
                     Stitch::Init,
+                    stages,
                     &*buffer_guard,
                     &*texture_guard,
                 );
@@ -1495,55 +2777,132 @@ pub fn queue_submit<B: GfxBackend>(queue_id: QueueId, command_buffer_ids: &[Comm
                     comb.raw.last_mut().unwrap().finish();
                 }
             }
+            drop(trackers);
+
+            // release the resources on their source queues, signaling
+            // the semaphores to be waited on by this submission
+            for source in source_queues {
+                let (barriers, rest): (Vec<_>, Vec<_>) = releases
+                    .into_iter()
+                    .partition(|&(queue, _)| queue == source);
+                releases = rest;
+                let release = if barriers.is_empty() {
+                    None
+                } else {
+                    let dev_stored = Stored {
+                        value: device_id,
+                        ref_count: device.life_guard.ref_count.clone(),
+                    };
+                    let mut comb = device
+                        .command_allocator(source)
+                        .allocate(dev_stored, &device.raw);
+                    let source_stages = queue_stages(device.queue_type(source));
+                    let raw = comb.raw.last_mut().unwrap();
+                    unsafe {
+                        raw.begin(
+                            hal::command::CommandBufferFlags::ONE_TIME_SUBMIT,
+                            hal::command::CommandBufferInheritanceInfo::default(),
+                        );
+                        raw.pipeline_barrier(
+                            source_stages .. source_stages,
+                            hal::memory::Dependencies::empty(),
+                            barriers.into_iter().map(|(_, barrier)| barrier),
+                        );
+                        raw.finish();
+                    }
+                    Some(comb)
+                };
+
+                let semaphore = device.raw.create_semaphore().unwrap();
+                let submission = hal::queue::Submission {
+                    command_buffers: release.iter().flat_map(|comb| &comb.raw),
+                    wait_semaphores: iter::empty::<(&B::Semaphore, hal::pso::PipelineStage)>(),
+                    signal_semaphores: iter::once(&semaphore),
+                };
+                unsafe {
+                    device.raw_queue_mut(source).submit(submission, None);
+                }
+                if let Some(comb) = release {
+                    device
+                        .command_allocator(source)
+                        .after_submit(comb, submit_index);
+                }
+                semaphores.push(semaphore);
+            }
         }
 
         // now prepare the GPU submission
         let fence = device.raw.create_fence(false).unwrap();
         {
+            wait_semaphores.extend(semaphores.iter().map(|semaphore| (semaphore, stages)));
             let (command_buffer_guard, _) = hub.command_buffers.read(&mut token);
             let submission = hal::queue::Submission::<_, _, &[B::Semaphore]> {
                 //TODO: may `OneShot` be enough?
-                command_buffers: command_buffer_ids
+                command_buffers: pending_writes
                     .iter()
-                    .flat_map(|&cmb_id| &command_buffer_guard[cmb_id].raw),
+                    .flat_map(|writes| &writes.command_buffer.raw)
+                    .chain(
+                        command_buffer_ids
+                            .iter()
+                            .flat_map(|&cmb_id| &command_buffer_guard[cmb_id].raw),
+                    ),
                 wait_semaphores,
                 signal_semaphores: &[], //TODO: signal `sem_present`?
             };
 
             unsafe {
-                device.queue_group.queues[0]
-                    .as_raw_mut()
+                device
+                    .raw_queue_mut(queue_index)
                     .submit(submission, Some(&fence));
             }
         }
 
-        (submit_index, fence)
+        (submit_index, fence, semaphores, pending_writes)
     };
 
     // No need for write access to the device from here on out
     let callbacks = {
         let mut token = Token::root();
         let (device_guard, mut token) = hub.devices.read(&mut token);
-        let device = &device_guard[queue_id];
+        let device = &device_guard[device_id];
+
+        let com_allocator = device.command_allocator(queue_index);
+        let mut resources = Vec::new();
+        if let Some(writes) = pending_writes {
+            com_allocator.after_submit(writes.command_buffer, submit_index);
+            resources.extend(writes.staging.into_iter().map(|resource| (None, resource)));
+        }
+
+        // return the command buffers to the allocator, the native objects
+        // they created are freed with the submission
+        for &cmb_id in command_buffer_ids {
+            let (mut cmd_buf, _) = hub.command_buffers.unregister(cmb_id, &mut token);
+            resources.extend(
+                cmd_buf
+                    .temporaries
+                    .drain(..)
+                    .map(|resource| (None, resource)),
+            );
+            com_allocator.after_submit(cmd_buf, submit_index);
+        }
 
-        let callbacks = device.maintain(false, &mut token);
+        let callbacks = device.maintain(hub, false, &mut token);
         device.pending.lock().active.alloc().init(ActiveSubmission {
             index: submit_index,
-            fence,
-            resources: Vec::new(),
+            queue_index,
+            fence: Arc::new(fence),
+            semaphores,
+            resources,
             mapped: Vec::new(),
+            work_done: Vec::new(),
         });
-
-        // finally, return the command buffers to the allocator
-        for &cmb_id in command_buffer_ids {
-            let (cmd_buf, _) = hub.command_buffers.unregister(cmb_id, &mut token);
-            device.com_allocator.after_submit(cmd_buf, submit_index);
-        }
+        device.wake_maintenance();
 
         callbacks
     };
 
-    Device::<B>::fire_map_callbacks(callbacks);
+    Device::<B>::fire_callbacks(callbacks);
+    submit_index
 }
 
 #[no_mangle]
@@ -1551,27 +2910,320 @@ pub extern "C" fn wgpu_queue_submit(
     queue_id: QueueId,
     command_buffers: *const CommandBufferId,
     command_buffers_length: usize,
-) {
+) -> SubmissionIndex {
     let command_buffer_ids =
         unsafe { slice::from_raw_parts(command_buffers, command_buffers_length) };
-    gfx_select!(queue_id => queue_submit(queue_id, command_buffer_ids))
+    gfx_select!(queue_id => queue_submit(&*GLOBAL, queue_id, command_buffer_ids))
 }
 
-pub fn device_create_render_pipeline<B: GfxBackend>(
-    device_id: DeviceId,
-    desc: &pipeline::RenderPipelineDescriptor,
-    id_in: Input<RenderPipelineId>,
-) -> Output<RenderPipelineId> {
-    let hub = B::hub();
-    let mut token = Token::root();
+/// Register a callback to be fired once all the work submitted to the queue
+/// so far is done executing on the GPU.
+///
+/// Like the buffer mapping callbacks, it's fired when the device is maintained.
+pub fn queue_on_submitted_work_done<B: GfxBackend>(
+    global: &Global,
+    queue_id: QueueId,
+    callback: SubmittedWorkDoneCallback,
+    userdata: *mut u8,
+) {
+    let hub = B::hub(global);
+    let (device_id, queue_index) = {
+        let (queue_guard, _) = hub.queues.read(&mut Token::root());
+        let queue = &queue_guard[queue_id];
+        (queue.device_id.value, queue.index)
+    };
 
-    let sc = desc.sample_count;
-    assert!(
-        sc == 1 || sc == 2 || sc == 4 || sc == 8 || sc == 16 || sc == 32,
-        "Invalid sample_count of {}",
-        sc
-    );
-    let sc = sc as u8;
+    let (device_guard, _) = hub.devices.read(&mut Token::root());
+    let device = &device_guard[device_id];
+    let mut pending = device.pending.lock();
+    let closure = SubmittedWorkDoneClosure { callback, userdata };
+    match pending
+        .active
+        .iter_mut()
+        .rev()
+        .find(|a| a.queue_index == queue_index)
+    {
+        Some(submission) => submission.work_done.push(closure),
+        None => {
+            pending.ready_work_done.push(closure);
+            drop(pending);
+            // the queue is idle, so the maintenance can fire it right away
+            device.wake_maintenance();
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wgpu_queue_on_submitted_work_done(
+    queue_id: QueueId,
+    callback: SubmittedWorkDoneCallback,
+    userdata: *mut u8,
+) {
+    gfx_select!(queue_id => queue_on_submitted_work_done(&*GLOBAL, queue_id, callback, userdata))
+}
+
+/// Write `data` into the buffer at the given offset.
+///
+/// The data is copied into a staging buffer right away, and the upload
+/// is executed on the queue before the command buffers of the next submission.
+/// The offset and the size of the data must be multiples of
+/// `COPY_BUFFER_ALIGNMENT`.
+pub fn queue_write_buffer<B: GfxBackend>(
+    global: &Global,
+    queue_id: QueueId,
+    buffer_id: BufferId,
+    buffer_offset: BufferAddress,
+    data: &[u8],
+) {
+    let hub = B::hub(global);
+    let (device_id, queue_index) = {
+        let (queue_guard, _) = hub.queues.read(&mut Token::root());
+        let queue = &queue_guard[queue_id];
+        (queue.device_id.value, queue.index)
+    };
+
+    let mut token = Token::root();
+    let (device_guard, mut token) = hub.devices.read(&mut token);
+    let device = &device_guard[device_id];
+    let (buffer_guard, _) = hub.buffers.read(&mut token);
+
+    let size = data.len() as BufferAddress;
+    let buffer_size = buffer_guard[buffer_id].size;
+    assert!(
+        buffer_offset % command::COPY_BUFFER_ALIGNMENT == 0 &&
+            size % command::COPY_BUFFER_ALIGNMENT == 0,
+        "Buffer write offset {} and size {} must be multiples of {}",
+        buffer_offset,
+        size,
+        command::COPY_BUFFER_ALIGNMENT
+    );
+    assert!(
+        buffer_offset
+            .checked_add(size)
+            .filter(|&end| end <= buffer_size)
+            .is_some(),
+        "Writing {} bytes at offset {} overruns the buffer of size {}",
+        size,
+        buffer_offset,
+        buffer_size
+    );
+    if data.is_empty() {
+        return;
+    }
+    let (staging, memory) = device.create_staging_buffer(data);
+    buffer_guard[buffer_id]
+        .uninitialized
+        .lock()
+        .take(buffer_offset .. buffer_offset + size);
+
+    let mut pending_writes = device.pending_writes.lock();
+    let writes = device.pending_writes_mut(device_id, &mut *pending_writes, queue_index);
+    let cmb = &mut writes.command_buffer;
+    let (dst_buffer, dst_pending) =
+        cmb.trackers
+            .buffers
+            .use_replace(&*buffer_guard, buffer_id, (), resource::BufferUsage::COPY_DST);
+    let barriers = dst_pending.map(|pending| hal::memory::Barrier::Buffer {
+        states: pending.to_states(),
+        target: &dst_buffer.raw,
+        families: None,
+        range: None .. None,
+    });
+    let region = hal::command::BufferCopy {
+        src: 0,
+        dst: buffer_offset,
+        size,
+    };
+    let stages = all_buffer_stages() & queue_stages(cmb.queue_type);
+    let cmb_raw = cmb.raw.last_mut().unwrap();
+    unsafe {
+        cmb_raw.pipeline_barrier(
+            stages .. stages,
+            hal::memory::Dependencies::empty(),
+            barriers,
+        );
+        cmb_raw.copy_buffer(&staging, &dst_buffer.raw, iter::once(region));
+    }
+    writes.staging.push(NativeResource::Buffer(staging, memory));
+}
+
+/// # Safety
+///
+/// `data` must point to `data_length` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn wgpu_queue_write_buffer(
+    queue_id: QueueId,
+    buffer_id: BufferId,
+    buffer_offset: BufferAddress,
+    data: *const u8,
+    data_length: usize,
+) {
+    let data = slice::from_raw_parts(data, data_length);
+    gfx_select!(queue_id => queue_write_buffer(&*GLOBAL, queue_id, buffer_id, buffer_offset, data))
+}
+
+/// Write texel `data` into a region of the texture.
+///
+/// Like `queue_write_buffer`, the upload goes through a staging buffer and
+/// is executed before the command buffers of the next submission.
+pub fn queue_write_texture<B: GfxBackend>(
+    global: &Global,
+    queue_id: QueueId,
+    destination: &command::TextureCopyView,
+    data: &[u8],
+    data_layout: &command::TextureDataLayout,
+    size: Extent3d,
+) {
+    let hub = B::hub(global);
+    let (device_id, queue_index) = {
+        let (queue_guard, _) = hub.queues.read(&mut Token::root());
+        let queue = &queue_guard[queue_id];
+        (queue.device_id.value, queue.index)
+    };
+
+    let mut token = Token::root();
+    let (device_guard, mut token) = hub.devices.read(&mut token);
+    let device = &device_guard[device_id];
+    let (_, mut token) = hub.buffers.read(&mut token); //skip token
+    let (texture_guard, _) = hub.textures.read(&mut token);
+
+    let texture = &texture_guard[destination.texture];
+    let aspects = texture.full_range.aspects;
+    let buffer_width = command::validate_buffer_texture_copy(
+        texture.kind,
+        texture.format,
+        destination,
+        data_layout.row_pitch,
+        data_layout.image_height,
+        size,
+    );
+    let format_desc = conv::map_texture_format(texture.format).surface_desc();
+    let block_size = format_desc.bits as u32 / command::BITS_PER_BYTE;
+    let (block_width, block_height) = (format_desc.dim.0 as u32, format_desc.dim.1 as u32);
+    let image_height = if data_layout.image_height == 0 {
+        size.height
+    } else {
+        data_layout.image_height
+    };
+    if size.width == 0 || size.height == 0 || size.depth == 0 {
+        return;
+    }
+    // the data is laid out in rows of texel blocks
+    let block_rows = (size.height - 1) / block_height + 1;
+    let blocks_per_row = (size.width - 1) / block_width + 1;
+    let bytes_per_row = blocks_per_row as BufferAddress * block_size as BufferAddress;
+    // the rows after the first one start `row_pitch` bytes apart
+    let row_skips = (size.depth - 1) as BufferAddress * image_height as BufferAddress /
+        block_height as BufferAddress +
+        (block_rows - 1) as BufferAddress;
+    assert!(
+        row_skips == 0 || data_layout.row_pitch as BufferAddress >= bytes_per_row,
+        "Row pitch {} is smaller than the {} bytes of a row of the {:?} region",
+        data_layout.row_pitch,
+        bytes_per_row,
+        size
+    );
+    let required_size = (data_layout.row_pitch as BufferAddress)
+        .checked_mul(row_skips)
+        .and_then(|skipped| skipped.checked_add(bytes_per_row))
+        .and_then(|rows| rows.checked_add(data_layout.offset))
+        .unwrap_or(!0);
+    assert!(
+        required_size <= data.len() as BufferAddress,
+        "Texture data of {} bytes is too small for the {:?} region at offset {}",
+        data.len(),
+        size,
+        data_layout.offset
+    );
+    let (staging, memory) = device.create_staging_buffer(data);
+
+    let mut pending_writes = device.pending_writes.lock();
+    let writes = device.pending_writes_mut(device_id, &mut *pending_writes, queue_index);
+    // a partially written subresource has to be zeroed first
+    let uninitialized = texture.take_uninitialized(&destination.to_selector(aspects));
+    if !uninitialized.is_empty() && !destination.covers_subresource(texture.kind, size) {
+        device.zero_texture(writes, &*texture_guard, destination.texture, uninitialized);
+    }
+    let cmb = &mut writes.command_buffer;
+    let (dst_texture, dst_pending) = cmb.trackers.textures.use_replace(
+        &*texture_guard,
+        destination.texture,
+        destination.to_selector(aspects),
+        resource::TextureUsage::COPY_DST,
+    );
+    let barriers = dst_pending.map(|pending| hal::memory::Barrier::Image {
+        states: pending.to_states(),
+        target: &dst_texture.raw,
+        families: None,
+        range: pending.selector,
+    });
+
+    if let resource::TexturePlacement::SwapChain(ref link, _) = dst_texture.placement {
+        cmb.swap_chain_links.alloc().init(swap_chain::SwapChainLink {
+            swap_chain_id: link.swap_chain_id.clone(),
+            epoch: *link.epoch.lock(),
+            image_index: link.image_index,
+        });
+    }
+
+    let region = hal::command::BufferImageCopy {
+        buffer_offset: data_layout.offset,
+        buffer_width,
+        buffer_height: data_layout.image_height,
+        image_layers: destination.to_sub_layers(aspects),
+        image_offset: conv::map_origin(destination.origin),
+        image_extent: conv::map_extent(size),
+    };
+    let stages = all_image_stages() & queue_stages(cmb.queue_type);
+    let cmb_raw = cmb.raw.last_mut().unwrap();
+    unsafe {
+        cmb_raw.pipeline_barrier(
+            stages .. stages,
+            hal::memory::Dependencies::empty(),
+            barriers,
+        );
+        cmb_raw.copy_buffer_to_image(
+            &staging,
+            &dst_texture.raw,
+            hal::image::Layout::TransferDstOptimal,
+            iter::once(region),
+        );
+    }
+    writes.staging.push(NativeResource::Buffer(staging, memory));
+}
+
+/// # Safety
+///
+/// `data` must point to `data_length` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn wgpu_queue_write_texture(
+    queue_id: QueueId,
+    destination: &command::TextureCopyView,
+    data: *const u8,
+    data_length: usize,
+    data_layout: &command::TextureDataLayout,
+    size: Extent3d,
+) {
+    let data = slice::from_raw_parts(data, data_length);
+    gfx_select!(queue_id => queue_write_texture(&*GLOBAL, queue_id, destination, data, data_layout, size))
+}
+
+pub fn device_create_render_pipeline<B: GfxBackend>(
+    global: &Global,
+    device_id: DeviceId,
+    desc: &pipeline::RenderPipelineDescriptor,
+    id_in: Input<RenderPipelineId>,
+) -> RenderPipelineId {
+    let hub = B::hub(global);
+    let mut token = Token::root();
+
+    let sc = desc.sample_count;
+    assert!(
+        sc == 1 || sc == 2 || sc == 4 || sc == 8 || sc == 16 || sc == 32,
+        "Invalid sample_count of {}",
+        sc
+    );
+    let sc = sc as u8;
 
     let color_states =
         unsafe { slice::from_raw_parts(desc.color_states, desc.color_states_length) };
@@ -1658,9 +3310,50 @@ pub fn device_create_render_pipeline<B: GfxBackend>(
         depth_bounds: None,
     };
 
-    let raw_pipeline = {
+    let (raw_pipeline, dev_stored) = {
+        let (adapter_guard, mut token) = hub.adapters.read(&mut token);
         let (device_guard, mut token) = hub.devices.read(&mut token);
         let device = &device_guard[device_id];
+        let physical_device = &adapter_guard[device.adapter_id].raw.physical_device;
+        for state in color_states {
+            let features = texture_format_features::<B>(physical_device, state.format);
+            assert!(
+                features
+                    .flags
+                    .contains(TextureFormatFeatureFlags::COLOR_ATTACHMENT),
+                "Format {:?} can't be used as a color attachment",
+                state.format
+            );
+            assert!(
+                (state.color_blend == pipeline::BlendDescriptor::REPLACE &&
+                    state.alpha_blend == pipeline::BlendDescriptor::REPLACE) ||
+                    features.flags.contains(TextureFormatFeatureFlags::BLENDABLE),
+                "Format {:?} doesn't support blending",
+                state.format
+            );
+            assert!(
+                features.sample_counts & desc.sample_count != 0,
+                "Format {:?} doesn't support {} samples",
+                state.format,
+                desc.sample_count
+            );
+        }
+        if let Some(state) = depth_stencil_state {
+            let features = texture_format_features::<B>(physical_device, state.format);
+            assert!(
+                features
+                    .flags
+                    .contains(TextureFormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT),
+                "Format {:?} can't be used as a depth-stencil attachment",
+                state.format
+            );
+            assert!(
+                features.sample_counts & desc.sample_count != 0,
+                "Format {:?} doesn't support {} samples",
+                state.format,
+                desc.sample_count
+            );
+        }
         let (pipeline_layout_guard, mut token) = hub.pipeline_layouts.read(&mut token);
         let layout = &pipeline_layout_guard[desc.layout].raw;
         let (shader_module_guard, _) = hub.shader_modules.read(&mut token);
@@ -1777,12 +3470,17 @@ pub fn device_create_render_pipeline<B: GfxBackend>(
         };
 
         // TODO: cache
-        unsafe {
+        let raw = unsafe {
             device
                 .raw
                 .create_graphics_pipeline(&pipeline_desc, None)
                 .unwrap()
-        }
+        };
+        let dev_stored = Stored {
+            value: device_id,
+            ref_count: device.life_guard.ref_count.clone(),
+        };
+        (raw, dev_stored)
     };
 
     let pass_context = RenderPassContext {
@@ -1805,6 +3503,7 @@ pub fn device_create_render_pipeline<B: GfxBackend>(
 
     let pipeline = pipeline::RenderPipeline {
         raw: raw_pipeline,
+        device_id: dev_stored,
         layout_id: desc.layout,
         pass_context,
         flags,
@@ -1817,26 +3516,26 @@ pub fn device_create_render_pipeline<B: GfxBackend>(
         .register_identity(id_in, pipeline, &mut token)
 }
 
-#[cfg(not(feature = "remote"))]
 #[no_mangle]
 pub extern "C" fn wgpu_device_create_render_pipeline(
     device_id: DeviceId,
     desc: &pipeline::RenderPipelineDescriptor,
 ) -> RenderPipelineId {
-    gfx_select!(device_id => device_create_render_pipeline(device_id, desc, PhantomData))
+    gfx_select!(device_id => device_create_render_pipeline(&*GLOBAL, device_id, desc, None))
 }
 
 pub fn device_create_compute_pipeline<B: GfxBackend>(
+    global: &Global,
     device_id: DeviceId,
     desc: &pipeline::ComputePipelineDescriptor,
     id_in: Input<ComputePipelineId>,
-) -> Output<ComputePipelineId> {
-    let hub = B::hub();
+) -> ComputePipelineId {
+    let hub = B::hub(global);
     let mut token = Token::root();
 
-    let raw_pipeline = {
+    let (raw_pipeline, dev_stored) = {
         let (device_guard, mut token) = hub.devices.read(&mut token);
-        let device = &device_guard[device_id].raw;
+        let device = &device_guard[device_id];
         let (pipeline_layout_guard, mut token) = hub.pipeline_layouts.read(&mut token);
         let layout = &pipeline_layout_guard[desc.layout].raw;
         let pipeline_stage = &desc.compute_stage;
@@ -1863,55 +3562,79 @@ pub fn device_create_compute_pipeline<B: GfxBackend>(
             parent,
         };
 
-        unsafe {
+        let raw = unsafe {
             device
+                .raw
                 .create_compute_pipeline(&pipeline_desc, None)
                 .unwrap()
-        }
+        };
+        let dev_stored = Stored {
+            value: device_id,
+            ref_count: device.life_guard.ref_count.clone(),
+        };
+        (raw, dev_stored)
     };
 
     let pipeline = pipeline::ComputePipeline {
         raw: raw_pipeline,
+        device_id: dev_stored,
         layout_id: desc.layout,
     };
     hub.compute_pipelines
         .register_identity(id_in, pipeline, &mut token)
 }
 
-#[cfg(not(feature = "remote"))]
 #[no_mangle]
 pub extern "C" fn wgpu_device_create_compute_pipeline(
     device_id: DeviceId,
     desc: &pipeline::ComputePipelineDescriptor,
 ) -> ComputePipelineId {
-    gfx_select!(device_id => device_create_compute_pipeline(device_id, desc, PhantomData))
+    gfx_select!(device_id => device_create_compute_pipeline(&*GLOBAL, device_id, desc, None))
 }
 
 pub fn device_create_swap_chain<B: GfxBackend>(
+    global: &Global,
     device_id: DeviceId,
     surface_id: SurfaceId,
     desc: &swap_chain::SwapChainDescriptor,
     id_in: Input<SwapChainId>,
-    image_ids: Vec<(Input<TextureId>, Input<TextureViewId>)>,
-) -> Output<SwapChainId> {
+    mut image_ids: Vec<(Input<TextureId>, Input<TextureViewId>)>,
+) -> SwapChainId {
     info!("creating swap chain {:?}", desc);
-    let hub = B::hub();
+    let hub = B::hub(global);
     let mut token = Token::root();
 
-    let (mut surface_guard, mut token) = GLOBAL.surfaces.write(&mut token);
+    let (mut surface_guard, mut token) = global.surfaces.write(&mut token);
     let (adapter_guard, mut token) = hub.adapters.read(&mut token);
     let (device_guard, mut token) = hub.devices.read(&mut token);
     let device = &device_guard[device_id];
     let surface = &mut surface_guard[surface_id];
 
-    let (caps, formats, _present_modes) = {
+    let (caps, formats, present_modes) = {
         let suf = B::get_surface_mut(surface);
         let adapter = &adapter_guard[device.adapter_id];
         assert!(suf.supports_queue_family(&adapter.raw.queue_families[0]));
         suf.compatibility(&adapter.raw.physical_device)
     };
-    let num_frames = *caps.image_count.start(); //TODO: configure?
-    let config = desc.to_hal(num_frames);
+    let mut desc = desc.clone();
+    desc.present_mode = desc.present_mode.fallback(&present_modes);
+    desc.image_count = desc
+        .image_count
+        .max(*caps.image_count.start())
+        .min(*caps.image_count.end());
+    // the presentation engine can hold on to the minimum number of images
+    desc.max_frame_latency = desc
+        .max_frame_latency
+        .max(1)
+        .min(desc.image_count - *caps.image_count.start() + 1);
+    let config = desc.to_hal(desc.image_count);
+
+    assert!(
+        caps.usage.contains(config.image_usage),
+        "Requested usage {:?} is not supported by the surface: {:?}",
+        desc.usage,
+        conv::map_hal_texture_usage(caps.usage)
+    );
 
     if let Some(formats) = formats {
         assert!(
@@ -1970,26 +3693,31 @@ pub fn device_create_swap_chain<B: GfxBackend>(
             .create_swapchain(suf, config, old_raw)
             .unwrap()
     };
+    // the presentation engine may create more images than requested,
+    // their IDs are allocated by the context
+    image_ids.resize(images.len(), (None, None));
 
-    let (id, id_out) = hub.swap_chains.new_identity(id_in);
+    let id = hub.swap_chains.new_identity(id_in);
     surface.swap_chain = Some(id);
 
     let mut trackers = device.trackers.lock();
     let mut swap_chain = swap_chain::SwapChain {
         raw: Some(raw_swap_chain),
-        surface_id: Stored {
+        surface_id: Some(Stored {
             value: surface_id,
             ref_count: surface.ref_count.clone(),
-        },
+        }),
         device_id: Stored {
             value: device_id,
             ref_count: device.life_guard.ref_count.clone(),
         },
         desc: desc.clone(),
-        frames: Vec::with_capacity(num_frames as usize),
-        acquired: Vec::with_capacity(1), //TODO: get it from gfx-hal?
+        frames: Vec::with_capacity(desc.image_count as usize),
+        acquired: Vec::with_capacity(desc.max_frame_latency as usize),
+        pending_image: None,
         sem_available,
         command_pool,
+        headless: None,
     };
 
     for ((i, image), (id_texture_in, id_view_in)) in images.into_iter().enumerate().zip(image_ids) {
@@ -2019,16 +3747,23 @@ pub fn device_create_swap_chain<B: GfxBackend>(
                 ref_count: device.life_guard.ref_count.clone(),
             },
             kind,
+            view_capabilities: hal::image::ViewCapabilities::empty(),
             format: desc.format,
+            usage: desc.usage,
             full_range: range.clone(),
-            placement: resource::TexturePlacement::SwapChain(swap_chain::SwapChainLink {
-                swap_chain_id: id, //TODO: strongly
-                epoch: Mutex::new(0),
-                image_index: i as hal::SwapImageIndex,
-            }),
+            // the presentation engine owns the contents of the frames
+            uninitialized: Mutex::new(Vec::new()),
+            placement: resource::TexturePlacement::SwapChain(
+                swap_chain::SwapChainLink {
+                    swap_chain_id: id, //TODO: strongly
+                    epoch: Mutex::new(0),
+                    image_index: i as hal::SwapImageIndex,
+                },
+                None,
+            ),
             life_guard: LifeGuard::new(),
         };
-        let (id_texture, _) = hub.textures.new_identity(id_texture_in);
+        let id_texture = hub.textures.new_identity(id_texture_in);
         let texture_id = Stored {
             ref_count: texture.life_guard.ref_count.clone(),
             value: id_texture,
@@ -2051,7 +3786,7 @@ pub fn device_create_swap_chain<B: GfxBackend>(
             is_owned_by_swap_chain: true,
             life_guard: LifeGuard::new(),
         };
-        let (id_view, _) = hub.texture_views.new_identity(id_view_in);
+        let id_view = hub.texture_views.new_identity(id_view_in);
         let view_id = Stored {
             ref_count: view.life_guard.ref_count.clone(),
             value: id_view,
@@ -2072,94 +3807,679 @@ pub fn device_create_swap_chain<B: GfxBackend>(
     }
 
     hub.swap_chains.register(id, swap_chain, &mut token);
-    id_out
+    id
 }
 
-#[cfg(not(feature = "remote"))]
 #[no_mangle]
 pub extern "C" fn wgpu_device_create_swap_chain(
     device_id: DeviceId,
     surface_id: SurfaceId,
     desc: &swap_chain::SwapChainDescriptor,
 ) -> SwapChainId {
-    let image_ids = vec![(PhantomData, PhantomData); 10]; //TODO: make this compatible with "remote"
-    gfx_select!(device_id => device_create_swap_chain(device_id, surface_id, desc, PhantomData, image_ids))
-}
-
-pub fn device_poll<B: GfxBackend>(device_id: DeviceId, force_wait: bool) {
-    let hub = B::hub();
-    let callbacks = {
-        let (device_guard, mut token) = hub.devices.read(&mut Token::root());
-        device_guard[device_id].maintain(force_wait, &mut token)
-    };
-    Device::<B>::fire_map_callbacks(callbacks);
+    let image_ids = vec![(None, None); desc.image_count as usize];
+    gfx_select!(device_id => device_create_swap_chain(&*GLOBAL, device_id, surface_id, desc, None, image_ids))
 }
 
-#[no_mangle]
-pub extern "C" fn wgpu_device_poll(device_id: DeviceId, force_wait: bool) {
-    gfx_select!(device_id => device_poll(device_id, force_wait))
-}
+/// Create a swap chain that isn't attached to any surface.
+///
+/// It owns a ring of `desc.image_count` textures (at least one), which are
+/// acquired in order. Presenting a frame copies its contents to the host and
+/// passes them to `callback` before returning. The present mode is ignored.
+pub fn device_create_headless_swap_chain<B: GfxBackend>(
+    global: &Global,
+    device_id: DeviceId,
+    desc: &swap_chain::SwapChainDescriptor,
+    callback: swap_chain::SwapChainFrameCallback,
+    userdata: *mut u8,
+    id_in: Input<SwapChainId>,
+    image_ids: Vec<(Input<TextureId>, Input<TextureViewId>)>,
+) -> SwapChainId {
+    info!("creating headless swap chain {:?}", desc);
+    let hub = B::hub(global);
+    let mut token = Token::root();
 
-pub fn device_destroy<B: GfxBackend>(device_id: DeviceId) {
-    let hub = B::hub();
-    let (device, mut token) = hub.devices.unregister(device_id, &mut Token::root());
-    device.maintain(true, &mut token);
-    device.com_allocator.destroy(&device.raw);
-}
+    let (adapter_guard, mut token) = hub.adapters.read(&mut token);
+    let (device_guard, mut token) = hub.devices.read(&mut token);
+    let device = &device_guard[device_id];
 
-#[no_mangle]
-pub extern "C" fn wgpu_device_destroy(device_id: DeviceId) {
-    gfx_select!(device_id => device_destroy(device_id))
-}
+    let format_desc = conv::map_texture_format(desc.format).surface_desc();
+    assert!(
+        format_desc.aspects == hal::format::Aspects::COLOR && format_desc.dim == (1, 1),
+        "Headless swap chains require an uncompressed color format, got {:?}",
+        desc.format
+    );
+    let mut desc = desc.clone();
+    desc.image_count = desc.image_count.max(1);
+    assert!(
+        desc.image_count as usize <= image_ids.len(),
+        "Unable to create more than {} headless swap chain images",
+        image_ids.len()
+    );
+    desc.max_frame_latency = desc.max_frame_latency.max(1).min(desc.image_count);
 
-pub type BufferMapReadCallback =
-    extern "C" fn(status: BufferMapAsyncStatus, data: *const u8, userdata: *mut u8);
-pub type BufferMapWriteCallback =
-    extern "C" fn(status: BufferMapAsyncStatus, data: *mut u8, userdata: *mut u8);
+    let format_features = texture_format_features::<B>(
+        &adapter_guard[device.adapter_id].raw.physical_device,
+        desc.format,
+    );
+    // the frames are read back with transfers
+    let texture_desc = resource::TextureDescriptor {
+        usage: desc.usage | resource::TextureUsage::COPY_SRC,
+        ..desc.to_texture_desc()
+    };
 
-pub fn buffer_map_async<B: GfxBackend>(
-    buffer_id: BufferId,
-    usage: resource::BufferUsage,
-    operation: BufferMapOperation,
-) {
-    let hub = B::hub();
-    let mut token = Token::root();
-    let (device_guard, mut token) = hub.devices.read(&mut token);
+    let bytes_per_row = desc.width * (format_desc.bits as u32 / command::BITS_PER_BYTE);
+    let (readback, memory) =
+        device.create_readback_buffer(bytes_per_row as BufferAddress * desc.height as BufferAddress);
+    let command_pool = unsafe {
+        device
+            .raw
+            .create_command_pool_typed(
+                &device.queue_group,
+                hal::pool::CommandPoolCreateFlags::RESET_INDIVIDUAL,
+            )
+            .unwrap()
+    };
 
-    let (device_id, ref_count) = {
-        let (mut buffer_guard, _) = hub.buffers.write(&mut token);
-        let buffer = &mut buffer_guard[buffer_id];
+    let id = hub.swap_chains.new_identity(id_in);
+    let mut trackers = device.trackers.lock();
+    let mut swap_chain = swap_chain::SwapChain {
+        raw: None,
+        surface_id: None,
+        device_id: Stored {
+            value: device_id,
+            ref_count: device.life_guard.ref_count.clone(),
+        },
+        desc: desc.clone(),
+        frames: Vec::with_capacity(desc.image_count as usize),
+        acquired: Vec::with_capacity(desc.max_frame_latency as usize),
+        pending_image: None,
+        sem_available: device.raw.create_semaphore().unwrap(),
+        command_pool,
+        headless: Some(swap_chain::Headless {
+            next_image: 0,
+            readback,
+            memory,
+            bytes_per_row,
+            callback,
+            userdata,
+        }),
+    };
 
-        if buffer.pending_map_operation.is_some() {
-            operation.call_error();
-            return;
-        }
+    for (i, (id_texture_in, id_view_in)) in image_ids
+        .into_iter()
+        .take(desc.image_count as usize)
+        .enumerate()
+    {
+        let mut texture = device.create_texture(device_id, &texture_desc, &format_features);
+        texture.usage = desc.usage;
+        texture.placement = match texture.placement {
+            resource::TexturePlacement::Memory(memory) => resource::TexturePlacement::SwapChain(
+                swap_chain::SwapChainLink {
+                    swap_chain_id: id, //TODO: strongly
+                    epoch: Mutex::new(0),
+                    image_index: i as hal::SwapImageIndex,
+                },
+                Some(memory),
+            ),
+            resource::TexturePlacement::SwapChain(..) => unreachable!(),
+        };
+        let range = texture.full_range.clone();
 
-        buffer.pending_map_operation = Some(operation);
-        (buffer.device_id.value, buffer.life_guard.ref_count.clone())
-    };
+        let view_raw = unsafe {
+            device
+                .raw
+                .create_image_view(
+                    &texture.raw,
+                    hal::image::ViewKind::D2,
+                    conv::map_texture_format(desc.format),
+                    hal::format::Swizzle::NO,
+                    range.clone(),
+                )
+                .unwrap()
+        };
+        let kind = texture.kind;
+        let id_texture = hub.textures.new_identity(id_texture_in);
+        let texture_id = Stored {
+            ref_count: texture.life_guard.ref_count.clone(),
+            value: id_texture,
+        };
+        trackers.textures.init(
+            id_texture,
+            &texture_id.ref_count,
+            range.clone(),
+            resource::TextureUsage::UNINITIALIZED,
+        );
+        hub.textures.register(id_texture, texture, &mut token);
 
-    let device = &device_guard[device_id];
+        let view = resource::TextureView {
+            raw: view_raw,
+            texture_id: texture_id.clone(),
+            format: desc.format,
+            extent: kind.extent(),
+            samples: kind.num_samples(),
+            range,
+            is_owned_by_swap_chain: true,
+            life_guard: LifeGuard::new(),
+        };
+        let id_view = hub.texture_views.new_identity(id_view_in);
+        let view_id = Stored {
+            ref_count: view.life_guard.ref_count.clone(),
+            value: id_view,
+        };
+        trackers.views.init(id_view, &view_id.ref_count, (), ());
+        hub.texture_views.register(id_view, view, &mut token);
 
-    device
-        .trackers
-        .lock()
-        .buffers
-        .change_replace(buffer_id, &ref_count, (), usage);
+        swap_chain.frames.alloc().init(swap_chain::Frame {
+            texture_id,
+            view_id,
+            fence: device.raw.create_fence(true).unwrap(),
+            sem_available: device.raw.create_semaphore().unwrap(),
+            sem_present: device.raw.create_semaphore().unwrap(),
+            acquired_epoch: None,
+            need_waiting: AtomicBool::new(false),
+            comb: swap_chain.command_pool.acquire_command_buffer(),
+        });
+    }
 
-    device.pending.lock().map(buffer_id, ref_count);
+    hub.swap_chains.register(id, swap_chain, &mut token);
+    id
 }
 
 #[no_mangle]
-pub extern "C" fn wgpu_buffer_map_read_async(
-    buffer_id: BufferId,
-    start: BufferAddress,
-    size: BufferAddress,
-    callback: BufferMapReadCallback,
+pub extern "C" fn wgpu_device_create_headless_swap_chain(
+    device_id: DeviceId,
+    desc: &swap_chain::SwapChainDescriptor,
+    callback: swap_chain::SwapChainFrameCallback,
     userdata: *mut u8,
-) {
+) -> SwapChainId {
+    let image_ids = vec![(None, None); desc.image_count.max(1) as usize];
+    gfx_select!(device_id => device_create_headless_swap_chain(&*GLOBAL, device_id, desc, callback, userdata, None, image_ids))
+}
+
+/// Destroy a swap chain, detaching it from its surface.
+///
+/// The frames must not be acquired. Their textures and views are destroyed
+/// once they are no longer used by the device.
+pub fn swap_chain_destroy<B: GfxBackend>(global: &Global, swap_chain_id: SwapChainId) {
+    let hub = B::hub(global);
+    let mut token = Token::root();
+
+    let (mut surface_guard, mut token) = global.surfaces.write(&mut token);
+    let (device_guard, mut token) = hub.devices.read(&mut token);
+    {
+        let (swap_chain_guard, _) = hub.swap_chains.read(&mut token);
+        let swap_chain = &swap_chain_guard[swap_chain_id];
+        assert!(
+            swap_chain.acquired.is_empty(),
+            "Destroyed swap chain has acquired frames: {:?}",
+            swap_chain.acquired
+        );
+    }
+    let (swap_chain, _) = hub.swap_chains.unregister(swap_chain_id, &mut token);
+    if let Some(ref surface_id) = swap_chain.surface_id {
+        let surface = &mut surface_guard[surface_id.value];
+        if surface.swap_chain == Some(swap_chain_id) {
+            surface.swap_chain = None;
+        }
+    }
+
+    let device = &device_guard[swap_chain.device_id.value];
+    let fences = swap_chain.frames.iter().map(|frame| &frame.fence);
+    if let Err(e) = unsafe {
+        device
+            .raw
+            .wait_for_fences(fences, hal::device::WaitFor::All, !0)
+    } {
+        warn!("Unable to wait for the frames of the swap chain: {:?}", e);
+    }
+    {
+        let mut pending = device.pending.lock();
+        for frame in swap_chain.frames.iter() {
+            pending.destroy(
+                ResourceId::Texture(frame.texture_id.value),
+                frame.texture_id.ref_count.clone(),
+            );
+            pending.destroy(
+                ResourceId::TextureView(frame.view_id.value),
+                frame.view_id.ref_count.clone(),
+            );
+        }
+    }
+    device.dispose_swap_chain(swap_chain);
+}
+
+#[no_mangle]
+pub extern "C" fn wgpu_swap_chain_destroy(swap_chain_id: SwapChainId) {
+    gfx_select!(swap_chain_id => swap_chain_destroy(&*GLOBAL, swap_chain_id))
+}
+
+pub fn device_poll<B: GfxBackend>(global: &Global, device_id: DeviceId, force_wait: bool) {
+    let hub = B::hub(global);
+    let callbacks = {
+        let (device_guard, mut token) = hub.devices.read(&mut Token::root());
+        device_guard[device_id].maintain(hub, force_wait, &mut token)
+    };
+    Device::<B>::fire_callbacks(callbacks);
+}
+
+#[no_mangle]
+pub extern "C" fn wgpu_device_poll(device_id: DeviceId, force_wait: bool) {
+    gfx_select!(device_id => device_poll(&*GLOBAL, device_id, force_wait))
+}
+
+/// Wait for the submission with the given index, and all the ones before it,
+/// to finish executing on the GPU, up to `timeout_ns` nanoseconds.
+///
+/// Returns `false` if the timeout has expired. The retired submissions are
+/// processed right away, firing their callbacks on the calling thread.
+pub fn device_wait_for_submission<B: GfxBackend>(
+    global: &Global,
+    device_id: DeviceId,
+    submission_index: SubmissionIndex,
+    timeout_ns: u64,
+) -> bool {
+    let hub = B::hub(global);
+    let (raw, fences) = {
+        let (device_guard, _) = hub.devices.read(&mut Token::root());
+        let device = &device_guard[device_id];
+        assert!(
+            submission_index <= device.life_guard.submission_index.load(Ordering::Acquire),
+            "Submission {} has not been made yet",
+            submission_index
+        );
+        let fences = device.pending.lock().fences_until(submission_index);
+        (Arc::clone(&device.raw), fences)
+    };
+    // the device is not locked while waiting
+    let is_done = fences.is_empty() || {
+        let status = unsafe {
+            raw.wait_for_fences(
+                fences.iter().map(|fence| &**fence),
+                hal::device::WaitFor::All,
+                timeout_ns,
+            )
+        };
+        status.unwrap_or(false)
+    };
+    // release the fences before the cleanup, so that they can be destroyed
+    drop(fences);
+    device_poll::<B>(global, device_id, false);
+    is_done
+}
+
+#[no_mangle]
+pub extern "C" fn wgpu_device_wait_for_submission(
+    device_id: DeviceId,
+    submission_index: SubmissionIndex,
+    timeout_ns: u64,
+) -> bool {
+    gfx_select!(device_id => device_wait_for_submission(&*GLOBAL, device_id, submission_index, timeout_ns))
+}
+
+struct ThreadWaker(thread::Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Drive `future` to completion on the current thread, polling the device
+/// in between so that pending buffer mappings get resolved.
+///
+/// Each device poll waits for the submitted work to finish, and the mappings
+/// done by it wake their futures. If the future is still pending after that,
+/// the thread is parked until it's woken by another thread maintaining the device.
+/// This is meant for futures that depend on the GPU, like the ones from
+/// `buffer_map_read` and `buffer_map_write`.
+pub fn device_block_on<B: GfxBackend, F: Future>(
+    global: &Global,
+    device_id: DeviceId,
+    future: F,
+) -> F::Output {
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut context = Context::from_waker(&waker);
+    let mut future = Box::pin(future);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return output;
+        }
+        device_poll::<B>(global, device_id, true);
+        // returns right away if the poll above has woken the future
+        thread::park();
+    }
+}
+
+/// A thread maintaining the device in the background,
+/// see `DeviceDescriptor::background_maintenance`.
+#[derive(Debug)]
+pub(crate) struct MaintenanceThread {
+    stop: Arc<AtomicBool>,
+    handle: thread::JoinHandle<()>,
+}
+
+impl MaintenanceThread {
+    pub(crate) fn spawn<B: GfxBackend>(global: &Arc<Global>, device_id: DeviceId) -> Self {
+        let global = Arc::downgrade(global);
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_flag = Arc::clone(&stop);
+        let handle = thread::Builder::new()
+            .name("wgpu-maintenance".to_string())
+            .spawn(move || {
+                while !stop_flag.load(Ordering::Acquire) {
+                    // the thread must not keep the context alive on its own
+                    let (raw, fence) = match global.upgrade() {
+                        Some(global) => {
+                            let hub = B::hub(&global);
+                            let (device_guard, _) = hub.devices.read(&mut Token::root());
+                            let device = &device_guard[device_id];
+                            let fence = device.pending.lock().oldest_fence();
+                            (Arc::clone(&device.raw), fence)
+                        }
+                        None => break,
+                    };
+                    // nothing is locked while waiting
+                    match fence {
+                        Some(fence) => unsafe {
+                            let _ = raw.wait_for_fence(&fence, MAINTENANCE_WAIT_MS * 1_000_000);
+                        },
+                        // woken up by new submissions and mapping requests
+                        None => thread::park(),
+                    }
+
+                    let global = match global.upgrade() {
+                        Some(global) => global,
+                        None => break,
+                    };
+                    let hub = B::hub(&global);
+                    let callbacks = {
+                        let (device_guard, mut token) = hub.devices.read(&mut Token::root());
+                        device_guard[device_id].maintain(hub, false, &mut token)
+                    };
+                    Device::<B>::fire_callbacks(callbacks);
+                }
+            })
+            .expect("Unable to spawn the maintenance thread");
+        MaintenanceThread { stop, handle }
+    }
+
+    fn stop(self) {
+        self.stop.store(true, Ordering::Release);
+        // the device may be destroyed from a callback fired by the thread itself
+        if self.handle.thread().id() != thread::current().id() {
+            self.handle.thread().unpark();
+            self.handle.join().unwrap();
+        }
+    }
+}
+
+pub fn device_destroy<B: GfxBackend>(global: &Global, device_id: DeviceId) {
+    let hub = B::hub(global);
+    let maintenance = {
+        let (device_guard, _) = hub.devices.read(&mut Token::root());
+        let maintenance = device_guard[device_id].maintenance.lock().take();
+        maintenance
+    };
+    if let Some(maintenance) = maintenance {
+        maintenance.stop();
+    }
+    let device = hub.devices.unregister(device_id, &mut Token::root()).0;
+    if let Err(e) = device.raw.wait_idle() {
+        warn!(
+            "Failed to wait for the device {:?} to be idle: {:?}",
+            device_id, e
+        );
+    }
+
+    let devices = [(device_id, device)];
+    let surface_ids = hub.release_objects(&devices);
+    if !surface_ids.is_empty() {
+        let (mut surface_guard, _) = global.surfaces.write(&mut Token::root());
+        for (swap_chain_id, surface_id) in surface_ids {
+            let surface = &mut surface_guard[surface_id];
+            if surface.swap_chain == Some(swap_chain_id) {
+                surface.swap_chain = None;
+            }
+        }
+    }
+    let [(_, device)] = devices;
+    device.dispose();
+}
+
+#[no_mangle]
+pub extern "C" fn wgpu_device_destroy(device_id: DeviceId) {
+    gfx_select!(device_id => device_destroy(&*GLOBAL, device_id))
+}
+
+impl<B: GfxBackend> Hub<B> {
+    /// Take the objects created from `devices` out of the hub, and free their
+    /// native resources with the devices.
+    ///
+    /// Returns the released swap chains, along with their surfaces.
+    fn release_objects(&self, devices: &[(DeviceId, Device<B>)]) -> Vec<(SwapChainId, SurfaceId)> {
+        let device_of = |id: DeviceId| {
+            devices
+                .iter()
+                .find(|(device_id, _)| *device_id == id)
+                .map(|(_, device)| device)
+        };
+        let owns = |stored: &Stored<DeviceId>| device_of(stored.value).is_some();
+        let free = |id: DeviceId, resource: NativeResource<B>| {
+            device_of(id).unwrap().pending.lock().free.push(resource)
+        };
+
+        let swap_chains = {
+            let (_, mut token) = self.devices.read(&mut Token::root());
+            self.swap_chains
+                .unregister_matching(|swap_chain| owns(&swap_chain.device_id), &mut token)
+        };
+        let mut surface_ids = Vec::new();
+        for (id, swap_chain) in swap_chains {
+            if let Some(ref surface_id) = swap_chain.surface_id {
+                surface_ids.push((id, surface_id.value));
+            }
+            device_of(swap_chain.device_id.value)
+                .unwrap()
+                .dispose_swap_chain(swap_chain);
+        }
+        // views are released first, looking up the devices of their textures
+        let textures = self
+            .textures
+            .unregister_matching(|texture| owns(&texture.device_id), &mut Token::root());
+        let texture_of = |id: TextureId| {
+            textures
+                .iter()
+                .find(|(texture_id, _)| *texture_id == id)
+                .map(|(_, texture)| texture)
+        };
+        let views = self.texture_views.unregister_matching(
+            |view| texture_of(view.texture_id.value).is_some(),
+            &mut Token::root(),
+        );
+        for (_, view) in views {
+            let texture = texture_of(view.texture_id.value).unwrap();
+            free(texture.device_id.value, NativeResource::ImageView(view.raw));
+        }
+        for (_, texture) in textures {
+            let memory = match texture.placement {
+                resource::TexturePlacement::SwapChain(_, None) => continue,
+                resource::TexturePlacement::SwapChain(_, Some(memory))
+                | resource::TexturePlacement::Memory(memory) => memory,
+            };
+            free(
+                texture.device_id.value,
+                NativeResource::Image(texture.raw, memory),
+            );
+        }
+        let buffers = self
+            .buffers
+            .unregister_matching(|buffer| owns(&buffer.device_id), &mut Token::root());
+        for (_, buffer) in buffers {
+            free(
+                buffer.device_id.value,
+                NativeResource::Buffer(buffer.raw, buffer.memory),
+            );
+        }
+        let bind_groups = self
+            .bind_groups
+            .unregister_matching(|bind_group| owns(&bind_group.device_id), &mut Token::root());
+        for (_, bind_group) in bind_groups {
+            free(
+                bind_group.device_id.value,
+                NativeResource::DescriptorSet(bind_group.raw),
+            );
+        }
+
+        let render_pipelines = self
+            .render_pipelines
+            .unregister_matching(|pipeline| owns(&pipeline.device_id), &mut Token::root());
+        let compute_pipelines = self
+            .compute_pipelines
+            .unregister_matching(|pipeline| owns(&pipeline.device_id), &mut Token::root());
+        let shader_modules = self
+            .shader_modules
+            .unregister_matching(|module| owns(&module.device_id), &mut Token::root());
+        let pipeline_layouts = self
+            .pipeline_layouts
+            .unregister_matching(|layout| owns(&layout.device_id), &mut Token::root());
+        let bind_group_layouts = self
+            .bind_group_layouts
+            .unregister_matching(|layout| owns(&layout.device_id), &mut Token::root());
+        let samplers = self
+            .samplers
+            .unregister_matching(|sampler| owns(&sampler.device_id), &mut Token::root());
+        unsafe {
+            for (_, pipeline) in render_pipelines {
+                let device = device_of(pipeline.device_id.value).unwrap();
+                device.raw.destroy_graphics_pipeline(pipeline.raw);
+            }
+            for (_, pipeline) in compute_pipelines {
+                let device = device_of(pipeline.device_id.value).unwrap();
+                device.raw.destroy_compute_pipeline(pipeline.raw);
+            }
+            for (_, module) in shader_modules {
+                let device = device_of(module.device_id.value).unwrap();
+                device.raw.destroy_shader_module(module.raw);
+            }
+            for (_, layout) in pipeline_layouts {
+                let device = device_of(layout.device_id.value).unwrap();
+                device.raw.destroy_pipeline_layout(layout.raw);
+            }
+            for (_, layout) in bind_group_layouts {
+                let device = device_of(layout.device_id.value).unwrap();
+                device.raw.destroy_descriptor_set_layout(layout.raw);
+            }
+            for (_, sampler) in samplers {
+                let device = device_of(sampler.device_id.value).unwrap();
+                device.raw.destroy_sampler(sampler.raw);
+            }
+        }
+
+        // the command buffers are freed with the pools of their devices
+        let mut command_buffers = self
+            .command_buffers
+            .unregister_matching(|cmb| owns(&cmb.device_id), &mut Token::root());
+        for (_, cmb) in &mut command_buffers {
+            for resource in cmb.temporaries.drain(..) {
+                free(cmb.device_id.value, resource);
+            }
+        }
+        let is_released =
+            |id: CommandBufferId| command_buffers.iter().any(|&(cmb_id, _)| cmb_id == id);
+        self.render_passes
+            .unregister_matching(|pass| is_released(pass.cmb_id.value), &mut Token::root());
+        self.compute_passes
+            .unregister_matching(|pass| is_released(pass.cmb_id.value), &mut Token::root());
+        // all the handles of the queues, not only the cached ones
+        self.queues
+            .unregister_matching(|queue| owns(&queue.device_id), &mut Token::root());
+
+        surface_ids
+    }
+
+    /// Destroy all the objects of the hub, and then the devices,
+    /// when the context is dropped.
+    pub(crate) fn clear(&mut self) {
+        let devices = self.devices.drain();
+        for &(id, ref device) in &devices {
+            if let Some(maintenance) = device.maintenance.lock().take() {
+                maintenance.stop();
+            }
+            if let Err(e) = device.raw.wait_idle() {
+                warn!("Failed to wait for the device {:?} to be idle: {:?}", id, e);
+            }
+        }
+        // the surfaces are drained after the hubs, no need to detach the swap chains
+        self.release_objects(&devices);
+        for (_, device) in devices {
+            device.dispose();
+        }
+        self.adapters.drain();
+    }
+}
+
+pub type BufferMapReadCallback =
+    extern "C" fn(status: BufferMapAsyncStatus, data: *const u8, userdata: *mut u8);
+pub type BufferMapWriteCallback =
+    extern "C" fn(status: BufferMapAsyncStatus, data: *mut u8, userdata: *mut u8);
+
+pub fn buffer_map_async<B: GfxBackend>(
+    global: &Global,
+    buffer_id: BufferId,
+    usage: resource::BufferUsage,
+    operation: BufferMapOperation,
+) {
+    let hub = B::hub(global);
+    let mut token = Token::root();
+    let (device_guard, mut token) = hub.devices.read(&mut token);
+
+    let (device_id, ref_count) = {
+        let (mut buffer_guard, _) = hub.buffers.write(&mut token);
+        let buffer = &mut buffer_guard[buffer_id];
+        let range = operation.range();
+        assert!(
+            range.start < range.end,
+            "Unable to map an empty range {:?}",
+            range
+        );
+        assert!(
+            range.end <= buffer.size,
+            "Mapping range {:?} is out of the buffer bounds (size {})",
+            range,
+            buffer.size
+        );
+
+        if buffer.is_mapping_overlapped(&range) {
+            operation.call_error();
+            return;
+        }
+
+        buffer.pending_map_operations.push(operation);
+        (buffer.device_id.value, buffer.life_guard.ref_count.clone())
+    };
+
+    let device = &device_guard[device_id];
+
+    device
+        .trackers
+        .lock()
+        .buffers
+        .change_replace(buffer_id, &ref_count, (), usage);
+
+    device.pending.lock().map(buffer_id, ref_count);
+    device.wake_maintenance();
+}
+
+#[no_mangle]
+pub extern "C" fn wgpu_buffer_map_read_async(
+    buffer_id: BufferId,
+    start: BufferAddress,
+    size: BufferAddress,
+    callback: BufferMapReadCallback,
+    userdata: *mut u8,
+) {
     let operation = BufferMapOperation::Read(start .. start + size, callback, userdata);
-    gfx_select!(buffer_id => buffer_map_async(buffer_id, resource::BufferUsage::MAP_READ, operation))
+    gfx_select!(buffer_id => buffer_map_async(&*GLOBAL, buffer_id, resource::BufferUsage::MAP_READ, operation))
 }
 
 #[no_mangle]
@@ -2171,37 +4491,500 @@ pub extern "C" fn wgpu_buffer_map_write_async(
     userdata: *mut u8,
 ) {
     let operation = BufferMapOperation::Write(start .. start + size, callback, userdata);
-    gfx_select!(buffer_id => buffer_map_async(buffer_id, resource::BufferUsage::MAP_WRITE, operation))
+    gfx_select!(buffer_id => buffer_map_async(&*GLOBAL, buffer_id, resource::BufferUsage::MAP_WRITE, operation))
 }
 
-pub fn buffer_unmap<B: GfxBackend>(buffer_id: BufferId) {
-    let hub = B::hub();
+fn poll_map_future(
+    shared: &resource::BufferMapFutureShared,
+    context: &mut Context,
+) -> Poll<Result<*mut u8, BufferMapAsyncStatus>> {
+    let mut state = shared.lock();
+    match state.result.take() {
+        Some(result) => Poll::Ready(result),
+        None => {
+            state.waker = Some(context.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+/// A read-only view of a mapped buffer range, unmapping the range on drop.
+#[derive(Debug)]
+pub struct BufferReadMapping<B: GfxBackend> {
+    global: Arc<Global>,
+    buffer_id: BufferId,
+    /// Keeps the buffer alive while it's mapped.
+    _ref_count: RefCount,
+    start: BufferAddress,
+    ptr: *const u8,
+    size: usize,
+    _phantom: PhantomData<B>,
+}
+
+unsafe impl<B: GfxBackend> Send for BufferReadMapping<B> {}
+
+impl<B: GfxBackend> Deref for BufferReadMapping<B> {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr, self.size) }
+    }
+}
+
+impl<B: GfxBackend> Drop for BufferReadMapping<B> {
+    fn drop(&mut self) {
+        buffer_unmap_range::<B>(
+            &self.global,
+            self.buffer_id,
+            self.start,
+            self.size as BufferAddress,
+        );
+    }
+}
+
+/// A writable view of a mapped buffer range, unmapping the range on drop.
+#[derive(Debug)]
+pub struct BufferWriteMapping<B: GfxBackend> {
+    global: Arc<Global>,
+    buffer_id: BufferId,
+    /// Keeps the buffer alive while it's mapped.
+    _ref_count: RefCount,
+    start: BufferAddress,
+    ptr: *mut u8,
+    size: usize,
+    _phantom: PhantomData<B>,
+}
+
+unsafe impl<B: GfxBackend> Send for BufferWriteMapping<B> {}
+
+impl<B: GfxBackend> Deref for BufferWriteMapping<B> {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr, self.size) }
+    }
+}
+
+impl<B: GfxBackend> DerefMut for BufferWriteMapping<B> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.ptr, self.size) }
+    }
+}
+
+impl<B: GfxBackend> Drop for BufferWriteMapping<B> {
+    fn drop(&mut self) {
+        buffer_unmap_range::<B>(
+            &self.global,
+            self.buffer_id,
+            self.start,
+            self.size as BufferAddress,
+        );
+    }
+}
+
+/// A pending `buffer_map_read` request.
+///
+/// Dropping it before it resolves cancels the request,
+/// unmapping the range if the mapping has already been done.
+#[derive(Debug)]
+pub struct BufferMapReadFuture<B: GfxBackend> {
+    global: Arc<Global>,
+    buffer_id: BufferId,
+    ref_count: RefCount,
+    start: BufferAddress,
+    size: usize,
+    shared: resource::BufferMapFutureShared,
+    done: bool,
+    _phantom: PhantomData<B>,
+}
+
+// nothing in the future is pinned
+impl<B: GfxBackend> Unpin for BufferMapReadFuture<B> {}
+
+impl<B: GfxBackend> Future for BufferMapReadFuture<B> {
+    type Output = Result<BufferReadMapping<B>, BufferMapAsyncStatus>;
+    fn poll(mut self: Pin<&mut Self>, context: &mut Context) -> Poll<Self::Output> {
+        let result = poll_map_future(&self.shared, context);
+        self.done = result.is_ready();
+        result.map(|result| {
+            result.map(|ptr| BufferReadMapping {
+                global: Arc::clone(&self.global),
+                buffer_id: self.buffer_id,
+                _ref_count: self.ref_count.clone(),
+                start: self.start,
+                ptr,
+                size: self.size,
+                _phantom: PhantomData,
+            })
+        })
+    }
+}
+
+impl<B: GfxBackend> Drop for BufferMapReadFuture<B> {
+    fn drop(&mut self) {
+        if !self.done {
+            let range = self.start .. self.start + self.size as BufferAddress;
+            cancel_map_operation::<B>(&self.global, self.buffer_id, range, &self.shared);
+        }
+    }
+}
+
+/// A pending `buffer_map_write` request.
+///
+/// Dropping it before it resolves cancels the request,
+/// unmapping the range if the mapping has already been done.
+#[derive(Debug)]
+pub struct BufferMapWriteFuture<B: GfxBackend> {
+    global: Arc<Global>,
+    buffer_id: BufferId,
+    ref_count: RefCount,
+    start: BufferAddress,
+    size: usize,
+    shared: resource::BufferMapFutureShared,
+    done: bool,
+    _phantom: PhantomData<B>,
+}
+
+// nothing in the future is pinned
+impl<B: GfxBackend> Unpin for BufferMapWriteFuture<B> {}
+
+impl<B: GfxBackend> Future for BufferMapWriteFuture<B> {
+    type Output = Result<BufferWriteMapping<B>, BufferMapAsyncStatus>;
+    fn poll(mut self: Pin<&mut Self>, context: &mut Context) -> Poll<Self::Output> {
+        let result = poll_map_future(&self.shared, context);
+        self.done = result.is_ready();
+        result.map(|result| {
+            result.map(|ptr| BufferWriteMapping {
+                global: Arc::clone(&self.global),
+                buffer_id: self.buffer_id,
+                _ref_count: self.ref_count.clone(),
+                start: self.start,
+                ptr,
+                size: self.size,
+                _phantom: PhantomData,
+            })
+        })
+    }
+}
+
+impl<B: GfxBackend> Drop for BufferMapWriteFuture<B> {
+    fn drop(&mut self) {
+        if !self.done {
+            let range = self.start .. self.start + self.size as BufferAddress;
+            cancel_map_operation::<B>(&self.global, self.buffer_id, range, &self.shared);
+        }
+    }
+}
+
+/// Request the buffer range to be mapped for reading.
+///
+/// The returned future resolves once the device is polled (see `device_poll`
+/// and `device_block_on`) after the GPU is done with the buffer.
+pub fn buffer_map_read<B: GfxBackend>(
+    global: &Arc<Global>,
+    buffer_id: BufferId,
+    start: BufferAddress,
+    size: BufferAddress,
+) -> BufferMapReadFuture<B> {
+    let ref_count = {
+        let hub = B::hub(global);
+        let mut token = Token::root();
+        let (_, mut token) = hub.devices.read(&mut token); //skip token
+        let (buffer_guard, _) = hub.buffers.read(&mut token);
+        buffer_guard[buffer_id].life_guard.ref_count.clone()
+    };
+    let shared = resource::BufferMapFutureShared::default();
+    let operation = BufferMapOperation::ReadFuture(start .. start + size, shared.clone());
+    buffer_map_async::<B>(
+        global,
+        buffer_id,
+        resource::BufferUsage::MAP_READ,
+        operation,
+    );
+    BufferMapReadFuture {
+        global: Arc::clone(global),
+        buffer_id,
+        ref_count,
+        start,
+        size: size as usize,
+        shared,
+        done: false,
+        _phantom: PhantomData,
+    }
+}
+
+/// Request the buffer range to be mapped for writing.
+///
+/// The written data is flushed when the resulting mapping is dropped.
+pub fn buffer_map_write<B: GfxBackend>(
+    global: &Arc<Global>,
+    buffer_id: BufferId,
+    start: BufferAddress,
+    size: BufferAddress,
+) -> BufferMapWriteFuture<B> {
+    let ref_count = {
+        let hub = B::hub(global);
+        let mut token = Token::root();
+        let (_, mut token) = hub.devices.read(&mut token); //skip token
+        let (buffer_guard, _) = hub.buffers.read(&mut token);
+        buffer_guard[buffer_id].life_guard.ref_count.clone()
+    };
+    let shared = resource::BufferMapFutureShared::default();
+    let operation = BufferMapOperation::WriteFuture(start .. start + size, shared.clone());
+    buffer_map_async::<B>(
+        global,
+        buffer_id,
+        resource::BufferUsage::MAP_WRITE,
+        operation,
+    );
+    BufferMapWriteFuture {
+        global: Arc::clone(global),
+        buffer_id,
+        ref_count,
+        start,
+        size: size as usize,
+        shared,
+        done: false,
+        _phantom: PhantomData,
+    }
+}
+
+fn flush_mapped_ranges<'a, B: hal::Backend>(
+    raw: &B::Device,
+    memory: &MemoryBlock<B>,
+    ranges: impl Iterator<Item = &'a resource::BufferMappedRange>,
+) {
+    let flush_ranges = ranges
+        .filter_map(|mapped| mapped.flush_range.clone())
+        .map(|range| (memory.memory(), range))
+        .collect::<Vec<_>>();
+    if !flush_ranges.is_empty() {
+        unsafe { raw.flush_mapped_memory_ranges(flush_ranges).unwrap() };
+    }
+}
+
+pub fn buffer_unmap<B: GfxBackend>(global: &Global, buffer_id: BufferId) {
+    let hub = B::hub(global);
     let mut token = Token::root();
 
     let (device_guard, mut token) = hub.devices.read(&mut token);
     let (mut buffer_guard, _) = hub.buffers.write(&mut token);
 
     let buffer = &mut buffer_guard[buffer_id];
-    let device_raw = &device_guard[buffer.device_id.value].raw;
-
-    if !buffer.mapped_write_ranges.is_empty() {
-        unsafe {
-            device_raw
-                .flush_mapped_memory_ranges(
-                    buffer
-                        .mapped_write_ranges
-                        .iter()
-                        .map(|r| (buffer.memory.memory(), r.clone())),
-                )
-                .unwrap()
-        };
-        buffer.mapped_write_ranges.clear();
-    }
+    let device_raw = &*device_guard[buffer.device_id.value].raw;
 
+    flush_mapped_ranges(device_raw, &buffer.memory, buffer.mapped_ranges.iter());
+    buffer.mapped_ranges.clear();
     buffer.memory.unmap(device_raw);
 }
 
 #[no_mangle]
 pub extern "C" fn wgpu_buffer_unmap(buffer_id: BufferId) {
-    gfx_select!(buffer_id => buffer_unmap(buffer_id))
+    gfx_select!(buffer_id => buffer_unmap(&*GLOBAL, buffer_id))
+}
+
+/// Unmap a single range previously mapped with the given start and size,
+/// leaving the other mapped ranges of the buffer intact.
+pub fn buffer_unmap_range<B: GfxBackend>(
+    global: &Global,
+    buffer_id: BufferId,
+    start: BufferAddress,
+    size: BufferAddress,
+) {
+    let hub = B::hub(global);
+    let mut token = Token::root();
+
+    let (device_guard, mut token) = hub.devices.read(&mut token);
+    let (mut buffer_guard, _) = hub.buffers.write(&mut token);
+
+    let buffer = &mut buffer_guard[buffer_id];
+    let device_raw = &*device_guard[buffer.device_id.value].raw;
+
+    let position = buffer
+        .mapped_ranges
+        .iter()
+        .position(|mapped| mapped.range == (start .. start + size))
+        .unwrap_or_else(|| panic!("Range {:?} is not mapped", start .. start + size));
+    unmap_range(device_raw, buffer, position);
+}
+
+fn unmap_range<B: hal::Backend>(
+    raw: &B::Device,
+    buffer: &mut resource::Buffer<B>,
+    position: usize,
+) {
+    let mapped = buffer.mapped_ranges.swap_remove(position);
+    flush_mapped_ranges(raw, &buffer.memory, iter::once(&mapped));
+    if buffer.mapped_ranges.is_empty() {
+        buffer.memory.unmap(raw);
+    }
+}
+
+/// Cancel the mapping operation awaited through `shared`: it's removed from
+/// the buffer if still pending, and otherwise its range is unmapped.
+fn cancel_map_operation<B: GfxBackend>(
+    global: &Global,
+    buffer_id: BufferId,
+    range: Range<BufferAddress>,
+    shared: &resource::BufferMapFutureShared,
+) {
+    let hub = B::hub(global);
+    let mut token = Token::root();
+    let (device_guard, mut token) = hub.devices.read(&mut token);
+    let (mut buffer_guard, _) = hub.buffers.write(&mut token);
+    let buffer = &mut buffer_guard[buffer_id];
+
+    if let Some(index) = buffer
+        .pending_map_operations
+        .iter()
+        .position(|operation| operation.is_awaited_by(shared))
+    {
+        buffer.pending_map_operations.swap_remove(index);
+        return;
+    }
+    // the callbacks are fired after the buffers are unlocked,
+    // so a mapping can be done while the result is not set yet
+    if let Some(Err(_)) = shared.lock().result {
+        return;
+    }
+    if let Some(position) = buffer
+        .mapped_ranges
+        .iter()
+        .position(|mapped| mapped.range == range)
+    {
+        let device_raw = &*device_guard[buffer.device_id.value].raw;
+        unmap_range(device_raw, buffer, position);
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wgpu_buffer_unmap_range(
+    buffer_id: BufferId,
+    start: BufferAddress,
+    size: BufferAddress,
+) {
+    gfx_select!(buffer_id => buffer_unmap_range(&*GLOBAL, buffer_id, start, size))
+}
+
+/// Get the host pointer to a range of the buffer, which has to be contained
+/// within one of the currently mapped ranges.
+///
+/// Returns null if the range is not mapped.
+pub fn buffer_get_mapped_range<B: GfxBackend>(
+    global: &Global,
+    buffer_id: BufferId,
+    start: BufferAddress,
+    size: BufferAddress,
+) -> *mut u8 {
+    let hub = B::hub(global);
+    let mut token = Token::root();
+    let (_, mut token) = hub.devices.read(&mut token); //skip token
+    let (buffer_guard, _) = hub.buffers.read(&mut token);
+
+    let end = match start.checked_add(size) {
+        Some(end) => end,
+        None => return ptr::null_mut(),
+    };
+    buffer_guard[buffer_id]
+        .mapped_ranges
+        .iter()
+        .find(|mapped| mapped.range.start <= start && end <= mapped.range.end)
+        .map_or(ptr::null_mut(), |mapped| unsafe {
+            mapped.ptr.offset((start - mapped.range.start) as isize)
+        })
+}
+
+#[no_mangle]
+pub extern "C" fn wgpu_buffer_get_mapped_range(
+    buffer_id: BufferId,
+    start: BufferAddress,
+    size: BufferAddress,
+) -> *mut u8 {
+    gfx_select!(buffer_id => buffer_get_mapped_range(&*GLOBAL, buffer_id, start, size))
+}
+
+/// Map the buffer range and block until it's ready, up to `timeout_ns` nanoseconds.
+///
+/// If the timeout expires while the mapping is still pending, it's cancelled
+/// and `BufferMapAsyncStatus::Timeout` is returned.
+pub fn buffer_map_sync<B: GfxBackend>(
+    global: &Global,
+    buffer_id: BufferId,
+    usage: resource::BufferUsage,
+    start: BufferAddress,
+    size: BufferAddress,
+    timeout_ns: u64,
+) -> Result<*mut u8, BufferMapAsyncStatus> {
+    let shared = resource::BufferMapFutureShared::default();
+    let operation = if usage == resource::BufferUsage::MAP_READ {
+        BufferMapOperation::ReadFuture(start .. start + size, shared.clone())
+    } else if usage == resource::BufferUsage::MAP_WRITE {
+        BufferMapOperation::WriteFuture(start .. start + size, shared.clone())
+    } else {
+        panic!("Buffers can only be mapped with MAP_READ or MAP_WRITE, not {:?}", usage)
+    };
+    buffer_map_async::<B>(global, buffer_id, usage, operation);
+
+    let (device_id, submission_index) = {
+        let hub = B::hub(global);
+        let mut token = Token::root();
+        let (_, mut token) = hub.devices.read(&mut token); //skip token
+        let (buffer_guard, _) = hub.buffers.read(&mut token);
+        let buffer = &buffer_guard[buffer_id];
+        (
+            buffer.device_id.value,
+            buffer.life_guard.submission_index.load(Ordering::Acquire),
+        )
+    };
+    device_wait_for_submission::<B>(global, device_id, submission_index, timeout_ns);
+
+    let hub = B::hub(global);
+    let mut token = Token::root();
+    let (_, mut token) = hub.devices.read(&mut token); //skip token
+    let (mut buffer_guard, _) = hub.buffers.write(&mut token);
+    let buffer = &mut buffer_guard[buffer_id];
+    // the operation may have been processed since the wait timed out,
+    // so it's only cancelled if it's still pending
+    if let Some(index) = buffer
+        .pending_map_operations
+        .iter()
+        .position(|operation| operation.is_awaited_by(&shared))
+    {
+        buffer.pending_map_operations.swap_remove(index);
+        return Err(BufferMapAsyncStatus::Timeout);
+    }
+    let result = shared.lock().result.take();
+    match result {
+        Some(result) => result,
+        // the callbacks are fired after the buffers are unlocked,
+        // so the outcome is found in the mapped ranges
+        None => buffer
+            .mapped_ranges
+            .iter()
+            .find(|mapped| mapped.range == (start .. start + size))
+            .map(|mapped| mapped.ptr)
+            .ok_or(BufferMapAsyncStatus::Error),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wgpu_buffer_map_read_sync(
+    buffer_id: BufferId,
+    start: BufferAddress,
+    size: BufferAddress,
+    timeout_ns: u64,
+) -> *const u8 {
+    let usage = resource::BufferUsage::MAP_READ;
+    gfx_select!(buffer_id => buffer_map_sync(&*GLOBAL, buffer_id, usage, start, size, timeout_ns))
+        .unwrap_or(ptr::null_mut())
+}
+
+#[no_mangle]
+pub extern "C" fn wgpu_buffer_map_write_sync(
+    buffer_id: BufferId,
+    start: BufferAddress,
+    size: BufferAddress,
+    timeout_ns: u64,
+) -> *mut u8 {
+    let usage = resource::BufferUsage::MAP_WRITE;
+    gfx_select!(buffer_id => buffer_map_sync(&*GLOBAL, buffer_id, usage, start, size, timeout_ns))
+        .unwrap_or(ptr::null_mut())
 }
@@ -1,6 +1,7 @@
 use crate::{
     backend,
-    id::{Input, Output},
+    id::Input,
+    instance::{InstanceDescriptor, InstanceOptions},
     Adapter,
     AdapterId,
     Backend,
@@ -23,6 +24,8 @@ use crate::{
     Instance,
     PipelineLayout,
     PipelineLayoutId,
+    Queue,
+    QueueId,
     RenderPass,
     RenderPassId,
     RenderPipeline,
@@ -42,15 +45,13 @@ use crate::{
     TypedId,
 };
 
+use hal::backend::FastHashMap;
 use lazy_static::lazy_static;
-#[cfg(not(feature = "remote"))]
-use parking_lot::Mutex;
-use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
-use vec_map::VecMap;
+use parking_lot::{Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
 #[allow(unused)]
 use std::cell::Cell;
-use std::{marker::PhantomData, ops, sync::Arc};
+use std::{fmt::Debug, marker::PhantomData, ops, sync::Arc};
 
 
 /// A simple structure to manage identities of objects.
@@ -100,10 +101,94 @@ impl<I: TypedId> IdentityManager<I> {
     }
 }
 
+/// Produces the IDs of the objects registered in a `Registry`.
+pub trait IdentityHandler<I>: Debug + Send + Sync {
+    /// Get the ID of a new object, given the one provided by the caller, if any.
+    fn process(&self, id_in: Input<I>) -> I;
+    /// Release the ID of an unregistered object.
+    fn free(&self, id: I);
+}
+
+impl<I: TypedId + Debug + Send> IdentityHandler<I> for Mutex<IdentityManager<I>> {
+    fn process(&self, id_in: Input<I>) -> I {
+        assert!(
+            id_in.is_none(),
+            "IDs are allocated by the context, but {:?} was provided",
+            id_in
+        );
+        self.lock().alloc()
+    }
+
+    fn free(&self, id: I) {
+        self.lock().free(id);
+    }
+}
+
+/// Takes the IDs provided by the caller, e.g. allocated by a remote client.
+///
+/// The objects the context creates on its own, like the extra images of a
+/// swap chain, get IDs from the upper half of the index space instead,
+/// which the callers never reach.
+#[derive(Debug)]
+pub struct IdentityPassthrough<I: TypedId> {
+    internal: Mutex<IdentityManager<I>>,
+}
+
+const INTERNAL_INDEX_BIT: Index = 1 << 31;
+
+impl<I: TypedId + Copy + Debug + Send> IdentityHandler<I> for IdentityPassthrough<I> {
+    fn process(&self, id_in: Input<I>) -> I {
+        match id_in {
+            Some(id) => {
+                let (index, _, backend) = id.unzip();
+                debug_assert_eq!(backend, self.internal.lock().backend);
+                assert_eq!(index & INTERNAL_INDEX_BIT, 0, "ID {:?} is out of range", id);
+                id
+            }
+            None => {
+                let (index, epoch, backend) = self.internal.lock().alloc().unzip();
+                I::zip(index | INTERNAL_INDEX_BIT, epoch, backend)
+            }
+        }
+    }
+
+    fn free(&self, id: I) {
+        let (index, epoch, backend) = id.unzip();
+        if index & INTERNAL_INDEX_BIT != 0 {
+            let internal = I::zip(index & !INTERNAL_INDEX_BIT, epoch, backend);
+            self.internal.lock().free(internal);
+        }
+    }
+}
+
+/// How a context gets the IDs of its objects, chosen when it's created.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IdentityMode {
+    /// The context allocates the IDs, callers don't provide any.
+    Local,
+    /// The callers provide the IDs, e.g. a server driven by a remote client,
+    /// except for the objects created internally.
+    Remote,
+}
+
+impl IdentityMode {
+    fn spawn<I: TypedId + Copy + Debug + Send + 'static>(
+        self,
+        backend: Backend,
+    ) -> Box<dyn IdentityHandler<I>> {
+        match self {
+            IdentityMode::Local => Box::new(Mutex::new(IdentityManager::new(backend))),
+            IdentityMode::Remote => Box::new(IdentityPassthrough {
+                internal: Mutex::new(IdentityManager::new(backend)),
+            }),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Storage<T, I: TypedId> {
     //TODO: consider concurrent hashmap?
-    map: VecMap<(T, Epoch)>,
+    map: FastHashMap<Index, (T, Epoch)>,
     _phantom: PhantomData<I>,
 }
 
@@ -111,7 +196,7 @@ impl<T, I: TypedId> ops::Index<I> for Storage<T, I> {
     type Output = T;
     fn index(&self, id: I) -> &T {
         let (index, epoch, _) = id.unzip();
-        let (ref value, storage_epoch) = self.map[index as usize];
+        let (ref value, storage_epoch) = self.map[&index];
         assert_eq!(epoch, storage_epoch);
         value
     }
@@ -120,7 +205,7 @@ impl<T, I: TypedId> ops::Index<I> for Storage<T, I> {
 impl<T, I: TypedId> ops::IndexMut<I> for Storage<T, I> {
     fn index_mut(&mut self, id: I) -> &mut T {
         let (index, epoch, _) = id.unzip();
-        let (ref mut value, storage_epoch) = self.map[index as usize];
+        let &mut (ref mut value, storage_epoch) = self.map.get_mut(&index).unwrap();
         assert_eq!(epoch, storage_epoch);
         value
     }
@@ -129,7 +214,7 @@ impl<T, I: TypedId> ops::IndexMut<I> for Storage<T, I> {
 impl<T, I: TypedId> Storage<T, I> {
     pub fn contains(&self, id: I) -> bool {
         let (index, epoch, _) = id.unzip();
-        match self.map.get(index as usize) {
+        match self.map.get(&index) {
             Some(&(_, storage_epoch)) => epoch == storage_epoch,
             None => false,
         }
@@ -137,7 +222,7 @@ impl<T, I: TypedId> Storage<T, I> {
 
     pub fn remove(&mut self, id: I) -> T {
         let (index, epoch, _) = id.unzip();
-        let (value, storage_epoch) = self.map.remove(index as usize).unwrap();
+        let (value, storage_epoch) = self.map.remove(&index).unwrap();
         assert_eq!(epoch, storage_epoch);
         value
     }
@@ -165,6 +250,8 @@ impl<B: hal::Backend> Access<Adapter<B>> for Surface {}
 impl<B: hal::Backend> Access<Device<B>> for Root {}
 impl<B: hal::Backend> Access<Device<B>> for Surface {}
 impl<B: hal::Backend> Access<Device<B>> for Adapter<B> {}
+impl Access<Queue> for Root {}
+impl<B: hal::Backend> Access<Queue> for Device<B> {}
 impl<B: hal::Backend> Access<SwapChain<B>> for Device<B> {}
 impl<B: hal::Backend> Access<PipelineLayout<B>> for Root {}
 impl<B: hal::Backend> Access<PipelineLayout<B>> for Device<B> {}
@@ -259,19 +346,17 @@ impl<'a, T> Drop for Token<'a, T> {
 
 #[derive(Debug)]
 pub struct Registry<T, I: TypedId> {
-    #[cfg(not(feature = "remote"))]
-    pub identity: Mutex<IdentityManager<I>>,
+    pub identity: Box<dyn IdentityHandler<I>>,
     data: RwLock<Storage<T, I>>,
     backend: Backend,
 }
 
-impl<T, I: TypedId> Registry<T, I> {
-    fn new(backend: Backend) -> Self {
+impl<T, I: TypedId + Copy + Debug + Send + 'static> Registry<T, I> {
+    fn new(backend: Backend, mode: IdentityMode) -> Self {
         Registry {
-            #[cfg(not(feature = "remote"))]
-            identity: Mutex::new(IdentityManager::new(backend)),
+            identity: mode.spawn(backend),
             data: RwLock::new(Storage {
-                map: VecMap::new(),
+                map: FastHashMap::default(),
                 _phantom: PhantomData,
             }),
             backend,
@@ -283,20 +368,12 @@ impl<T, I: TypedId + Copy> Registry<T, I> {
     pub fn register<A: Access<T>>(&self, id: I, value: T, _token: &mut Token<A>) {
         let (index, epoch, backend) = id.unzip();
         debug_assert_eq!(backend, self.backend);
-        let old = self.data.write().map.insert(index as usize, (value, epoch));
+        let old = self.data.write().map.insert(index, (value, epoch));
         assert!(old.is_none());
     }
 
-    #[cfg(not(feature = "remote"))]
-    pub fn new_identity(&self, _id_in: Input<I>) -> (I, Output<I>) {
-        let id = self.identity.lock().alloc();
-        (id, id)
-    }
-
-    #[cfg(feature = "remote")]
-    pub fn new_identity(&self, id_in: Input<I>) -> (I, Output<I>) {
-        //TODO: debug_assert_eq!(self.backend, id_in.backend());
-        (id_in, PhantomData)
+    pub fn new_identity(&self, id_in: Input<I>) -> I {
+        self.identity.process(id_in)
     }
 
     pub fn register_identity<A: Access<T>>(
@@ -304,17 +381,51 @@ impl<T, I: TypedId + Copy> Registry<T, I> {
         id_in: Input<I>,
         value: T,
         token: &mut Token<A>,
-    ) -> Output<I> {
-        let (id, output) = self.new_identity(id_in);
+    ) -> I {
+        let id = self.new_identity(id_in);
         self.register(id, value, token);
-        output
+        id
+    }
+
+    /// Take all the objects out, to destroy them along with the context.
+    pub(crate) fn drain(&mut self) -> Vec<(I, T)> {
+        let backend = self.backend;
+        self.data
+            .get_mut()
+            .map
+            .drain()
+            .map(|(index, (value, epoch))| (I::zip(index, epoch, backend), value))
+            .collect()
+    }
+
+    /// Take the objects matching `filter` out, freeing their ids.
+    pub(crate) fn unregister_matching<A: Access<T>>(
+        &self,
+        filter: impl Fn(&T) -> bool,
+        _token: &mut Token<A>,
+    ) -> Vec<(I, T)> {
+        let mut data = self.data.write();
+        let indices = data
+            .map
+            .iter()
+            .filter(|&(_, &(ref value, _))| filter(value))
+            .map(|(&index, _)| index)
+            .collect::<Vec<_>>();
+        indices
+            .into_iter()
+            .map(|index| {
+                let (value, epoch) = data.map.remove(&index).unwrap();
+                let id = I::zip(index, epoch, self.backend);
+                self.identity.free(id);
+                (id, value)
+            })
+            .collect()
     }
 
     pub fn unregister<A: Access<T>>(&self, id: I, _token: &mut Token<A>) -> (T, Token<T>) {
         let value = self.data.write().remove(id);
         //Note: careful about the order here!
-        #[cfg(not(feature = "remote"))]
-        self.identity.lock().free(id);
+        self.identity.free(id);
         (value, Token::new())
     }
 
@@ -337,6 +448,7 @@ impl<T, I: TypedId + Copy> Registry<T, I> {
 pub struct Hub<B: hal::Backend> {
     pub adapters: Registry<Adapter<B>, AdapterId>,
     pub devices: Registry<Device<B>, DeviceId>,
+    pub queues: Registry<Queue, QueueId>,
     pub swap_chains: Registry<SwapChain<B>, SwapChainId>,
     pub pipeline_layouts: Registry<PipelineLayout<B>, PipelineLayoutId>,
     pub shader_modules: Registry<ShaderModule<B>, ShaderModuleId>,
@@ -353,30 +465,31 @@ pub struct Hub<B: hal::Backend> {
     pub samplers: Registry<Sampler<B>, SamplerId>,
 }
 
-impl<B: GfxBackend> Default for Hub<B> {
-    fn default() -> Self {
+impl<B: GfxBackend> Hub<B> {
+    fn new(mode: IdentityMode) -> Self {
         Hub {
-            adapters: Registry::new(B::VARIANT),
-            devices: Registry::new(B::VARIANT),
-            swap_chains: Registry::new(B::VARIANT),
-            pipeline_layouts: Registry::new(B::VARIANT),
-            shader_modules: Registry::new(B::VARIANT),
-            bind_group_layouts: Registry::new(B::VARIANT),
-            bind_groups: Registry::new(B::VARIANT),
-            command_buffers: Registry::new(B::VARIANT),
-            render_passes: Registry::new(B::VARIANT),
-            render_pipelines: Registry::new(B::VARIANT),
-            compute_passes: Registry::new(B::VARIANT),
-            compute_pipelines: Registry::new(B::VARIANT),
-            buffers: Registry::new(B::VARIANT),
-            textures: Registry::new(B::VARIANT),
-            texture_views: Registry::new(B::VARIANT),
-            samplers: Registry::new(B::VARIANT),
+            adapters: Registry::new(B::VARIANT, mode),
+            devices: Registry::new(B::VARIANT, mode),
+            queues: Registry::new(B::VARIANT, mode),
+            swap_chains: Registry::new(B::VARIANT, mode),
+            pipeline_layouts: Registry::new(B::VARIANT, mode),
+            shader_modules: Registry::new(B::VARIANT, mode),
+            bind_group_layouts: Registry::new(B::VARIANT, mode),
+            bind_groups: Registry::new(B::VARIANT, mode),
+            command_buffers: Registry::new(B::VARIANT, mode),
+            render_passes: Registry::new(B::VARIANT, mode),
+            render_pipelines: Registry::new(B::VARIANT, mode),
+            compute_passes: Registry::new(B::VARIANT, mode),
+            compute_pipelines: Registry::new(B::VARIANT, mode),
+            buffers: Registry::new(B::VARIANT, mode),
+            textures: Registry::new(B::VARIANT, mode),
+            texture_views: Registry::new(B::VARIANT, mode),
+            samplers: Registry::new(B::VARIANT, mode),
         }
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct Hubs {
     vulkan: Hub<backend::Vulkan>,
     #[cfg(any(target_os = "ios", target_os = "macos"))]
@@ -387,31 +500,82 @@ pub struct Hubs {
     dx11: Hub<backend::Dx11>,
 }
 
+impl Hubs {
+    fn new(mode: IdentityMode) -> Self {
+        Hubs {
+            vulkan: Hub::new(mode),
+            #[cfg(any(target_os = "ios", target_os = "macos"))]
+            metal: Hub::new(mode),
+            #[cfg(windows)]
+            dx12: Hub::new(mode),
+            #[cfg(windows)]
+            dx11: Hub::new(mode),
+        }
+    }
+}
+
+/// A context owning an instance, with the surfaces and the objects
+/// of every backend created from it.
+///
+/// The contexts are independent: each one has its own ID spaces,
+/// managed according to its `IdentityMode`.
+/// The C API works with the default context, `GLOBAL`, which allocates the IDs.
 #[derive(Debug)]
 pub struct Global {
-    pub instance: Instance,
-    pub surfaces: Registry<Surface, SurfaceId>,
     hubs: Hubs,
+    pub surfaces: Registry<Surface, SurfaceId>,
+    pub instance: Instance,
+}
+
+impl Global {
+    pub fn new(desc: &InstanceDescriptor, identity_mode: IdentityMode) -> Self {
+        Self::with_options(&desc.to_options(), identity_mode)
+    }
+
+    fn with_options(options: &InstanceOptions, identity_mode: IdentityMode) -> Self {
+        Global {
+            hubs: Hubs::new(identity_mode),
+            surfaces: Registry::new(Backend::Empty, identity_mode),
+            instance: Instance::new(options),
+        }
+    }
+}
+
+impl Drop for Global {
+    fn drop(&mut self) {
+        // the objects of the hubs are created from the surfaces and the instance
+        self.hubs.vulkan.clear();
+        #[cfg(any(target_os = "ios", target_os = "macos"))]
+        self.hubs.metal.clear();
+        #[cfg(windows)]
+        {
+            self.hubs.dx12.clear();
+            self.hubs.dx11.clear();
+        }
+        self.surfaces.drain();
+    }
 }
 
 lazy_static! {
-    pub static ref GLOBAL: Arc<Global> = Arc::new(Global {
-        instance: Instance::new("wgpu", 1),
-        surfaces: Registry::new(Backend::Empty),
-        hubs: Hubs::default(),
-    });
+    /// Options of the `GLOBAL` instance, taken when it's initialized.
+    pub(crate) static ref INSTANCE_OPTIONS: Mutex<Option<InstanceOptions>> = Mutex::new(None);
+    /// The default context, used by the C API.
+    pub static ref GLOBAL: Arc<Global> = Arc::new(Global::with_options(
+        &INSTANCE_OPTIONS.lock().take().unwrap_or_default(),
+        IdentityMode::Local,
+    ));
 }
 
 pub trait GfxBackend: hal::Backend {
     const VARIANT: Backend;
-    fn hub() -> &'static Hub<Self>;
+    fn hub(global: &Global) -> &Hub<Self>;
     fn get_surface_mut(surface: &mut Surface) -> &mut Self::Surface;
 }
 
 impl GfxBackend for backend::Vulkan {
     const VARIANT: Backend = Backend::Vulkan;
-    fn hub() -> &'static Hub<Self> {
-        &GLOBAL.hubs.vulkan
+    fn hub(global: &Global) -> &Hub<Self> {
+        &global.hubs.vulkan
     }
     fn get_surface_mut(surface: &mut Surface) -> &mut Self::Surface {
         surface.vulkan.as_mut().unwrap()
@@ -421,8 +585,8 @@ impl GfxBackend for backend::Vulkan {
 #[cfg(any(target_os = "ios", target_os = "macos"))]
 impl GfxBackend for backend::Metal {
     const VARIANT: Backend = Backend::Metal;
-    fn hub() -> &'static Hub<Self> {
-        &GLOBAL.hubs.metal
+    fn hub(global: &Global) -> &Hub<Self> {
+        &global.hubs.metal
     }
     fn get_surface_mut(surface: &mut Surface) -> &mut Self::Surface {
         &mut surface.metal
@@ -432,8 +596,8 @@ impl GfxBackend for backend::Metal {
 #[cfg(windows)]
 impl GfxBackend for backend::Dx12 {
     const VARIANT: Backend = Backend::Dx12;
-    fn hub() -> &'static Hub<Self> {
-        &GLOBAL.hubs.dx12
+    fn hub(global: &Global) -> &Hub<Self> {
+        &global.hubs.dx12
     }
     fn get_surface_mut(surface: &mut Surface) -> &mut Self::Surface {
         surface.dx12.as_mut().unwrap()
@@ -443,10 +607,50 @@ impl GfxBackend for backend::Dx12 {
 #[cfg(windows)]
 impl GfxBackend for backend::Dx11 {
     const VARIANT: Backend = Backend::Dx11;
-    fn hub() -> &'static Hub<Self> {
-        &GLOBAL.hubs.dx11
+    fn hub(global: &Global) -> &Hub<Self> {
+        &global.hubs.dx11
     }
     fn get_surface_mut(surface: &mut Surface) -> &mut Self::Surface {
         &mut surface.dx11
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{Global, IdentityMode};
+    use crate::{Backend, BackendBit, InstanceDescriptor, SurfaceId, TypedId};
+
+    #[test]
+    fn independent_contexts() {
+        // no backends, so that it runs without a GPU
+        let desc = InstanceDescriptor {
+            backends: BackendBit::empty(),
+            ..InstanceDescriptor::default()
+        };
+        let first = Global::new(&desc, IdentityMode::Local);
+        let second = Global::new(&desc, IdentityMode::Local);
+        assert_eq!(
+            first.surfaces.identity.process(None),
+            second.surfaces.identity.process(None)
+        );
+        drop(first);
+        drop(second);
+    }
+
+    #[test]
+    fn remote_internal_ids() {
+        let desc = InstanceDescriptor {
+            backends: BackendBit::empty(),
+            ..InstanceDescriptor::default()
+        };
+        let global = Global::new(&desc, IdentityMode::Remote);
+        let provided = SurfaceId::zip(0, 1, Backend::Empty);
+        let internal = global.surfaces.identity.process(None);
+        assert_ne!(internal.unzip().0, provided.unzip().0);
+        assert_eq!(global.surfaces.identity.process(Some(provided)), provided);
+        global.surfaces.identity.free(internal);
+        let reused = global.surfaces.identity.process(None);
+        assert_eq!(reused.unzip().0, internal.unzip().0);
+        assert_ne!(reused, internal);
+    }
+}
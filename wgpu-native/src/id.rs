@@ -72,19 +72,15 @@ impl<T> TypedId for Id<T> {
     }
 }
 
-#[cfg(feature = "remote")]
-pub type Input<T> = T;
-#[cfg(not(feature = "remote"))]
-pub type Input<T> = PhantomData<T>;
-#[cfg(not(feature = "remote"))]
-pub type Output<T> = T;
-#[cfg(feature = "remote")]
-pub type Output<T> = PhantomData<T>;
+/// The ID of a new object, if provided by the caller.
+///
+/// Whether it's required or not depends on the `IdentityMode` of the context.
+pub type Input<T> = Option<T>;
 
 
 pub type AdapterId = Id<crate::Adapter<Dummy>>;
 pub type DeviceId = Id<crate::Device<Dummy>>;
-pub type QueueId = DeviceId;
+pub type QueueId = Id<crate::Queue>;
 // Resource
 pub type BufferId = Id<crate::Buffer<Dummy>>;
 pub type TextureViewId = Id<crate::TextureView<Dummy>>;
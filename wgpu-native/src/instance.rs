@@ -1,32 +1,224 @@
 use crate::{
     backend,
     binding_model::MAX_BIND_GROUPS,
-    device::BIND_BUFFER_ALIGNMENT,
-    hub::{GfxBackend, Token, GLOBAL},
-    id::{Input, Output},
+    command::CommandAllocator,
+    conv,
+    device::{DedicatedQueue, MaintenanceThread, QueueType, BIND_BUFFER_ALIGNMENT},
+    gfx_select,
+    hub::{GfxBackend, Global, Token, GLOBAL, INSTANCE_OPTIONS},
+    id::Input,
     AdapterId,
     Backend,
     Device,
     DeviceId,
     RefCount,
     SwapChainId,
+    TextureFormat,
+    TextureUsage,
 };
-#[cfg(not(feature = "remote"))]
-use crate::{gfx_select, LifeGuard, SurfaceId};
+use crate::{LifeGuard, SurfaceId};
 
-#[cfg(not(feature = "remote"))]
 use bitflags::bitflags;
 use log::info;
 #[cfg(feature = "remote")]
 use serde::{Deserialize, Serialize};
 
-use hal::{self, Instance as _, PhysicalDevice as _};
-#[cfg(not(feature = "remote"))]
-use std::marker::PhantomData;
+use hal::{
+    self,
+    backend::FastHashMap,
+    queue::QueueFamily as _,
+    Capability as _,
+    Instance as _,
+    PhysicalDevice as _,
+};
+use std::{
+    ffi::CStr,
+    os::raw::c_char,
+    ptr,
+    sync::Arc,
+};
+
 
+/// Severity of a message reported by the backend validation.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ValidationMessageLevel {
+    Error = 0,
+    Warning = 1,
+    Info = 2,
+    Verbose = 3,
+}
+
+pub type ValidationMessageCallback =
+    extern "C" fn(level: ValidationMessageLevel, message: *const c_char, userdata: *mut u8);
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct InstanceDescriptor {
+    /// Application name, as a null-terminated string. Null means "wgpu".
+    pub app_name: *const c_char,
+    pub app_version: u32,
+    /// Backends to initialize, adapters can only be requested on them.
+    pub backends: BackendBit,
+    /// Called with the validation messages, in addition to logging them.
+    ///
+    /// The validation can't be toggled here: the Vulkan backend creates its
+    /// instance with the validation layers in its debug builds only, so the
+    /// callback is never called with a release build of the backend.
+    pub validation_callback: Option<ValidationMessageCallback>,
+    pub userdata: *mut u8,
+}
+
+/// The owned form of `InstanceDescriptor`, waiting for the instance creation.
+#[derive(Debug)]
+pub(crate) struct InstanceOptions {
+    pub name: String,
+    pub version: u32,
+    pub backends: BackendBit,
+    pub callback: Option<(ValidationMessageCallback, *mut u8)>,
+}
+
+unsafe impl Send for InstanceOptions {}
+
+impl Default for InstanceOptions {
+    fn default() -> Self {
+        InstanceOptions {
+            name: "wgpu".to_string(),
+            version: 1,
+            backends: BackendBit::all(),
+            callback: None,
+        }
+    }
+}
+
+impl Default for InstanceDescriptor {
+    fn default() -> Self {
+        InstanceDescriptor {
+            app_name: ptr::null(),
+            app_version: 1,
+            backends: BackendBit::all(),
+            validation_callback: None,
+            userdata: ptr::null_mut(),
+        }
+    }
+}
+
+impl InstanceDescriptor {
+    pub(crate) fn to_options(&self) -> InstanceOptions {
+        let name = if self.app_name.is_null() {
+            "wgpu".to_string()
+        } else {
+            unsafe { CStr::from_ptr(self.app_name) }
+                .to_string_lossy()
+                .into_owned()
+        };
+        InstanceOptions {
+            name,
+            version: self.app_version,
+            backends: self.backends,
+            callback: self.validation_callback.map(|callback| (callback, self.userdata)),
+        }
+    }
+}
+
+/// Forwarding of the Vulkan debug messages to the user callback.
+#[cfg(all(any(unix, windows), not(target_os = "ios"), not(target_os = "macos")))]
+mod vulkan_debug {
+    use super::{ValidationMessageCallback, ValidationMessageLevel};
+    use ash::{extensions::ext::DebugUtils, vk};
+    use log::warn;
+    use std::{ffi::c_void, ptr};
+
+    /// Forwards the Vulkan debug messages to the user callback.
+    ///
+    /// The messages are already logged by the backend, which only enables
+    /// the debug utilities in its debug builds.
+    pub(super) struct Messenger {
+        ext: DebugUtils,
+        raw: vk::DebugUtilsMessengerEXT,
+        // boxed to have a stable address for the messenger
+        _callback: Box<(ValidationMessageCallback, *mut u8)>,
+    }
+
+    // the callback can be invoked on any thread using the instance
+    unsafe impl Send for Messenger {}
+    unsafe impl Sync for Messenger {}
+
+    impl std::fmt::Debug for Messenger {
+        fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            formatter.debug_struct("Messenger").finish()
+        }
+    }
+
+    impl Drop for Messenger {
+        fn drop(&mut self) {
+            unsafe { self.ext.destroy_debug_utils_messenger(self.raw, None) };
+        }
+    }
+
+    unsafe extern "system" fn messenger_callback(
+        severity: vk::DebugUtilsMessageSeverityFlagsEXT,
+        _message_type: vk::DebugUtilsMessageTypeFlagsEXT,
+        data: *const vk::DebugUtilsMessengerCallbackDataEXT,
+        user_data: *mut c_void,
+    ) -> vk::Bool32 {
+        let (callback, userdata) = *(user_data as *const (ValidationMessageCallback, *mut u8));
+        let level = match severity {
+            vk::DebugUtilsMessageSeverityFlagsEXT::ERROR => ValidationMessageLevel::Error,
+            vk::DebugUtilsMessageSeverityFlagsEXT::WARNING => ValidationMessageLevel::Warning,
+            vk::DebugUtilsMessageSeverityFlagsEXT::INFO => ValidationMessageLevel::Info,
+            _ => ValidationMessageLevel::Verbose,
+        };
+        if !(*data).p_message.is_null() {
+            callback(level, (*data).p_message, userdata);
+        }
+        vk::FALSE
+    }
+
+    impl Messenger {
+        /// Start forwarding the messages to the callback.
+        pub(super) fn new(
+            instance: &gfx_backend_vulkan::Instance,
+            callback: (ValidationMessageCallback, *mut u8),
+        ) -> Option<Self> {
+            // The backend creates the instance on its own, and only enables the
+            // validation layers and the debug utilities in its debug builds.
+            if !instance.extensions.contains(&DebugUtils::name()) {
+                warn!(
+                    "The validation callback is never called: the Vulkan backend is \
+                     a release build, which enables neither the validation layers \
+                     nor the debug utilities."
+                );
+                return None;
+            }
+            let callback = Box::new(callback);
+            let entry = ash::Entry::new().ok()?;
+            let ext = DebugUtils::new(&entry, &instance.raw.0);
+            let info = vk::DebugUtilsMessengerCreateInfoEXT {
+                s_type: vk::StructureType::DEBUG_UTILS_MESSENGER_CREATE_INFO_EXT,
+                p_next: ptr::null(),
+                flags: vk::DebugUtilsMessengerCreateFlagsEXT::empty(),
+                message_severity: vk::DebugUtilsMessageSeverityFlagsEXT::all(),
+                message_type: vk::DebugUtilsMessageTypeFlagsEXT::all(),
+                pfn_user_callback: Some(messenger_callback),
+                p_user_data: &*callback as *const _ as *mut c_void,
+            };
+            let raw = unsafe { ext.create_debug_utils_messenger(&info, None) }.ok()?;
+            Some(Messenger {
+                ext,
+                raw,
+                _callback: callback,
+            })
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct Instance {
+    pub(crate) backends: BackendBit,
+    // only kept alive, and destroyed before the instance
+    #[cfg(all(any(unix, windows), not(target_os = "ios"), not(target_os = "macos")))]
+    _vulkan_messenger: Option<vulkan_debug::Messenger>,
     vulkan: Option<gfx_backend_vulkan::Instance>,
     #[cfg(any(target_os = "ios", target_os = "macos"))]
     metal: gfx_backend_metal::Instance,
@@ -37,18 +229,39 @@ pub struct Instance {
 }
 
 impl Instance {
-    pub(crate) fn new(name: &str, version: u32) -> Self {
+    pub(crate) fn new(options: &InstanceOptions) -> Self {
+        let (name, version) = (options.name.as_str(), options.version);
+        info!("creating instance {:?}", options);
+
+        //TODO: reconsider once `create` returns a `Result`
+        let vulkan = if cfg!(all(any(unix, windows), not(target_os = "ios"), not(target_os = "macos"))) &&
+            options.backends.contains(BackendBit::VULKAN)
+        {
+            Some(gfx_backend_vulkan::Instance::create(name, version))
+        } else {
+            None
+        };
+        #[cfg(all(any(unix, windows), not(target_os = "ios"), not(target_os = "macos")))]
+        let vulkan_messenger = match vulkan {
+            Some(ref inst) => options
+                .callback
+                .and_then(|callback| vulkan_debug::Messenger::new(inst, callback)),
+            None => None,
+        };
+
         Instance {
-            //TODO: reconsider once `create` returns a `Result`
-            vulkan: if cfg!(all(any(unix, windows), not(target_os = "ios"), not(target_os = "macos"))) {
-                Some(gfx_backend_vulkan::Instance::create(name, version))
-            } else {
-                None
-            },
+            backends: options.backends,
+            #[cfg(all(any(unix, windows), not(target_os = "ios"), not(target_os = "macos")))]
+            _vulkan_messenger: vulkan_messenger,
+            vulkan,
             #[cfg(any(target_os = "ios", target_os = "macos"))]
             metal: gfx_backend_metal::Instance::create(name, version),
             #[cfg(windows)]
-            dx12: Some(gfx_backend_dx12::Instance::create(name, version)),
+            dx12: if options.backends.contains(BackendBit::DX12) {
+                Some(gfx_backend_dx12::Instance::create(name, version))
+            } else {
+                None
+            },
             #[cfg(windows)]
             dx11: gfx_backend_dx11::Instance::create(name, version),
         }
@@ -85,11 +298,12 @@ pub enum PowerPreference {
     HighPerformance = 2,
 }
 
-#[cfg(not(feature = "remote"))]
 bitflags! {
     #[repr(transparent)]
+    #[cfg_attr(feature = "remote", derive(Serialize, Deserialize))]
     pub struct BackendBit: u32 {
         const VULKAN = 1 << Backend::Vulkan as u32;
+        /// Not available yet, no adapters are found on it.
         const GL = 1 << Backend::Gl as u32;
         const METAL = 1 << Backend::Metal as u32;
         const DX12 = 1 << Backend::Dx12 as u32;
@@ -99,7 +313,6 @@ bitflags! {
     }
 }
 
-#[cfg(not(feature = "remote"))]
 impl From<Backend> for BackendBit {
     fn from(backend: Backend) -> Self {
         BackendBit::from_bits(1 << backend as u32).unwrap()
@@ -111,7 +324,6 @@ impl From<Backend> for BackendBit {
 #[cfg_attr(feature = "remote", derive(Serialize, Deserialize))]
 pub struct RequestAdapterOptions {
     pub power_preference: PowerPreference,
-    #[cfg(not(feature = "remote"))]
     pub backends: BackendBit,
 }
 
@@ -119,7 +331,6 @@ impl Default for RequestAdapterOptions {
     fn default() -> Self {
         RequestAdapterOptions {
             power_preference: PowerPreference::Default,
-            #[cfg(not(feature = "remote"))]
             backends: BackendBit::PRIMARY,
         }
     }
@@ -130,6 +341,38 @@ impl Default for RequestAdapterOptions {
 #[cfg_attr(feature = "remote", derive(Serialize, Deserialize))]
 pub struct Extensions {
     pub anisotropic_filtering: bool,
+    /// Allows dynamically uniform indexing into arrays of buffers and textures in shaders.
+    //TODO: non-uniform indexing and partially bound arrays,
+    // once gfx-hal exposes them.
+    pub descriptor_indexing: bool,
+    /// Allows the BC1-BC7 compressed texture formats.
+    pub texture_compression_bc: bool,
+    /// Allows the ETC2 and EAC compressed texture formats.
+    pub texture_compression_etc2: bool,
+    /// Allows the LDR profile of the ASTC compressed texture formats.
+    pub texture_compression_astc: bool,
+}
+
+impl Extensions {
+    pub(crate) fn to_hal_features(&self) -> hal::Features {
+        let mut features = hal::Features::empty();
+        if self.descriptor_indexing {
+            features |= hal::Features::SHADER_UNIFORM_BUFFER_ARRAY_DYNAMIC_INDEXING
+                | hal::Features::SHADER_SAMPLED_IMAGE_ARRAY_DYNAMIC_INDEXING
+                | hal::Features::SHADER_STORAGE_BUFFER_ARRAY_DYNAMIC_INDEXING
+                | hal::Features::SHADER_STORAGE_IMAGE_ARRAY_DYNAMIC_INDEXING;
+        }
+        if self.texture_compression_bc {
+            features |= hal::Features::FORMAT_BC;
+        }
+        if self.texture_compression_etc2 {
+            features |= hal::Features::FORMAT_ETC2;
+        }
+        if self.texture_compression_astc {
+            features |= hal::Features::FORMAT_ASTC_LDR;
+        }
+        features
+    }
 }
 
 #[repr(C)]
@@ -153,13 +396,28 @@ impl Default for Limits {
 pub struct DeviceDescriptor {
     pub extensions: Extensions,
     pub limits: Limits,
+    /// Number of compute-only queues to open in addition to the general one.
+    pub compute_queue_count: u32,
+    /// Number of transfer-only queues to open in addition to the general one.
+    pub transfer_queue_count: u32,
+    /// Run a background thread that retires finished submissions and fires
+    /// the buffer mapping callbacks as soon as the GPU work is done.
+    ///
+    /// The callbacks are then invoked on that thread, unless the user happens
+    /// to poll the device first (with `wgpu_device_poll` or `wgpu_queue_submit`),
+    /// in which case they run on the polling thread. Either way, each callback
+    /// is invoked exactly once, and never with any internal locks held.
+    pub background_maintenance: bool,
 }
 
-#[cfg(not(feature = "remote"))]
-pub fn wgpu_create_surface(raw_handle: raw_window_handle::RawWindowHandle) -> SurfaceId {
+pub fn create_surface(
+    global: &Global,
+    raw_handle: raw_window_handle::RawWindowHandle,
+    id_in: Input<SurfaceId>,
+) -> SurfaceId {
     use raw_window_handle::RawWindowHandle as Rwh;
 
-    let instance = &GLOBAL.instance;
+    let instance = &global.instance;
     let ref_count = LifeGuard::new().ref_count;
     let surface = match raw_handle {
         #[cfg(target_os = "ios")]
@@ -219,12 +477,14 @@ pub fn wgpu_create_surface(raw_handle: raw_window_handle::RawWindowHandle) -> Su
     };
 
     let mut token = Token::root();
-    GLOBAL
-        .surfaces
-        .register_identity(PhantomData, surface, &mut token)
+    global.surfaces.register_identity(id_in, surface, &mut token)
 }
 
-#[cfg(all(not(feature = "remote"), unix, not(target_os = "ios"), not(target_os = "macos")))]
+pub fn wgpu_create_surface(raw_handle: raw_window_handle::RawWindowHandle) -> SurfaceId {
+    create_surface(&*GLOBAL, raw_handle, None)
+}
+
+#[cfg(all(unix, not(target_os = "ios"), not(target_os = "macos")))]
 #[no_mangle]
 pub extern "C" fn wgpu_create_surface_from_xlib(
     display: *mut *const std::ffi::c_void,
@@ -238,7 +498,47 @@ pub extern "C" fn wgpu_create_surface_from_xlib(
     }))
 }
 
-#[cfg(all(not(feature = "remote"), any(target_os = "ios", target_os = "macos")))]
+#[cfg(all(unix, not(target_os = "ios"), not(target_os = "macos")))]
+#[no_mangle]
+pub extern "C" fn wgpu_create_surface_from_wayland(
+    display: *mut std::ffi::c_void,
+    surface: *mut std::ffi::c_void,
+) -> SurfaceId {
+    use raw_window_handle::unix::WaylandHandle;
+    wgpu_create_surface(raw_window_handle::RawWindowHandle::Wayland(WaylandHandle {
+        surface,
+        display,
+        ..WaylandHandle::empty()
+    }))
+}
+
+/// Create a surface for an xcb window. Requires the `xcb` feature.
+#[cfg(all(
+    feature = "xcb",
+    unix,
+    not(target_os = "ios"),
+    not(target_os = "macos")
+))]
+#[no_mangle]
+pub extern "C" fn wgpu_create_surface_from_xcb(
+    connection: *mut std::ffi::c_void,
+    window: u32,
+) -> SurfaceId {
+    let surface = Surface {
+        swap_chain: None,
+        ref_count: LifeGuard::new().ref_count,
+        vulkan: GLOBAL
+            .instance
+            .vulkan
+            .as_ref()
+            .map(|inst| inst.create_surface_from_xcb(connection as *mut _, window)),
+    };
+
+    let mut token = Token::root();
+    GLOBAL.surfaces.register_identity(None, surface, &mut token)
+}
+
+#[cfg(any(target_os = "ios", target_os = "macos"))]
 #[no_mangle]
 pub extern "C" fn wgpu_create_surface_from_metal_layer(layer: *mut std::ffi::c_void) -> SurfaceId {
     let surface = Surface {
@@ -253,10 +553,10 @@ pub extern "C" fn wgpu_create_surface_from_metal_layer(layer: *mut std::ffi::c_v
 
     GLOBAL
         .surfaces
-        .register_identity(PhantomData, surface, &mut Token::root())
+        .register_identity(None, surface, &mut Token::root())
 }
 
-#[cfg(all(not(feature = "remote"), windows))]
+#[cfg(windows)]
 #[no_mangle]
 pub extern "C" fn wgpu_create_surface_from_windows_hwnd(
     _hinstance: *mut std::ffi::c_void,
@@ -271,22 +571,46 @@ pub extern "C" fn wgpu_create_surface_from_windows_hwnd(
     ))
 }
 
+/// Create the instance, with the backends and validation callback of `desc`.
+///
+/// It has to be called before any other function, otherwise the instance is
+/// created on first use, with all the backends and no validation callback.
+pub fn create_instance(desc: &InstanceDescriptor) {
+    *INSTANCE_OPTIONS.lock() = Some(desc.to_options());
+    lazy_static::initialize(&GLOBAL);
+    assert!(
+        INSTANCE_OPTIONS.lock().take().is_none(),
+        "The instance has already been created"
+    );
+}
+
+#[no_mangle]
+pub extern "C" fn wgpu_create_instance(desc: &InstanceDescriptor) {
+    create_instance(desc)
+}
+
+/// Request an adapter of one of the backends enabled in `desc`.
+///
+/// `input_ids` are the IDs to use for each backend, if the context takes them
+/// from the caller, see `IdentityMode`. Otherwise, it's empty.
 pub fn request_adapter(
+    global: &Global,
     desc: &RequestAdapterOptions,
-    input_ids: &[Input<AdapterId>],
-) -> Option<Output<AdapterId>> {
-    let instance = &GLOBAL.instance;
+    input_ids: &[AdapterId],
+) -> Option<AdapterId> {
+    let instance = &global.instance;
     let mut device_types = Vec::new();
 
-    #[cfg(feature = "remote")]
-    let find_input = |b: Backend| input_ids.iter().find(|id| id.backend() == b).cloned();
-    #[cfg(not(feature = "remote"))]
-    let find_input = |b: Backend| {
-        let _ = input_ids;
-        if desc.backends.contains(b.into()) {
-            Some(PhantomData)
-        } else {
+    let find_input = |b: Backend| -> Option<Input<AdapterId>> {
+        if !desc.backends.contains(b.into()) || !instance.backends.contains(b.into()) {
             None
+        } else if input_ids.is_empty() {
+            Some(None)
+        } else {
+            input_ids
+                .iter()
+                .find(|id| id.backend() == b)
+                .map(|&id| Some(id))
         }
     };
 
@@ -363,7 +687,7 @@ pub fn request_adapter(
                 raw: adapters_vk.swap_remove(selected),
             };
             info!("Adapter Vulkan {:?}", adapter.raw.info);
-            let id_out = backend::Vulkan::hub().adapters.register_identity(
+            let id_out = backend::Vulkan::hub(global).adapters.register_identity(
                 id_vulkan.unwrap(),
                 adapter,
                 &mut token,
@@ -379,7 +703,7 @@ pub fn request_adapter(
                 raw: adapters_mtl.swap_remove(selected),
             };
             info!("Adapter Metal {:?}", adapter.raw.info);
-            let id_out = backend::Metal::hub().adapters.register_identity(
+            let id_out = backend::Metal::hub(global).adapters.register_identity(
                 id_metal.unwrap(),
                 adapter,
                 &mut token,
@@ -395,7 +719,7 @@ pub fn request_adapter(
                 raw: adapters_dx12.swap_remove(selected),
             };
             info!("Adapter Dx12 {:?}", adapter.raw.info);
-            let id_out = backend::Dx12::hub().adapters.register_identity(
+            let id_out = backend::Dx12::hub(global).adapters.register_identity(
                 id_dx12.unwrap(),
                 adapter,
                 &mut token,
@@ -408,7 +732,7 @@ pub fn request_adapter(
                 raw: adapters_dx11.swap_remove(selected),
             };
             info!("Adapter Dx11 {:?}", adapter.raw.info);
-            let id_out = backend::Dx11::hub().adapters.register_identity(
+            let id_out = backend::Dx11::hub(global).adapters.register_identity(
                 id_dx11.unwrap(),
                 adapter,
                 &mut token,
@@ -421,23 +745,112 @@ pub fn request_adapter(
     None
 }
 
-#[cfg(not(feature = "remote"))]
 #[no_mangle]
 pub extern "C" fn wgpu_request_adapter(desc: Option<&RequestAdapterOptions>) -> AdapterId {
-    request_adapter(&desc.cloned().unwrap_or_default(), &[]).unwrap()
+    request_adapter(&*GLOBAL, &desc.cloned().unwrap_or_default(), &[]).unwrap()
 }
 
 pub fn adapter_request_device<B: GfxBackend>(
+    global: &Arc<Global>,
     adapter_id: AdapterId,
-    _desc: &DeviceDescriptor,
+    desc: &DeviceDescriptor,
     id_in: Input<DeviceId>,
-) -> Output<DeviceId> {
-    let hub = B::hub();
+) -> DeviceId {
+    let hub = B::hub(global);
     let mut token = Token::root();
     let device = {
         let (adapter_guard, _) = hub.adapters.read(&mut token);
         let adapter = &adapter_guard[adapter_id].raw;
-        let (raw, queue_group) = adapter.open_with::<_, hal::General>(1, |_qf| true).unwrap();
+
+        let mut features = desc.extensions.to_hal_features();
+        let available_features = adapter.physical_device.features();
+        assert!(
+            available_features.contains(features),
+            "Requested extensions {:?} are not supported by the adapter: missing {:?}",
+            desc.extensions,
+            features - available_features
+        );
+        // cube array views are part of the core API where the adapter supports them
+        features |= available_features & hal::Features::IMAGE_CUBE_ARRAY;
+        // the mipmaps of the formats that can't be blitted are generated
+        // by writing to storage images of any format
+        features |= available_features & hal::Features::SHADER_STORAGE_IMAGE_WRITE_WITHOUT_FORMAT;
+
+        let general_family = adapter
+            .queue_families
+            .iter()
+            .find(|family| hal::General::supported_by(family.queue_type()))
+            .expect("No general queue family is available");
+        // Dedicated queues are opened on the families of their exact type,
+        // falling back to the general family if the adapter doesn't have one.
+        let mut requests = vec![(general_family, vec![1.0])];
+        let mut dedicated = Vec::new();
+        for &(ty, count) in &[
+            (QueueType::Compute, desc.compute_queue_count),
+            (QueueType::Transfer, desc.transfer_queue_count),
+        ] {
+            if count == 0 {
+                continue;
+            }
+            let family = adapter
+                .queue_families
+                .iter()
+                .find(|family| family.queue_type() == ty.to_hal())
+                .unwrap_or(general_family);
+            let position = match requests.iter().position(|&(f, _)| f.id() == family.id()) {
+                Some(position) => position,
+                None => {
+                    requests.push((family, Vec::new()));
+                    requests.len() - 1
+                }
+            };
+            let priorities = &mut requests[position].1;
+            priorities.extend((0 .. count).map(|_| 1.0));
+            assert!(
+                priorities.len() <= family.max_queues(),
+                "Unable to open {} {:?} queues: queue family {:?} only has {}",
+                count,
+                ty,
+                family.id(),
+                family.max_queues()
+            );
+            dedicated.extend((0 .. count).map(|_| (ty, family.id())));
+        }
+
+        let families = requests
+            .iter()
+            .map(|&(family, ref priorities)| (family, priorities.as_slice()))
+            .collect::<Vec<_>>();
+        let mut gpu = unsafe { adapter.physical_device.open(&families, features) }.unwrap();
+        let mut queue_group = gpu
+            .queues
+            .take::<hal::General>(general_family.id())
+            .unwrap();
+        let mut raw_queues = FastHashMap::default();
+        raw_queues.insert(
+            general_family.id(),
+            queue_group
+                .queues
+                .drain(1 ..)
+                .map(|queue| queue.into_raw())
+                .collect::<Vec<_>>()
+                .into_iter(),
+        );
+        for &(family, _) in &requests[1 ..] {
+            let queues = gpu.queues.take_raw(family.id()).unwrap();
+            raw_queues.insert(family.id(), queues.into_iter());
+        }
+        let dedicated_queues = dedicated
+            .into_iter()
+            .enumerate()
+            .map(|(position, (ty, family))| DedicatedQueue {
+                raw: raw_queues.get_mut(&family).unwrap().next().unwrap(),
+                ty,
+                family,
+                com_allocator: CommandAllocator::new(family, 1 + position, ty),
+            })
+            .collect();
+        let raw = gpu.device;
 
         let limits = adapter.physical_device.limits();
         assert_eq!(
@@ -452,18 +865,142 @@ pub fn adapter_request_device<B: GfxBackend>(
         );
 
         let mem_props = adapter.physical_device.memory_properties();
-        Device::new(raw, adapter_id, queue_group, mem_props)
+        Device::new(
+            raw,
+            adapter_id,
+            queue_group,
+            dedicated_queues,
+            mem_props,
+            desc.extensions.clone(),
+            features,
+        )
     };
 
-    hub.devices.register_identity(id_in, device, &mut token)
+    let id = hub.devices.new_identity(id_in);
+    hub.devices.register(id, device, &mut token);
+    if desc.background_maintenance {
+        let (device_guard, _) = hub.devices.read(&mut token);
+        *device_guard[id].maintenance.lock() = Some(MaintenanceThread::spawn::<B>(global, id));
+    }
+    id
 }
 
-#[cfg(not(feature = "remote"))]
 #[no_mangle]
 pub extern "C" fn wgpu_adapter_request_device(
     adapter_id: AdapterId,
     desc: Option<&DeviceDescriptor>,
 ) -> DeviceId {
     let desc = &desc.cloned().unwrap_or_default();
-    gfx_select!(adapter_id => adapter_request_device(adapter_id, desc, PhantomData))
+    gfx_select!(adapter_id => adapter_request_device(&*GLOBAL, adapter_id, desc, None))
+}
+
+bitflags! {
+    #[repr(transparent)]
+    pub struct TextureFormatFeatureFlags: u32 {
+        /// The format can be sampled with linear filtering.
+        const FILTERABLE = 1;
+        /// The format supports blending as a color attachment.
+        const BLENDABLE = 2;
+        /// The format supports atomic operations as a storage texture.
+        const STORAGE_ATOMIC = 4;
+        /// The format can be used as a color attachment.
+        const COLOR_ATTACHMENT = 8;
+        /// The format can be used as a depth-stencil attachment.
+        const DEPTH_STENCIL_ATTACHMENT = 16;
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct TextureFormatFeatures {
+    /// Texture usages allowed for the format.
+    pub allowed_usages: TextureUsage,
+    pub flags: TextureFormatFeatureFlags,
+    /// Supported sample counts for the attachments of this format,
+    /// with the bit of value `n` set if `n` samples are supported.
+    pub sample_counts: u32,
+}
+
+pub(crate) fn texture_format_features<B: hal::Backend>(
+    physical_device: &B::PhysicalDevice,
+    format: TextureFormat,
+) -> TextureFormatFeatures {
+    use hal::format::ImageFeature as If;
+
+    let hal_format = conv::map_texture_format(format);
+    let features = physical_device
+        .format_properties(Some(hal_format))
+        .optimal_tiling;
+
+    let mut allowed_usages = TextureUsage::NONE;
+    let mut flags = TextureFormatFeatureFlags::empty();
+    let mut attachment_usage = hal::image::Usage::empty();
+    // hal doesn't report the transfer support, which every supported format has
+    if !features.is_empty() {
+        allowed_usages |= TextureUsage::COPY_SRC | TextureUsage::COPY_DST;
+    }
+    if features.contains(If::SAMPLED) {
+        allowed_usages |= TextureUsage::SAMPLED;
+    }
+    if features.contains(If::STORAGE) {
+        allowed_usages |= TextureUsage::STORAGE | TextureUsage::STORAGE_READ;
+    }
+    if features.contains(If::COLOR_ATTACHMENT) {
+        allowed_usages |= TextureUsage::OUTPUT_ATTACHMENT;
+        flags |= TextureFormatFeatureFlags::COLOR_ATTACHMENT;
+        attachment_usage |= hal::image::Usage::COLOR_ATTACHMENT;
+    }
+    if features.contains(If::DEPTH_STENCIL_ATTACHMENT) {
+        allowed_usages |= TextureUsage::OUTPUT_ATTACHMENT;
+        flags |= TextureFormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT;
+        attachment_usage |= hal::image::Usage::DEPTH_STENCIL_ATTACHMENT;
+    }
+    if features.contains(If::SAMPLED_LINEAR) {
+        flags |= TextureFormatFeatureFlags::FILTERABLE;
+    }
+    if features.contains(If::COLOR_ATTACHMENT_BLEND) {
+        flags |= TextureFormatFeatureFlags::BLENDABLE;
+    }
+    if features.contains(If::STORAGE_ATOMIC) {
+        flags |= TextureFormatFeatureFlags::STORAGE_ATOMIC;
+    }
+
+    let sample_counts = if attachment_usage.is_empty() {
+        1
+    } else {
+        physical_device
+            .image_format_properties(
+                hal_format,
+                2,
+                hal::image::Tiling::Optimal,
+                attachment_usage,
+                hal::image::ViewCapabilities::empty(),
+            )
+            .map_or(1, |properties| properties.sample_count_mask as u32)
+    };
+
+    TextureFormatFeatures {
+        allowed_usages,
+        flags,
+        sample_counts,
+    }
+}
+
+pub fn adapter_get_texture_format_features<B: GfxBackend>(
+    global: &Global,
+    adapter_id: AdapterId,
+    format: TextureFormat,
+) -> TextureFormatFeatures {
+    let hub = B::hub(global);
+    let mut token = Token::root();
+    let (adapter_guard, _) = hub.adapters.read(&mut token);
+    texture_format_features::<B>(&adapter_guard[adapter_id].raw.physical_device, format)
+}
+
+#[no_mangle]
+pub extern "C" fn wgpu_adapter_get_texture_format_features(
+    adapter_id: AdapterId,
+    format: TextureFormat,
+) -> TextureFormatFeatures {
+    gfx_select!(adapter_id => adapter_get_texture_format_features(&*GLOBAL, adapter_id, format))
 }
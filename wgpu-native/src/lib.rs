@@ -24,8 +24,17 @@ mod track;
 pub use self::binding_model::*;
 pub use self::command::*;
 pub use self::device::*;
-#[cfg(feature = "remote")]
-pub use self::hub::{Access, IdentityManager, Registry, Token};
+pub use self::hub::{
+    Access,
+    Global,
+    IdentityHandler,
+    IdentityManager,
+    IdentityMode,
+    IdentityPassthrough,
+    Registry,
+    Token,
+    GLOBAL,
+};
 pub use self::id::*;
 pub use self::instance::*;
 pub use self::pipeline::*;
@@ -39,7 +48,7 @@ use std::{
     sync::atomic::{AtomicUsize, Ordering},
 };
 
-type SubmissionIndex = usize;
+pub type SubmissionIndex = usize;
 type Index = u32;
 type Epoch = u32;
 
@@ -209,6 +218,7 @@ macro_rules! gfx_select {
             $crate::Backend::Dx12 => $function::<$crate::backend::Dx12>( $($param),+ ),
             #[cfg(windows)]
             $crate::Backend::Dx11 => $function::<$crate::backend::Dx11>( $($param),+ ),
+            $crate::Backend::Gl => panic!("The GL backend is not available"),
             _ => unreachable!()
         }
     };
@@ -2,9 +2,11 @@ use crate::{
     device::RenderPassContext,
     resource,
     BufferAddress,
+    DeviceId,
     PipelineLayoutId,
     RawString,
     ShaderModuleId,
+    Stored,
     U32Array,
 };
 
@@ -263,6 +265,7 @@ pub struct ComputePipelineDescriptor {
 #[derive(Debug)]
 pub struct ComputePipeline<B: hal::Backend> {
     pub(crate) raw: B::ComputePipeline,
+    pub(crate) device_id: Stored<DeviceId>,
     pub(crate) layout_id: PipelineLayoutId,
 }
 
@@ -341,6 +344,7 @@ bitflags! {
 #[derive(Debug)]
 pub struct RenderPipeline<B: hal::Backend> {
     pub(crate) raw: B::GraphicsPipeline,
+    pub(crate) device_id: Stored<DeviceId>,
     pub(crate) layout_id: PipelineLayoutId,
     pub(crate) pass_context: RenderPassContext,
     pub(crate) flags: PipelineFlags,
@@ -16,7 +16,7 @@ use hal;
 use parking_lot::Mutex;
 use rendy_memory::MemoryBlock;
 
-use std::borrow::Borrow;
+use std::{borrow::Borrow, ops::Range, sync::Arc, task::Waker};
 
 bitflags! {
     #[repr(transparent)]
@@ -53,49 +53,169 @@ pub struct BufferDescriptor {
 }
 
 #[repr(C)]
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum BufferMapAsyncStatus {
     Success,
     Error,
     Unknown,
     ContextLost,
+    Timeout,
+}
+
+/// State shared between a pending mapping operation and the `Future` waiting on it.
+#[derive(Debug, Default)]
+pub struct BufferMapFutureState {
+    pub(crate) result: Option<Result<*mut u8, BufferMapAsyncStatus>>,
+    pub(crate) waker: Option<Waker>,
+}
+
+unsafe impl Send for BufferMapFutureState {}
+
+pub(crate) type BufferMapFutureShared = Arc<Mutex<BufferMapFutureState>>;
+
+pub(crate) fn complete_map_future(
+    shared: &BufferMapFutureShared,
+    result: Result<*mut u8, BufferMapAsyncStatus>,
+) {
+    let waker = {
+        let mut state = shared.lock();
+        state.result = Some(result);
+        state.waker.take()
+    };
+    // wake outside of the lock, so that the executor can poll right away
+    if let Some(waker) = waker {
+        waker.wake();
+    }
 }
 
 #[derive(Clone, Debug)]
 pub enum BufferMapOperation {
     Read(std::ops::Range<u64>, BufferMapReadCallback, *mut u8),
     Write(std::ops::Range<u64>, BufferMapWriteCallback, *mut u8),
+    ReadFuture(std::ops::Range<u64>, BufferMapFutureShared),
+    WriteFuture(std::ops::Range<u64>, BufferMapFutureShared),
 }
 
 unsafe impl Send for BufferMapOperation {}
 unsafe impl Sync for BufferMapOperation {}
 
 impl BufferMapOperation {
+    pub(crate) fn range(&self) -> std::ops::Range<u64> {
+        match *self {
+            BufferMapOperation::Read(ref range, ..) => range.clone(),
+            BufferMapOperation::Write(ref range, ..) => range.clone(),
+            BufferMapOperation::ReadFuture(ref range, _) => range.clone(),
+            BufferMapOperation::WriteFuture(ref range, _) => range.clone(),
+        }
+    }
+
+    /// Check if this operation is the one awaited through `shared`.
+    pub(crate) fn is_awaited_by(&self, shared: &BufferMapFutureShared) -> bool {
+        match *self {
+            BufferMapOperation::ReadFuture(_, ref other) => Arc::ptr_eq(other, shared),
+            BufferMapOperation::WriteFuture(_, ref other) => Arc::ptr_eq(other, shared),
+            _ => false,
+        }
+    }
+
     pub(crate) fn call_error(self) {
         match self {
             BufferMapOperation::Read(_, callback, userdata) => {
-                log::error!("wgpu_buffer_map_read_async failed: buffer mapping is pending");
+                log::error!("wgpu_buffer_map_read_async failed: range is already mapped or pending");
                 callback(BufferMapAsyncStatus::Error, std::ptr::null_mut(), userdata);
             }
             BufferMapOperation::Write(_, callback, userdata) => {
-                log::error!("wgpu_buffer_map_write_async failed: buffer mapping is pending");
+                log::error!("wgpu_buffer_map_write_async failed: range is already mapped or pending");
                 callback(BufferMapAsyncStatus::Error, std::ptr::null_mut(), userdata);
             }
+            BufferMapOperation::ReadFuture(_, shared) => {
+                log::error!("buffer_map_read failed: range is already mapped or pending");
+                complete_map_future(&shared, Err(BufferMapAsyncStatus::Error));
+            }
+            BufferMapOperation::WriteFuture(_, shared) => {
+                log::error!("buffer_map_write failed: range is already mapped or pending");
+                complete_map_future(&shared, Err(BufferMapAsyncStatus::Error));
+            }
         }
     }
 }
 
+/// A range of the buffer that is currently mapped to the host.
+#[derive(Debug)]
+pub(crate) struct BufferMappedRange {
+    pub(crate) range: std::ops::Range<BufferAddress>,
+    pub(crate) ptr: *mut u8,
+    /// Memory range to flush on unmap, for writable non-coherent mappings.
+    pub(crate) flush_range: Option<std::ops::Range<u64>>,
+}
+
+unsafe impl Send for BufferMappedRange {}
+unsafe impl Sync for BufferMappedRange {}
+
+/// Parts of a resource that haven't been written to yet, and have to be
+/// zero-initialized before they can be read.
+#[derive(Debug)]
+pub(crate) struct UninitializedRanges<I>(Vec<Range<I>>);
+
+impl<I: Copy + Ord> UninitializedRanges<I> {
+    pub(crate) fn new(range: Range<I>) -> Self {
+        if range.start < range.end {
+            UninitializedRanges(vec![range])
+        } else {
+            UninitializedRanges(Vec::new())
+        }
+    }
+
+    /// Mark `range` as initialized, returning the parts of it that were not.
+    pub(crate) fn take(&mut self, range: Range<I>) -> Vec<Range<I>> {
+        let mut taken = Vec::new();
+        let mut remaining = Vec::with_capacity(self.0.len() + 1);
+        for uninit in self.0.drain(..) {
+            let start = uninit.start.max(range.start);
+            let end = uninit.end.min(range.end);
+            if start >= end {
+                remaining.push(uninit);
+                continue;
+            }
+            if uninit.start < start {
+                remaining.push(uninit.start .. start);
+            }
+            if end < uninit.end {
+                remaining.push(end .. uninit.end);
+            }
+            taken.push(start .. end);
+        }
+        self.0 = remaining;
+        taken
+    }
+}
+
 #[derive(Debug)]
 pub struct Buffer<B: hal::Backend> {
     pub(crate) raw: B::Buffer,
     pub(crate) device_id: Stored<DeviceId>,
     pub(crate) memory: MemoryBlock<B>,
     pub(crate) size: BufferAddress,
-    pub(crate) mapped_write_ranges: Vec<std::ops::Range<u64>>,
-    pub(crate) pending_map_operation: Option<BufferMapOperation>,
+    pub(crate) usage: BufferUsage,
+    pub(crate) uninitialized: Mutex<UninitializedRanges<BufferAddress>>,
+    pub(crate) mapped_ranges: Vec<BufferMappedRange>,
+    pub(crate) pending_map_operations: Vec<BufferMapOperation>,
     pub(crate) life_guard: LifeGuard,
 }
 
+impl<B: hal::Backend> Buffer<B> {
+    /// Check if the range intersects with any of the mapped or pending ranges.
+    pub(crate) fn is_mapping_overlapped(&self, range: &std::ops::Range<BufferAddress>) -> bool {
+        let overlaps = |other: &std::ops::Range<BufferAddress>| {
+            other.start < range.end && range.start < other.end
+        };
+        self.mapped_ranges.iter().any(|mapped| overlaps(&mapped.range)) ||
+            self.pending_map_operations
+                .iter()
+                .any(|operation| overlaps(&operation.range()))
+    }
+}
+
 impl<B: hal::Backend> Borrow<RefCount> for Buffer<B> {
     fn borrow(&self) -> &RefCount {
         &self.life_guard.ref_count
@@ -171,6 +291,126 @@ pub enum TextureFormat {
     Depth32Float = 41,
     Depth24Plus = 42,
     Depth24PlusStencil8 = 43,
+
+    // BC compressed formats, available with `Extensions::texture_compression_bc`
+    Bc1RgbaUnorm = 44,
+    Bc1RgbaUnormSrgb = 45,
+    Bc2RgbaUnorm = 46,
+    Bc2RgbaUnormSrgb = 47,
+    Bc3RgbaUnorm = 48,
+    Bc3RgbaUnormSrgb = 49,
+    Bc4RUnorm = 50,
+    Bc4RSnorm = 51,
+    Bc5RgUnorm = 52,
+    Bc5RgSnorm = 53,
+    Bc6hRgbUfloat = 54,
+    Bc6hRgbSfloat = 55,
+    Bc7RgbaUnorm = 56,
+    Bc7RgbaUnormSrgb = 57,
+
+    // ETC2 and EAC compressed formats, available with `Extensions::texture_compression_etc2`
+    Etc2Rgb8Unorm = 58,
+    Etc2Rgb8UnormSrgb = 59,
+    Etc2Rgb8A1Unorm = 60,
+    Etc2Rgb8A1UnormSrgb = 61,
+    Etc2Rgba8Unorm = 62,
+    Etc2Rgba8UnormSrgb = 63,
+    EacR11Unorm = 64,
+    EacR11Snorm = 65,
+    EacRg11Unorm = 66,
+    EacRg11Snorm = 67,
+
+    // ASTC compressed formats, available with `Extensions::texture_compression_astc`
+    Astc4x4RgbaUnorm = 68,
+    Astc4x4RgbaUnormSrgb = 69,
+    Astc5x4RgbaUnorm = 70,
+    Astc5x4RgbaUnormSrgb = 71,
+    Astc5x5RgbaUnorm = 72,
+    Astc5x5RgbaUnormSrgb = 73,
+    Astc6x5RgbaUnorm = 74,
+    Astc6x5RgbaUnormSrgb = 75,
+    Astc6x6RgbaUnorm = 76,
+    Astc6x6RgbaUnormSrgb = 77,
+    Astc8x5RgbaUnorm = 78,
+    Astc8x5RgbaUnormSrgb = 79,
+    Astc8x6RgbaUnorm = 80,
+    Astc8x6RgbaUnormSrgb = 81,
+    Astc8x8RgbaUnorm = 82,
+    Astc8x8RgbaUnormSrgb = 83,
+    Astc10x5RgbaUnorm = 84,
+    Astc10x5RgbaUnormSrgb = 85,
+    Astc10x6RgbaUnorm = 86,
+    Astc10x6RgbaUnormSrgb = 87,
+    Astc10x8RgbaUnorm = 88,
+    Astc10x8RgbaUnormSrgb = 89,
+    Astc10x10RgbaUnorm = 90,
+    Astc10x10RgbaUnormSrgb = 91,
+    Astc12x10RgbaUnorm = 92,
+    Astc12x10RgbaUnormSrgb = 93,
+    Astc12x12RgbaUnorm = 94,
+    Astc12x12RgbaUnormSrgb = 95,
+}
+
+impl TextureFormat {
+    /// Adapter features required for textures of this format.
+    pub(crate) fn required_features(self) -> hal::Features {
+        use TextureFormat as Tf;
+        match self {
+            Tf::Bc1RgbaUnorm |
+            Tf::Bc1RgbaUnormSrgb |
+            Tf::Bc2RgbaUnorm |
+            Tf::Bc2RgbaUnormSrgb |
+            Tf::Bc3RgbaUnorm |
+            Tf::Bc3RgbaUnormSrgb |
+            Tf::Bc4RUnorm |
+            Tf::Bc4RSnorm |
+            Tf::Bc5RgUnorm |
+            Tf::Bc5RgSnorm |
+            Tf::Bc6hRgbUfloat |
+            Tf::Bc6hRgbSfloat |
+            Tf::Bc7RgbaUnorm |
+            Tf::Bc7RgbaUnormSrgb => hal::Features::FORMAT_BC,
+            Tf::Etc2Rgb8Unorm |
+            Tf::Etc2Rgb8UnormSrgb |
+            Tf::Etc2Rgb8A1Unorm |
+            Tf::Etc2Rgb8A1UnormSrgb |
+            Tf::Etc2Rgba8Unorm |
+            Tf::Etc2Rgba8UnormSrgb |
+            Tf::EacR11Unorm |
+            Tf::EacR11Snorm |
+            Tf::EacRg11Unorm |
+            Tf::EacRg11Snorm => hal::Features::FORMAT_ETC2,
+            Tf::Astc4x4RgbaUnorm |
+            Tf::Astc4x4RgbaUnormSrgb |
+            Tf::Astc5x4RgbaUnorm |
+            Tf::Astc5x4RgbaUnormSrgb |
+            Tf::Astc5x5RgbaUnorm |
+            Tf::Astc5x5RgbaUnormSrgb |
+            Tf::Astc6x5RgbaUnorm |
+            Tf::Astc6x5RgbaUnormSrgb |
+            Tf::Astc6x6RgbaUnorm |
+            Tf::Astc6x6RgbaUnormSrgb |
+            Tf::Astc8x5RgbaUnorm |
+            Tf::Astc8x5RgbaUnormSrgb |
+            Tf::Astc8x6RgbaUnorm |
+            Tf::Astc8x6RgbaUnormSrgb |
+            Tf::Astc8x8RgbaUnorm |
+            Tf::Astc8x8RgbaUnormSrgb |
+            Tf::Astc10x5RgbaUnorm |
+            Tf::Astc10x5RgbaUnormSrgb |
+            Tf::Astc10x6RgbaUnorm |
+            Tf::Astc10x6RgbaUnormSrgb |
+            Tf::Astc10x8RgbaUnorm |
+            Tf::Astc10x8RgbaUnormSrgb |
+            Tf::Astc10x10RgbaUnorm |
+            Tf::Astc10x10RgbaUnormSrgb |
+            Tf::Astc12x10RgbaUnorm |
+            Tf::Astc12x10RgbaUnormSrgb |
+            Tf::Astc12x12RgbaUnorm |
+            Tf::Astc12x12RgbaUnormSrgb => hal::Features::FORMAT_ASTC_LDR,
+            _ => hal::Features::empty(),
+        }
+    }
 }
 
 bitflags! {
@@ -181,9 +421,10 @@ bitflags! {
         const SAMPLED = 4;
         const STORAGE = 8;
         const OUTPUT_ATTACHMENT = 16;
+        const STORAGE_READ = 32;
         const NONE = 0;
         /// The combination of all read-only usages.
-        const READ_ALL = Self::COPY_SRC.bits | Self::SAMPLED.bits;
+        const READ_ALL = Self::COPY_SRC.bits | Self::SAMPLED.bits | Self::STORAGE_READ.bits;
         /// The combination of all write-only and read-write usages.
         const WRITE_ALL = Self::COPY_DST.bits | Self::STORAGE.bits | Self::OUTPUT_ATTACHMENT.bits;
         /// The combination of all usages that the are guaranteed to be be ordered by the hardware.
@@ -208,15 +449,15 @@ pub struct TextureDescriptor {
 
 #[derive(Debug)]
 pub(crate) enum TexturePlacement<B: hal::Backend> {
-    #[cfg_attr(not(not(feature = "remote")), allow(unused))]
-    SwapChain(SwapChainLink<Mutex<SwapImageEpoch>>),
+    /// Images of a swap chain, which own their memory if it's headless.
+    SwapChain(SwapChainLink<Mutex<SwapImageEpoch>>, Option<MemoryBlock<B>>),
     Memory(MemoryBlock<B>),
 }
 
 impl<B: hal::Backend> TexturePlacement<B> {
     pub fn as_swap_chain(&self) -> &SwapChainLink<Mutex<SwapImageEpoch>> {
         match *self {
-            TexturePlacement::SwapChain(ref link) => link,
+            TexturePlacement::SwapChain(ref link, _) => link,
             TexturePlacement::Memory(_) => panic!("Expected swap chain link!"),
         }
     }
@@ -227,12 +468,40 @@ pub struct Texture<B: hal::Backend> {
     pub(crate) raw: B::Image,
     pub(crate) device_id: Stored<DeviceId>,
     pub(crate) kind: hal::image::Kind,
+    pub(crate) view_capabilities: hal::image::ViewCapabilities,
     pub(crate) format: TextureFormat,
+    pub(crate) usage: TextureUsage,
     pub(crate) full_range: hal::image::SubresourceRange,
+    /// Array layers that haven't been initialized yet, per mip level.
+    pub(crate) uninitialized: Mutex<Vec<UninitializedRanges<hal::image::Layer>>>,
     pub(crate) placement: TexturePlacement<B>,
     pub(crate) life_guard: LifeGuard,
 }
 
+impl<B: hal::Backend> Texture<B> {
+    /// Mark the subresources of `range` as initialized, returning the
+    /// ones that were not, with all the aspects of the texture.
+    pub(crate) fn take_uninitialized(
+        &self,
+        range: &hal::image::SubresourceRange,
+    ) -> Vec<hal::image::SubresourceRange> {
+        let mut uninitialized = self.uninitialized.lock();
+        let mut taken = Vec::new();
+        for level in range.levels.clone() {
+            let layer_ranges = match uninitialized.get_mut(level as usize) {
+                Some(layer_ranges) => layer_ranges.take(range.layers.clone()),
+                None => continue,
+            };
+            taken.extend(layer_ranges.into_iter().map(|layers| hal::image::SubresourceRange {
+                aspects: self.full_range.aspects,
+                levels: level .. level + 1,
+                layers,
+            }));
+        }
+        taken
+    }
+}
+
 impl<B: hal::Backend> Borrow<RefCount> for Texture<B> {
     fn borrow(&self) -> &RefCount {
         &self.life_guard.ref_count
@@ -253,6 +522,20 @@ impl Default for TextureAspect {
     }
 }
 
+/// A range of texture subresources, used by `command_encoder_clear_texture`.
+///
+/// A `level_count` or `array_layer_count` of zero means all the levels
+/// or layers till the end of the texture.
+#[repr(C)]
+#[derive(Clone, Debug)]
+pub struct TextureSubresourceRange {
+    pub aspect: TextureAspect,
+    pub base_mip_level: u32,
+    pub level_count: u32,
+    pub base_array_layer: u32,
+    pub array_layer_count: u32,
+}
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
 pub enum TextureViewDimension {
@@ -286,7 +569,6 @@ pub struct TextureView<B: hal::Backend> {
     pub(crate) samples: hal::image::NumSamples,
     pub(crate) range: hal::image::SubresourceRange,
     pub(crate) is_owned_by_swap_chain: bool,
-    #[cfg_attr(not(not(feature = "remote")), allow(dead_code))]
     pub(crate) life_guard: LifeGuard,
 }
 
@@ -362,4 +644,23 @@ pub struct SamplerDescriptor {
 #[derive(Debug)]
 pub struct Sampler<B: hal::Backend> {
     pub(crate) raw: B::Sampler,
+    pub(crate) device_id: Stored<DeviceId>,
+    pub(crate) life_guard: LifeGuard,
+}
+
+#[cfg(test)]
+mod test {
+    use super::UninitializedRanges;
+
+    #[test]
+    fn take_uninitialized() {
+        let mut ranges = UninitializedRanges::new(0 .. 16u64);
+        assert_eq!(ranges.take(4 .. 8), vec![4 .. 8]);
+        assert_eq!(ranges.0, vec![0 .. 4, 8 .. 16]);
+        assert_eq!(ranges.take(2 .. 12), vec![2 .. 4, 8 .. 12]);
+        assert_eq!(ranges.0, vec![0 .. 2, 12 .. 16]);
+        assert_eq!(ranges.take(4 .. 8), vec![]);
+        assert_eq!(ranges.take(0 .. 16), vec![0 .. 2, 12 .. 16]);
+        assert!(ranges.0.is_empty());
+    }
 }
@@ -2,8 +2,9 @@ use crate::{
     conv,
     device::all_image_stages,
     gfx_select,
-    hub::{GfxBackend, Token},
+    hub::{GfxBackend, Global, Storage, Token, GLOBAL},
     resource,
+    AdapterId,
     DeviceId,
     Extent3d,
     Stored,
@@ -12,22 +13,30 @@ use crate::{
     TextureId,
     TextureViewId,
 };
-#[cfg(not(feature = "remote"))]
-use crate::hub::GLOBAL;
 
-use hal::{self, Device as _, Swapchain as _};
+use hal::{self, Device as _, Surface as _, Swapchain as _};
 use log::{trace, warn};
 use parking_lot::Mutex;
+use rendy_memory::{Block, MemoryBlock};
 
 use std::{
     iter,
     mem,
+    ptr,
     sync::atomic::{AtomicBool, Ordering},
+    time::Instant,
 };
 
 pub type SwapImageEpoch = u64;
 
 const FRAME_TIMEOUT_MS: u64 = 1000;
+/// Formats listed for surfaces that accept any format.
+const FALLBACK_SURFACE_FORMATS: [resource::TextureFormat; 4] = [
+    resource::TextureFormat::Bgra8UnormSrgb,
+    resource::TextureFormat::Bgra8Unorm,
+    resource::TextureFormat::Rgba8UnormSrgb,
+    resource::TextureFormat::Rgba8Unorm,
+];
 
 #[derive(Debug)]
 pub(crate) struct SwapChainLink<E> {
@@ -56,37 +65,223 @@ pub(crate) struct Frame<B: hal::Backend> {
     pub comb: hal::command::CommandBuffer<B, hal::General, hal::command::MultiShot>,
 }
 
+/// Receives the contents of a presented headless frame, laid out in rows
+/// of `bytes_per_row` bytes. The data is only valid during the call.
+pub type SwapChainFrameCallback =
+    extern "C" fn(data: *const u8, size: usize, bytes_per_row: u32, userdata: *mut u8);
+
+/// The presentation target of a swap chain that isn't attached to a surface.
+///
+/// Frames are acquired in a round-robin order, and copied into the readback
+/// buffer on presentation, to be handed to the callback.
+#[derive(Debug)]
+pub(crate) struct Headless<B: hal::Backend> {
+    pub next_image: hal::SwapImageIndex,
+    pub readback: B::Buffer,
+    pub memory: MemoryBlock<B>,
+    pub bytes_per_row: u32,
+    pub callback: SwapChainFrameCallback,
+    pub userdata: *mut u8,
+}
+
+unsafe impl<B: hal::Backend> Send for Headless<B> {}
+unsafe impl<B: hal::Backend> Sync for Headless<B> {}
+
 //TODO: does it need a ref-counted lifetime?
 #[derive(Debug)]
 pub struct SwapChain<B: hal::Backend> {
     //Note: it's only an option because we may need to move it out
     // and then put a new swapchain back in.
     pub(crate) raw: Option<B::Swapchain>,
-    pub(crate) surface_id: Stored<SurfaceId>,
+    pub(crate) surface_id: Option<Stored<SurfaceId>>,
     pub(crate) device_id: Stored<DeviceId>,
     pub(crate) desc: SwapChainDescriptor,
     pub(crate) frames: Vec<Frame<B>>,
     pub(crate) acquired: Vec<hal::SwapImageIndex>,
+    /// An image acquired from the presentation engine, which frame
+    /// wasn't ready in time, with its acquisition status. It's handed
+    /// out by the next acquisition.
+    pub(crate) pending_image: Option<(hal::SwapImageIndex, SwapChainStatus)>,
     pub(crate) sem_available: B::Semaphore,
-    #[cfg_attr(feature = "remote", allow(dead_code))] //TODO: remove
     pub(crate) command_pool: hal::CommandPool<B, hal::General>,
+    pub(crate) headless: Option<Headless<B>>,
+}
+
+impl<B: hal::Backend> SwapChain<B> {
+    /// Pick the next frame of the ring, if the swap chain is headless, or the
+    /// image left over by the last acquisition.
+    fn next_ready_image(&mut self) -> Option<(hal::SwapImageIndex, SwapChainStatus)> {
+        match self.headless {
+            Some(ref headless) => Some((headless.next_image, SwapChainStatus::Success)),
+            None => self.pending_image.take(),
+        }
+    }
+
+    /// Start tracking an acquired image as a frame, waiting for its previous
+    /// presentation to finish for at most `timeout_ns` nanoseconds.
+    ///
+    /// The `status` of the image acquisition is kept for the next one
+    /// if the frame isn't ready in time.
+    fn acquire_frame(
+        &mut self,
+        device: &B::Device,
+        texture_guard: &Storage<resource::Texture<B>, TextureId>,
+        image_index: hal::SwapImageIndex,
+        status: SwapChainStatus,
+        timeout_ns: u64,
+    ) -> Result<SwapChainOutput, SwapChainStatus> {
+        let frame = &mut self.frames[image_index as usize];
+        match unsafe { device.wait_for_fence(&frame.fence, timeout_ns) } {
+            Ok(true) => {}
+            Ok(false) => {
+                // the image is already acquired from the presentation engine
+                if self.headless.is_none() {
+                    self.pending_image = Some((image_index, status));
+                }
+                return Err(SwapChainStatus::Timeout);
+            }
+            Err(hal::device::OomOrDeviceLost::DeviceLost(_)) => return Err(SwapChainStatus::Lost),
+            Err(hal::device::OomOrDeviceLost::OutOfMemory(e)) => {
+                panic!(
+                    "Unable to wait for the frame (image {}): {}",
+                    image_index, e
+                )
+            }
+        }
+        self.acquired.push(image_index);
+        let frame_count = self.frames.len() as hal::SwapImageIndex;
+        if let Some(ref mut headless) = self.headless {
+            headless.next_image = (image_index + 1) % frame_count;
+        }
+
+        let frame = &mut self.frames[image_index as usize];
+        mem::swap(&mut frame.sem_available, &mut self.sem_available);
+        frame.need_waiting.store(true, Ordering::Release);
+
+        let frame_epoch = texture_guard[frame.texture_id.value]
+            .placement
+            .as_swap_chain()
+            .bump_epoch();
+
+        assert_eq!(
+            frame.acquired_epoch, None,
+            "Last swapchain output hasn't been presented"
+        );
+        frame.acquired_epoch = Some(frame_epoch);
+
+        Ok(SwapChainOutput {
+            texture_id: frame.texture_id.value,
+            view_id: frame.view_id.value,
+        })
+    }
 }
 
+/// The way frames are handed to the presentation engine.
+///
+/// `Vsync` is always supported. When the requested mode isn't supported
+/// by the surface, the swap chain falls back to another one:
+///   - `NoVsync` falls back to `Mailbox`, then to `Vsync`
+///   - `Mailbox` and `Relaxed` fall back to `Vsync`
 #[repr(C)]
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum PresentMode {
+    /// Present immediately, the frames may tear.
     NoVsync = 0,
+    /// Wait for the vertical blank, presenting the frames in order.
     Vsync = 1,
+    /// Wait for the vertical blank, replacing the queued frame with the latest one.
+    Mailbox = 2,
+    /// Like `Vsync`, but a late frame is presented immediately.
+    Relaxed = 3,
+}
+
+impl PresentMode {
+    fn to_hal(self) -> hal::PresentMode {
+        match self {
+            PresentMode::NoVsync => hal::PresentMode::Immediate,
+            PresentMode::Vsync => hal::PresentMode::Fifo,
+            PresentMode::Mailbox => hal::PresentMode::Mailbox,
+            PresentMode::Relaxed => hal::PresentMode::Relaxed,
+        }
+    }
+
+    fn from_hal(mode: hal::PresentMode) -> Self {
+        match mode {
+            hal::PresentMode::Immediate => PresentMode::NoVsync,
+            hal::PresentMode::Fifo => PresentMode::Vsync,
+            hal::PresentMode::Mailbox => PresentMode::Mailbox,
+            hal::PresentMode::Relaxed => PresentMode::Relaxed,
+        }
+    }
+
+    /// Apply the fallback rules to find a mode in the `supported` list.
+    pub(crate) fn fallback(self, supported: &[hal::PresentMode]) -> Self {
+        let chain: &[PresentMode] = match self {
+            PresentMode::NoVsync => &[PresentMode::NoVsync, PresentMode::Mailbox],
+            PresentMode::Vsync => &[],
+            PresentMode::Mailbox => &[PresentMode::Mailbox],
+            PresentMode::Relaxed => &[PresentMode::Relaxed],
+        };
+        chain
+            .iter()
+            .cloned()
+            .find(|mode| supported.contains(&mode.to_hal()))
+            .unwrap_or(PresentMode::Vsync)
+    }
+}
+
+/// Swap chain configurations supported by a surface on an adapter.
+///
+/// The format and present mode lists are owned by the structure,
+/// and released when it's dropped (or passed to `wgpu_surface_capabilities_free`).
+#[repr(C)]
+#[derive(Debug)]
+pub struct SurfaceCapabilities {
+    pub formats: *const resource::TextureFormat,
+    pub formats_length: usize,
+    pub present_modes: *const PresentMode,
+    pub present_modes_length: usize,
+    pub usage: resource::TextureUsage,
+    pub min_image_count: u32,
+    pub max_image_count: u32,
+    /// The current size of the surface, or zero if it's determined by the swap chain.
+    pub current_extent: Extent3d,
+    pub min_extent: Extent3d,
+    pub max_extent: Extent3d,
+}
+
+impl Drop for SurfaceCapabilities {
+    fn drop(&mut self) {
+        unsafe {
+            drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+                self.formats as *mut resource::TextureFormat,
+                self.formats_length,
+            )));
+            drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+                self.present_modes as *mut PresentMode,
+                self.present_modes_length,
+            )));
+        }
+    }
 }
 
 #[repr(C)]
 #[derive(Clone, Debug)]
 pub struct SwapChainDescriptor {
+    /// Besides `OUTPUT_ATTACHMENT`, the images can be used for copies
+    /// and sampling, if the surface supports it (see `SurfaceCapabilities::usage`).
     pub usage: resource::TextureUsage,
     pub format: resource::TextureFormat,
     pub width: u32,
     pub height: u32,
     pub present_mode: PresentMode,
+    /// Desired number of images, clamped to the range supported by the surface.
+    /// Zero selects the minimum.
+    pub image_count: u32,
+    /// Maximum number of images that can be acquired and not yet presented.
+    /// It's clamped to the number of images the surface allows acquiring at once,
+    /// which grows with `image_count`. Zero is treated as one.
+    pub max_frame_latency: u32,
 }
 
 impl SwapChainDescriptor {
@@ -97,13 +292,9 @@ impl SwapChainDescriptor {
             conv::map_texture_format(self.format),
             num_frames,
         );
-        //TODO: check for supported
         config.image_usage = conv::map_texture_usage(self.usage, hal::format::Aspects::COLOR);
         config.composite_alpha = hal::window::CompositeAlpha::OPAQUE;
-        config.present_mode = match self.present_mode {
-            PresentMode::NoVsync => hal::PresentMode::Immediate,
-            PresentMode::Vsync => hal::PresentMode::Fifo,
-        };
+        config.present_mode = self.present_mode.to_hal();
         config
     }
 
@@ -131,142 +322,225 @@ pub struct SwapChainOutput {
     pub view_id: TextureViewId,
 }
 
+/// The outcome of a swap chain image acquisition.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SwapChainStatus {
+    Success = 0,
+    /// The image was acquired, but the swap chain doesn't match
+    /// the surface exactly anymore and should be re-created.
+    Suboptimal = 1,
+    /// No image became available before the timeout, or the maximum
+    /// frame latency has been reached.
+    Timeout = 2,
+    /// The swap chain has to be re-created to match the surface.
+    Outdated = 3,
+    /// The surface or the device was lost.
+    Lost = 4,
+}
+
 pub fn swap_chain_get_next_texture<B: GfxBackend>(
-    swap_chain_id: SwapChainId
+    global: &Global,
+    swap_chain_id: SwapChainId,
 ) -> SwapChainOutput {
-    let hub = B::hub();
+    let hub = B::hub(global);
     let mut token = Token::root();
 
-    #[cfg(not(feature = "remote"))]
-    let (mut surface_guard, mut token) = GLOBAL.surfaces.write(&mut token);
+    let (mut surface_guard, mut token) = global.surfaces.write(&mut token);
     let (device_guard, mut token) = hub.devices.read(&mut token);
     let (mut swap_chain_guard, _) = hub.swap_chains.write(&mut token);
     let swap_chain = &mut swap_chain_guard[swap_chain_id];
     let device = &device_guard[swap_chain.device_id.value];
 
-    let image_index = unsafe {
-        swap_chain
-            .raw
-            .as_mut()
-            .unwrap()
-            .acquire_image(!0, Some(&swap_chain.sem_available), None)
+    let image_index = match swap_chain.next_ready_image() {
+        Some(ready) => Ok(ready),
+        None => unsafe {
+            swap_chain
+                .raw
+                .as_mut()
+                .unwrap()
+                .acquire_image(!0, Some(&swap_chain.sem_available), None)
+                .map(|(index, suboptimal)| match suboptimal {
+                    Some(_) => (index, SwapChainStatus::Suboptimal),
+                    None => (index, SwapChainStatus::Success),
+                })
+        },
     };
 
-    #[cfg(not(feature = "remote"))]
-    {
-        if image_index.is_err() {
-            warn!("acquire_image failed, re-creating");
-            //TODO: remove this once gfx-rs stops destroying the old swapchain
-            device.raw.wait_idle().unwrap();
-            let (mut texture_guard, mut token) = hub.textures.write(&mut token);
-            let (mut texture_view_guard, _) = hub.texture_views.write(&mut token);
-            let mut trackers = device.trackers.lock();
-
-            let old_raw = swap_chain.raw.take();
-            let config = swap_chain.desc.to_hal(swap_chain.frames.len() as u32);
-            let surface = &mut surface_guard[swap_chain.surface_id.value];
-
-            let (raw, images) = unsafe {
-                let suf = B::get_surface_mut(surface);
+    if image_index.is_err() {
+        warn!("acquire_image failed, re-creating");
+        //TODO: remove this once gfx-rs stops destroying the old swapchain
+        device.raw.wait_idle().unwrap();
+        let (mut texture_guard, mut token) = hub.textures.write(&mut token);
+        let (mut texture_view_guard, _) = hub.texture_views.write(&mut token);
+        let mut trackers = device.trackers.lock();
+
+        let old_raw = swap_chain.raw.take();
+        let config = swap_chain.desc.to_hal(swap_chain.frames.len() as u32);
+        let surface = &mut surface_guard[swap_chain.surface_id.as_ref().unwrap().value];
+
+        let (raw, images) = unsafe {
+            let suf = B::get_surface_mut(surface);
+            device.raw.create_swapchain(suf, config, old_raw).unwrap()
+        };
+        swap_chain.raw = Some(raw);
+        for (frame, image) in swap_chain.frames.iter_mut().zip(images) {
+            let texture = &mut texture_guard[frame.texture_id.value];
+            let view_raw = unsafe {
                 device
                     .raw
-                    .create_swapchain(suf, config, old_raw)
+                    .create_image_view(
+                        &image,
+                        hal::image::ViewKind::D2,
+                        conv::map_texture_format(texture.format),
+                        hal::format::Swizzle::NO,
+                        texture.full_range.clone(),
+                    )
                     .unwrap()
             };
-            swap_chain.raw = Some(raw);
-            for (frame, image) in swap_chain.frames.iter_mut().zip(images) {
-                let texture = &mut texture_guard[frame.texture_id.value];
-                let view_raw = unsafe {
-                    device
-                        .raw
-                        .create_image_view(
-                            &image,
-                            hal::image::ViewKind::D2,
-                            conv::map_texture_format(texture.format),
-                            hal::format::Swizzle::NO,
-                            texture.full_range.clone(),
-                        )
-                        .unwrap()
-                };
-                texture.raw = image;
-                trackers.textures.reset(
-                    frame.texture_id.value,
-                    texture.full_range.clone(),
-                    resource::TextureUsage::UNINITIALIZED,
-                );
-                let old_view = mem::replace(&mut texture_view_guard[frame.view_id.value].raw, view_raw);
-                unsafe {
-                    device.raw.destroy_image_view(old_view);
-                }
+            texture.raw = image;
+            trackers.textures.reset(
+                frame.texture_id.value,
+                texture.full_range.clone(),
+                resource::TextureUsage::UNINITIALIZED,
+            );
+            let old_view = mem::replace(&mut texture_view_guard[frame.view_id.value].raw, view_raw);
+            unsafe {
+                device.raw.destroy_image_view(old_view);
             }
         }
     }
 
-    let image_index = match image_index {
-        Ok((index, suboptimal)) => {
-            if suboptimal.is_some() {
+    let (image_index, status) = match image_index {
+        Ok((index, status)) => {
+            if status == SwapChainStatus::Suboptimal {
                 warn!("acquire_image: sub-optimal");
             }
-            index
+            (index, status)
         }
         Err(_) => unsafe {
-            swap_chain
+            let index = swap_chain
                 .raw
                 .as_mut()
                 .unwrap()
                 .acquire_image(!0, Some(&swap_chain.sem_available), None)
                 .unwrap()
-                .0
+                .0;
+            (index, SwapChainStatus::Success)
         },
     };
 
-    assert_ne!(
-        swap_chain.acquired.len(),
-        swap_chain.acquired.capacity(),
-        "Unable to acquire any more swap chain images before presenting"
+    assert!(
+        swap_chain.acquired.len() < swap_chain.desc.max_frame_latency as usize,
+        "Unable to acquire more than {} swap chain images before presenting",
+        swap_chain.desc.max_frame_latency
     );
-    swap_chain.acquired.push(image_index);
 
-    let frame = &mut swap_chain.frames[image_index as usize];
-    let status = unsafe {
-        device
-            .raw
-            .wait_for_fence(&frame.fence, FRAME_TIMEOUT_MS * 1_000_000)
+    let (texture_guard, _) = hub.textures.read(&mut token);
+    swap_chain
+        .acquire_frame(
+            &device.raw,
+            &*texture_guard,
+            image_index,
+            status,
+            FRAME_TIMEOUT_MS * 1_000_000,
+        )
+        .unwrap_or_else(|status| {
+            panic!(
+                "Unable to acquire the frame (image {}): {:?}",
+                image_index, status
+            )
+        })
+}
+
+#[no_mangle]
+pub extern "C" fn wgpu_swap_chain_get_next_texture(swap_chain_id: SwapChainId) -> SwapChainOutput {
+    gfx_select!(swap_chain_id => swap_chain_get_next_texture(&*GLOBAL, swap_chain_id))
+}
+
+/// Acquire the next swap chain image, waiting at most `timeout_ns` nanoseconds.
+///
+/// Unlike `swap_chain_get_next_texture`, this doesn't re-create the swap chain
+/// on errors, leaving it to the caller. The output is only returned for the
+/// `Success` and `Suboptimal` statuses.
+pub fn swap_chain_acquire_next_texture<B: GfxBackend>(
+    global: &Global,
+    swap_chain_id: SwapChainId,
+    timeout_ns: u64,
+) -> (SwapChainStatus, Option<SwapChainOutput>) {
+    let hub = B::hub(global);
+    let mut token = Token::root();
+
+    let (device_guard, mut token) = hub.devices.read(&mut token);
+    let (mut swap_chain_guard, mut token) = hub.swap_chains.write(&mut token);
+    let swap_chain = &mut swap_chain_guard[swap_chain_id];
+    let device = &device_guard[swap_chain.device_id.value];
+
+    if swap_chain.acquired.len() >= swap_chain.desc.max_frame_latency as usize {
+        return (SwapChainStatus::Timeout, None);
+    }
+    let start = Instant::now();
+    let result = match swap_chain.next_ready_image() {
+        Some(ready) => Ok(ready),
+        None => unsafe {
+            swap_chain
+                .raw
+                .as_mut()
+                .unwrap()
+                .acquire_image(timeout_ns, Some(&swap_chain.sem_available), None)
+                .map(|(index, suboptimal)| match suboptimal {
+                    Some(_) => (index, SwapChainStatus::Suboptimal),
+                    None => (index, SwapChainStatus::Success),
+                })
+        },
+    };
+    let (image_index, status) = match result {
+        Ok(acquired) => acquired,
+        Err(hal::window::AcquireError::NotReady) | Err(hal::window::AcquireError::Timeout) => {
+            return (SwapChainStatus::Timeout, None)
+        }
+        Err(hal::window::AcquireError::OutOfDate) => return (SwapChainStatus::Outdated, None),
+        Err(hal::window::AcquireError::SurfaceLost(_)) |
+        Err(hal::window::AcquireError::DeviceLost(_)) => return (SwapChainStatus::Lost, None),
+        Err(hal::window::AcquireError::OutOfMemory(e)) => {
+            panic!("Unable to acquire a swap chain image: {}", e)
+        }
     };
-    assert_eq!(
-        status,
-        Ok(true),
-        "GPU got stuck on a frame (image {}) :(",
-        image_index
-    );
-    mem::swap(&mut frame.sem_available, &mut swap_chain.sem_available);
-    frame.need_waiting.store(true, Ordering::Release);
 
+    // the frame is waited for with the rest of the timeout
+    let elapsed = start.elapsed();
+    let elapsed_ns = elapsed.as_secs() * 1_000_000_000 + elapsed.subsec_nanos() as u64;
     let (texture_guard, _) = hub.textures.read(&mut token);
-    let frame_epoch = texture_guard[frame.texture_id.value]
-        .placement
-        .as_swap_chain()
-        .bump_epoch();
-
-    assert_eq!(
-        frame.acquired_epoch, None,
-        "Last swapchain output hasn't been presented"
-    );
-    frame.acquired_epoch = Some(frame_epoch);
-
-    SwapChainOutput {
-        texture_id: frame.texture_id.value,
-        view_id: frame.view_id.value,
+    match swap_chain.acquire_frame(
+        &device.raw,
+        &*texture_guard,
+        image_index,
+        status,
+        timeout_ns.saturating_sub(elapsed_ns),
+    ) {
+        Ok(output) => (status, Some(output)),
+        Err(status) => (status, None),
     }
 }
 
+/// Acquire the next swap chain image, writing it into `output`
+/// if the returned status is `Success` or `Suboptimal`.
 #[no_mangle]
-pub extern "C" fn wgpu_swap_chain_get_next_texture(swap_chain_id: SwapChainId) -> SwapChainOutput {
-    gfx_select!(swap_chain_id => swap_chain_get_next_texture(swap_chain_id))
+pub extern "C" fn wgpu_swap_chain_acquire_next_texture(
+    swap_chain_id: SwapChainId,
+    timeout_ns: u64,
+    output: &mut SwapChainOutput,
+) -> SwapChainStatus {
+    let (status, acquired) = gfx_select!(swap_chain_id =>
+        swap_chain_acquire_next_texture(&*GLOBAL, swap_chain_id, timeout_ns));
+    if let Some(acquired) = acquired {
+        *output = acquired;
+    }
+    status
 }
 
-pub fn swap_chain_present<B: GfxBackend>(swap_chain_id: SwapChainId) {
-    let hub = B::hub();
+pub fn swap_chain_present<B: GfxBackend>(global: &Global, swap_chain_id: SwapChainId) {
+    let hub = B::hub(global);
     let mut token = Token::root();
 
     let (mut device_guard, mut token) = hub.devices.write(&mut token);
@@ -292,7 +566,17 @@ pub fn swap_chain_present<B: GfxBackend>(swap_chain_id: SwapChainId) {
     let texture = &texture_guard[frame.texture_id.value];
     texture.placement.as_swap_chain().bump_epoch();
 
-    //TODO: support for swapchain being sampled or read by the shader?
+    // headless frames are copied into the readback buffer instead
+    let present_state = match swap_chain.headless {
+        Some(_) => (
+            hal::image::Access::TRANSFER_READ,
+            hal::image::Layout::TransferSrcOptimal,
+        ),
+        None => (
+            hal::image::Access::COLOR_ATTACHMENT_WRITE,
+            hal::image::Layout::Present,
+        ),
+    };
 
     trace!("transit {:?} to present", frame.texture_id.value);
     let mut trackers = device.trackers.lock();
@@ -306,46 +590,208 @@ pub fn swap_chain_present<B: GfxBackend>(swap_chain_id: SwapChainId) {
         )
         .map(|pending| hal::memory::Barrier::Image {
             states: conv::map_texture_state(pending.usage.start, hal::format::Aspects::COLOR)
-                .. (
-                    hal::image::Access::COLOR_ATTACHMENT_WRITE,
-                    hal::image::Layout::Present,
-                ),
+                .. present_state,
             target: &texture.raw,
             families: None,
             range: pending.selector,
         });
 
-    let err = unsafe {
+    let headless = match swap_chain.headless {
+        Some(ref mut headless) => headless,
+        None => {
+            let err = unsafe {
+                frame.comb.begin(false);
+                frame.comb.pipeline_barrier(
+                    all_image_stages() .. hal::pso::PipelineStage::COLOR_ATTACHMENT_OUTPUT,
+                    hal::memory::Dependencies::empty(),
+                    barriers,
+                );
+                frame.comb.finish();
+
+                // now prepare the GPU submission
+                let submission = hal::Submission {
+                    command_buffers: iter::once(&frame.comb),
+                    wait_semaphores: None,
+                    signal_semaphores: Some(&frame.sem_present),
+                };
+
+                device.raw.reset_fence(&frame.fence).unwrap();
+                let queue = &mut device.queue_group.queues[0];
+                queue.submit(submission, Some(&frame.fence));
+                queue.present(
+                    iter::once((swap_chain.raw.as_ref().unwrap(), image_index)),
+                    iter::once(&frame.sem_present),
+                )
+            };
+
+            if let Err(e) = err {
+                warn!("present failed: {:?}", e);
+            }
+            return;
+        }
+    };
+
+    let size = headless.bytes_per_row as hal::buffer::Offset *
+        swap_chain.desc.height as hal::buffer::Offset;
+    let region = hal::command::BufferImageCopy {
+        buffer_offset: 0,
+        buffer_width: 0,
+        buffer_height: 0,
+        image_layers: hal::image::SubresourceLayers {
+            aspects: hal::format::Aspects::COLOR,
+            level: 0,
+            layers: 0 .. 1,
+        },
+        image_offset: hal::image::Offset::ZERO,
+        image_extent: texture.kind.extent(),
+    };
+    let host_barrier = hal::memory::Barrier::Buffer {
+        states: hal::buffer::Access::TRANSFER_WRITE .. hal::buffer::Access::HOST_READ,
+        target: &headless.readback,
+        families: None,
+        range: None .. None,
+    };
+    unsafe {
         frame.comb.begin(false);
         frame.comb.pipeline_barrier(
-            all_image_stages() .. hal::pso::PipelineStage::COLOR_ATTACHMENT_OUTPUT,
+            all_image_stages() .. hal::pso::PipelineStage::TRANSFER,
             hal::memory::Dependencies::empty(),
             barriers,
         );
+        frame.comb.copy_image_to_buffer(
+            &texture.raw,
+            hal::image::Layout::TransferSrcOptimal,
+            &headless.readback,
+            iter::once(region),
+        );
+        frame.comb.pipeline_barrier(
+            hal::pso::PipelineStage::TRANSFER .. hal::pso::PipelineStage::HOST,
+            hal::memory::Dependencies::empty(),
+            iter::once(host_barrier),
+        );
         frame.comb.finish();
 
-        // now prepare the GPU submission
         let submission = hal::Submission {
             command_buffers: iter::once(&frame.comb),
             wait_semaphores: None,
-            signal_semaphores: Some(&frame.sem_present),
+            signal_semaphores: None,
         };
-
         device.raw.reset_fence(&frame.fence).unwrap();
-        let queue = &mut device.queue_group.queues[0];
-        queue.submit(submission, Some(&frame.fence));
-        queue.present(
-            iter::once((swap_chain.raw.as_ref().unwrap(), image_index)),
-            iter::once(&frame.sem_present),
-        )
-    };
+        device.queue_group.queues[0]
+            .submit::<_, _, B::Semaphore, _, _>(submission, Some(&frame.fence));
+    }
 
-    if let Err(e) = err {
-        warn!("present failed: {:?}", e);
+    // the contents are passed before returning, so wait for as long as it takes:
+    // software rasterizers may need more than a frame's time
+    match unsafe { device.raw.wait_for_fence(&frame.fence, !0) } {
+        Ok(true) => {}
+        status => {
+            warn!(
+                "Unable to read the frame (image {}) back: {:?}",
+                image_index, status
+            );
+            return;
+        }
     }
+    let is_coherent = headless
+        .memory
+        .properties()
+        .contains(hal::memory::Properties::COHERENT);
+    let (ptr, mapped_range) = {
+        let mapped = headless.memory.map(&device.raw, 0 .. size).unwrap();
+        (mapped.ptr(), mapped.range())
+    };
+    let mut data = vec![0u8; size as usize];
+    unsafe {
+        if !is_coherent {
+            device
+                .raw
+                .invalidate_mapped_memory_ranges(iter::once((headless.memory.memory(), mapped_range)))
+                .unwrap();
+        }
+        ptr::copy_nonoverlapping(ptr.as_ptr(), data.as_mut_ptr(), data.len());
+    }
+    headless.memory.unmap(&device.raw);
+
+    // fire the callback with nothing locked
+    let (callback, userdata, bytes_per_row) =
+        (headless.callback, headless.userdata, headless.bytes_per_row);
+    drop(trackers);
+    drop(texture_guard);
+    drop(swap_chain_guard);
+    drop(device_guard);
+    callback(data.as_ptr(), data.len(), bytes_per_row, userdata);
 }
 
 #[no_mangle]
 pub extern "C" fn wgpu_swap_chain_present(swap_chain_id: SwapChainId) {
-    gfx_select!(swap_chain_id => swap_chain_present(swap_chain_id))
+    gfx_select!(swap_chain_id => swap_chain_present(&*GLOBAL, swap_chain_id))
+}
+
+pub fn surface_get_capabilities<B: GfxBackend>(
+    global: &Global,
+    surface_id: SurfaceId,
+    adapter_id: AdapterId,
+) -> SurfaceCapabilities {
+    let hub = B::hub(global);
+    let mut token = Token::root();
+    let (mut surface_guard, mut token) = global.surfaces.write(&mut token);
+    let (adapter_guard, _) = hub.adapters.read(&mut token);
+
+    let suf = B::get_surface_mut(&mut surface_guard[surface_id]);
+    let (caps, formats, present_modes) =
+        suf.compatibility(&adapter_guard[adapter_id].raw.physical_device);
+
+    // `None` means that the surface accepts any format
+    let formats = match formats {
+        Some(formats) => formats
+            .into_iter()
+            .filter_map(conv::map_hal_texture_format)
+            .collect::<Vec<_>>(),
+        None => FALLBACK_SURFACE_FORMATS.to_vec(),
+    };
+    let present_modes = present_modes
+        .into_iter()
+        .map(PresentMode::from_hal)
+        .collect::<Vec<_>>();
+    let map_extent = |extent: hal::window::Extent2D| Extent3d {
+        width: extent.width,
+        height: extent.height,
+        depth: 1,
+    };
+    let current_extent = caps.current_extent.map_or(
+        Extent3d {
+            width: 0,
+            height: 0,
+            depth: 1,
+        },
+        map_extent,
+    );
+
+    let (formats_length, present_modes_length) = (formats.len(), present_modes.len());
+    SurfaceCapabilities {
+        formats: Box::into_raw(formats.into_boxed_slice()) as *const _,
+        formats_length,
+        present_modes: Box::into_raw(present_modes.into_boxed_slice()) as *const _,
+        present_modes_length,
+        usage: conv::map_hal_texture_usage(caps.usage),
+        min_image_count: *caps.image_count.start(),
+        max_image_count: *caps.image_count.end(),
+        current_extent,
+        min_extent: map_extent(*caps.extents.start()),
+        max_extent: map_extent(*caps.extents.end()),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wgpu_surface_get_capabilities(
+    surface_id: SurfaceId,
+    adapter_id: AdapterId,
+) -> SurfaceCapabilities {
+    gfx_select!(adapter_id => surface_get_capabilities(&*GLOBAL, surface_id, adapter_id))
+}
+
+#[no_mangle]
+pub extern "C" fn wgpu_surface_capabilities_free(capabilities: SurfaceCapabilities) {
+    drop(capabilities);
 }
@@ -96,6 +96,16 @@ impl ResourceState for BufferState {
         Ok(())
     }
 
+    fn transfer(&self, id: Self::Id, output: &mut Vec<PendingTransition<Self>>) {
+        if !self.last.is_empty() {
+            output.push(PendingTransition {
+                id,
+                selector: (),
+                usage: self.last .. self.last,
+            });
+        }
+    }
+
     fn optimize(&mut self) {}
 }
 
@@ -117,4 +127,17 @@ mod test {
         bs.change(id, (), BufferUsage::INDEX, None).unwrap();
         assert_eq!(bs.last, BufferUsage::VERTEX | BufferUsage::INDEX);
     }
+
+    #[test]
+    fn transfer() {
+        let id = TypedId::zip(0, 0, Backend::Empty);
+        let mut transitions = Vec::new();
+        BufferState::default().transfer(id, &mut transitions);
+        assert!(transitions.is_empty());
+
+        let bs = Unit::new(BufferUsage::UNIFORM);
+        bs.transfer(id, &mut transitions);
+        assert_eq!(transitions.len(), 1);
+        assert_eq!(transitions[0].usage, BufferUsage::UNIFORM .. BufferUsage::UNIFORM);
+    }
 }
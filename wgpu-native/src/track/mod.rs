@@ -2,7 +2,17 @@ mod buffer;
 mod range;
 mod texture;
 
-use crate::{hub::Storage, Backend, BindGroupId, Epoch, Index, RefCount, TextureViewId, TypedId};
+use crate::{
+    device::QueueIndex,
+    hub::Storage,
+    Backend,
+    BindGroupId,
+    Epoch,
+    Index,
+    RefCount,
+    TextureViewId,
+    TypedId,
+};
 
 use hal::backend::FastHashMap;
 
@@ -116,6 +126,12 @@ pub trait ResourceState: Clone + Default {
         output: Option<&mut Vec<PendingTransition<Self>>>,
     ) -> Result<(), PendingTransition<Self>>;
 
+    /// Produce transitions that keep the last usage of all the sub-resources.
+    ///
+    /// These are used to transfer the ownership of a resource between
+    /// queue families, which requires a barrier even if the usage stays.
+    fn transfer(&self, id: Self::Id, output: &mut Vec<PendingTransition<Self>>);
+
     /// Try to optimize the internal representation.
     fn optimize(&mut self);
 }
@@ -146,6 +162,9 @@ pub struct ResourceTracker<S: ResourceState> {
     map: FastHashMap<Index, Resource<S>>,
     /// Temporary storage for collecting transitions.
     temp: Vec<PendingTransition<S>>,
+    /// Queues that the resources were last submitted to.
+    /// Only maintained by the device trackers.
+    owners: FastHashMap<Index, QueueIndex>,
     /// The backend variant for all the tracked resources.
     backend: Backend,
 }
@@ -156,6 +175,7 @@ impl<S: ResourceState> ResourceTracker<S> {
         ResourceTracker {
             map: FastHashMap::default(),
             temp: Vec::new(),
+            owners: FastHashMap::default(),
             backend,
         }
     }
@@ -164,6 +184,7 @@ impl<S: ResourceState> ResourceTracker<S> {
     pub fn remove(&mut self, id: S::Id) -> bool {
         let (index, epoch, backend) = id.unzip();
         debug_assert_eq!(backend, self.backend);
+        self.owners.remove(&index);
         match self.map.remove(&index) {
             Some(resource) => {
                 assert_eq!(resource.epoch, epoch);
@@ -180,6 +201,19 @@ impl<S: ResourceState> ResourceTracker<S> {
         }
     }
 
+    /// Check if a resource is tracked.
+    pub fn contains(&self, id: S::Id) -> bool {
+        let (index, epoch, backend) = id.unzip();
+        debug_assert_eq!(backend, self.backend);
+        match self.map.get(&index) {
+            Some(resource) => {
+                assert_eq!(resource.epoch, epoch);
+                true
+            }
+            None => false,
+        }
+    }
+
     /// Return an iterator over used resources keys.
     pub fn used<'a>(&'a self) -> impl 'a + Iterator<Item = S::Id> {
         let backend = self.backend;
@@ -191,6 +225,7 @@ impl<S: ResourceState> ResourceTracker<S> {
     /// Clear the tracked contents.
     fn clear(&mut self) {
         self.map.clear();
+        self.owners.clear();
     }
 
     /// Initialize a resource to be used.
@@ -224,7 +259,6 @@ impl<S: ResourceState> ResourceTracker<S> {
     }
 
     /// Resets a resource to the specified usage.
-    #[cfg(not(feature = "remote"))]
     pub fn reset(
         &mut self,
         id: S::Id,
@@ -355,6 +389,32 @@ impl<S: ResourceState> ResourceTracker<S> {
         self.temp.drain(..)
     }
 
+    /// Make `queue` the owner of all the resources used by `other`.
+    ///
+    /// Returns the transitions keeping the current usage of the resources
+    /// previously owned by different queues, paired with these queues.
+    pub fn transfer_ownership(
+        &mut self,
+        other: &Self,
+        queue: QueueIndex,
+    ) -> Vec<(QueueIndex, PendingTransition<S>)> {
+        debug_assert_eq!(self.backend, other.backend);
+        let mut transfers = Vec::new();
+        for (&index, new) in other.map.iter() {
+            match self.owners.insert(index, queue) {
+                Some(old) if old != queue => {
+                    if let Some(resource) = self.map.get(&index) {
+                        let id = S::Id::zip(index, new.epoch, self.backend);
+                        resource.state.transfer(id, &mut self.temp);
+                        transfers.extend(self.temp.drain(..).map(|pending| (old, pending)));
+                    }
+                }
+                _ => {}
+            }
+        }
+        transfers
+    }
+
     /// Use a given resource provided by an `Id` with the specified usage.
     /// Combines storage access by 'Id' with the transition that extends
     /// the last read-only usage, if possible.
@@ -420,6 +480,8 @@ impl<I: Copy + Debug + TypedId> ResourceState for PhantomData<I> {
         Ok(())
     }
 
+    fn transfer(&self, _id: Self::Id, _output: &mut Vec<PendingTransition<Self>>) {}
+
     fn optimize(&mut self) {}
 }
 
@@ -53,6 +53,11 @@ impl<I: Copy + PartialOrd, T: Copy + PartialEq> RangedStates<I, T> {
         }
     }
 
+    /// Iterate over all the ranges with their values.
+    pub fn iter(&self) -> Iter<'_, (Range<I>, T)> {
+        self.ranges.iter()
+    }
+
     /// Merge the neighboring ranges together, where possible.
     pub fn coalesce(&mut self) {
         let mut num_removed = 0;
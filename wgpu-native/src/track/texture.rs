@@ -221,6 +221,32 @@ impl ResourceState for TextureState {
         Ok(())
     }
 
+    fn transfer(&self, id: Self::Id, output: &mut Vec<PendingTransition<Self>>) {
+        for (mip_id, mip) in self.mips.iter().enumerate() {
+            let level = mip_id as hal::image::Level;
+            for &(aspects, plane_states) in &[
+                (hal::format::Aspects::COLOR, &mip.color),
+                (hal::format::Aspects::DEPTH, &mip.depth),
+                (hal::format::Aspects::STENCIL, &mip.stencil),
+            ] {
+                for &(ref layers, unit) in plane_states.iter() {
+                    if unit.last == TextureUsage::UNINITIALIZED {
+                        continue;
+                    }
+                    output.push(PendingTransition {
+                        id,
+                        selector: hal::image::SubresourceRange {
+                            aspects,
+                            levels: level .. level + 1,
+                            layers: layers.clone(),
+                        },
+                        usage: unit.last .. unit.last,
+                    });
+                }
+            }
+        }
+    }
+
     fn optimize(&mut self) {
         for mip in self.mips.iter_mut() {
             mip.color.coalesce();
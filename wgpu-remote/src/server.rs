@@ -3,14 +3,25 @@ use crate::GlobalMessage;
 use ipc_channel::ipc::IpcReceiver;
 use wgn;
 
+use std::sync::Arc;
+
 #[derive(Debug)]
 pub struct Server {
     channel: IpcReceiver<GlobalMessage>,
+    global: Arc<wgn::Global>,
 }
 
 impl Server {
     pub(crate) fn new(channel: IpcReceiver<GlobalMessage>) -> Self {
-        Server { channel }
+        // the IDs are allocated by the client
+        let global = wgn::Global::new(
+            &wgn::InstanceDescriptor::default(),
+            wgn::IdentityMode::Remote,
+        );
+        Server {
+            channel,
+            global: Arc::new(global),
+        }
     }
 }
 
@@ -19,14 +30,14 @@ enum ControlFlow {
     Terminate,
 }
 
-fn process(message: GlobalMessage) -> ControlFlow {
+fn process(global: &Arc<wgn::Global>, message: GlobalMessage) -> ControlFlow {
     match message {
         GlobalMessage::RequestAdapter(ref desc, ref ids) => {
-            wgn::request_adapter(desc, ids);
+            wgn::request_adapter(global, desc, ids);
         }
         GlobalMessage::AdapterRequestDevice(adapter_id, ref desc, id) => {
             use wgn::adapter_request_device as fun;
-            wgn::gfx_select!(adapter_id => fun(adapter_id, desc, id));
+            wgn::gfx_select!(adapter_id => fun(global, adapter_id, desc, Some(id)));
         }
         GlobalMessage::Terminate => return ControlFlow::Terminate,
     }
@@ -37,7 +48,7 @@ fn process(message: GlobalMessage) -> ControlFlow {
 #[no_mangle]
 pub extern "C" fn wgpu_server_process(server: &Server) {
     while let Ok(message) = server.channel.try_recv() {
-        match process(message) {
+        match process(&server.global, message) {
             ControlFlow::Continue => {}
             ControlFlow::Terminate => break,
         }